@@ -1168,7 +1168,8 @@ void msgBox(SessionID sessionId, String type, String title, String text,
     ReconnectHandle? reconnect,
     int? reconnectTimeout,
     VoidCallback? onSubmit,
-    int? submitTimeout}) {
+    int? submitTimeout,
+    String submitText = 'OK'}) {
   dialogManager.dismissAll();
   List<Widget> buttons = [];
   bool hasOk = false;
@@ -1199,13 +1200,13 @@ void msgBox(SessionID sessionId, String type, String title, String text,
     late final Widget btn;
     if (submitTimeout != null) {
       btn = _CountDownButton(
-        text: 'OK',
+        text: submitText,
         second: submitTimeout,
         onPressed: submit,
         submitOnTimeout: true,
       );
     } else {
-      btn = dialogButton('OK', onPressed: submit);
+      btn = dialogButton(submitText, onPressed: submit);
     }
     buttons.insert(0, btn);
   }
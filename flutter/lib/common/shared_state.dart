@@ -349,6 +349,8 @@ initSharedStates(String id) {
   RemoteCursorMovedState.init(id);
   FingerprintState.init(id);
   PeerBoolOption.init(id, kOptionZoomCursor, () => false);
+  PeerBoolOption.init(id, kOptionHideLocalCursor, () => false);
+  PeerStringOption.init(id, kOptionCursorScale, () => '');
   UnreadChatCountState.init(id);
   if (isMobile) ConnectionTypeState.init(id); // desktop in other places
 }
@@ -363,6 +365,8 @@ removeSharedStates(String id) {
   RemoteCursorMovedState.delete(id);
   FingerprintState.delete(id);
   PeerBoolOption.delete(id, kOptionZoomCursor);
+  PeerBoolOption.delete(id, kOptionHideLocalCursor);
+  PeerStringOption.delete(id, kOptionCursorScale);
   UnreadChatCountState.delete(id);
   if (isMobile) ConnectionTypeState.delete(id);
 }
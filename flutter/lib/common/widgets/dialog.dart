@@ -1343,6 +1343,61 @@ void showRestartRemoteDevice(PeerInfo pi, String id, SessionID sessionId,
   if (res == true) bind.sessionRestartRemoteDevice(sessionId: sessionId);
 }
 
+// Copies exchanged in this session, the picked one becomes the clipboard of
+// the remote side.
+void showClipboardHistoryDialog(FFI ffi) {
+  final sessionId = ffi.sessionId;
+  final history = bind.sessionGetClipboardHistory(sessionId: sessionId);
+  final List<dynamic> entries = history.isEmpty ? [] : jsonDecode(history);
+  ffi.dialogManager.show((setState, close, context) {
+    Widget buildEntry(Map<String, dynamic> entry) {
+      final String preview = entry['preview'] ?? '';
+      final List<dynamic> formats = entry['formats'] ?? [];
+      final time = DateTime.fromMillisecondsSinceEpoch(entry['time'] ?? 0);
+      final hh = time.hour.toString().padLeft(2, '0');
+      final mm = time.minute.toString().padLeft(2, '0');
+      return ListTile(
+        dense: true,
+        leading: Icon(entry['remote'] == true
+            ? Icons.download_rounded
+            : Icons.upload_rounded),
+        title: Text(
+          preview.isEmpty ? formats.join(', ') : preview,
+          maxLines: 2,
+          overflow: TextOverflow.ellipsis,
+        ),
+        subtitle: Text('$hh:$mm'),
+        onTap: () {
+          bind.sessionPasteClipboardHistory(
+              sessionId: sessionId, index: entry['index']);
+          close();
+        },
+      );
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Clipboard history')),
+      content: SizedBox(
+        width: 400,
+        child: entries.isEmpty
+            ? Text(translate('Empty'))
+            : SingleChildScrollView(
+                child: Column(
+                  mainAxisSize: MainAxisSize.min,
+                  children: entries
+                      .map((e) => buildEntry(e as Map<String, dynamic>))
+                      .toList(),
+                ),
+              ),
+      ),
+      actions: [
+        dialogButton('Close', onPressed: close, isOutline: true),
+      ],
+      onCancel: close,
+    );
+  });
+}
+
 showSetOSPassword(
   SessionID sessionId,
   bool login,
@@ -1826,6 +1881,59 @@ void editAbPeerNoteDialog(String id) {
   });
 }
 
+void sysinfoDialog(String sysinfo) {
+  Map<String, dynamic> info = {};
+  try {
+    info = jsonDecode(sysinfo);
+  } catch (e) {
+    debugPrint('Failed to decode sysinfo: $e');
+  }
+  final uptime = Duration(seconds: info['uptime'] ?? 0);
+  final rows = <List<String>>[
+    ['Hostname', info['hostname'] ?? ''],
+    ['Username', info['username'] ?? ''],
+    ['OS', info['os'] ?? ''],
+    ['CPU', info['cpu'] ?? ''],
+    ['Memory', info['memory'] ?? ''],
+    for (final disk in (info['disks'] as List<dynamic>? ?? []))
+      [
+        '${translate('Disk')} ${disk['mount']}',
+        '${readableFileSize((disk['available'] ?? 0).toDouble())} / '
+            '${readableFileSize((disk['total'] ?? 0).toDouble())}'
+      ],
+    [
+      'Uptime',
+      '${uptime.inDays}d ${uptime.inHours % 24}h ${uptime.inMinutes % 60}m'
+    ],
+    ['Version', info['version'] ?? ''],
+  ];
+
+  gFFI.dialogManager.show((setState, close, context) {
+    return CustomAlertDialog(
+      title: Text(translate('System information')),
+      content: Column(
+        crossAxisAlignment: CrossAxisAlignment.start,
+        mainAxisSize: MainAxisSize.min,
+        children: rows
+            .where((e) => e[1].isNotEmpty)
+            .map((e) => Row(
+                  crossAxisAlignment: CrossAxisAlignment.start,
+                  children: [
+                    SizedBox(width: 120, child: Text(translate(e[0]))),
+                    Expanded(child: SelectableText(e[1])),
+                  ],
+                ).marginOnly(bottom: 4))
+            .toList(),
+      ),
+      actions: [
+        dialogButton("Close", onPressed: close),
+      ],
+      onSubmit: close,
+      onCancel: close,
+    );
+  });
+}
+
 void renameDialog(
     {required String oldName,
     FormFieldValidator<String>? validator,
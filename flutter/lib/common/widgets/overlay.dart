@@ -606,6 +606,12 @@ class QualityMonitor extends StatelessWidget {
                       _row(
                           "Codec", qualityMonitorModel.data.codecFormat ?? '-'),
                       _row("Chroma", qualityMonitorModel.data.chroma ?? '-'),
+                      _row("Decode",
+                          "${qualityMonitorModel.data.decodeTime ?? '-'}ms"),
+                      _row("Render",
+                          "${qualityMonitorModel.data.renderTime ?? '-'}ms"),
+                      if (qualityMonitorModel.data.relay != null)
+                        _row("Relay", qualityMonitorModel.data.relay!),
                     ],
                   ),
                 )
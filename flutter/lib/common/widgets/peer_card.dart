@@ -752,6 +752,23 @@ abstract class BasePeerCard extends StatelessWidget {
     );
   }
 
+  /// The inventory the peer sent on the last connection, if any.
+  @protected
+  Future<MenuEntryBase<String>?> _sysinfoAction(String id) async {
+    final sysinfo =
+        await bind.mainGetPeerOption(id: id, key: kPeerOptionSysinfo);
+    if (sysinfo.isEmpty) return null;
+    return MenuEntryButton<String>(
+      childBuilder: (TextStyle? style) => Text(
+        translate('System information'),
+        style: style,
+      ),
+      proc: () => sysinfoDialog(sysinfo),
+      padding: menuPadding,
+      dismissOnClicked: true,
+    );
+  }
+
   @protected
   MenuEntryBase<String> _renameAction(String id) {
     return MenuEntryButton<String>(
@@ -992,6 +1009,10 @@ class RecentPeerCard extends BasePeerCard {
     if (isWindows) {
       menuItems.add(_createShortCutAction(peer.id));
     }
+    final sysinfo = await _sysinfoAction(peer.id);
+    if (sysinfo != null) {
+      menuItems.add(sysinfo);
+    }
     menuItems.add(MenuEntryDivider());
     if (isMobile || isDesktop || isWebDesktop) {
       menuItems.add(_renameAction(peer.id));
@@ -1055,6 +1076,10 @@ class FavoritePeerCard extends BasePeerCard {
     if (isWindows) {
       menuItems.add(_createShortCutAction(peer.id));
     }
+    final sysinfo = await _sysinfoAction(peer.id);
+    if (sysinfo != null) {
+      menuItems.add(sysinfo);
+    }
     menuItems.add(MenuEntryDivider());
     if (isMobile || isDesktop || isWebDesktop) {
       menuItems.add(_renameAction(peer.id));
@@ -1174,6 +1199,10 @@ class AddressBookPeerCard extends BasePeerCard {
     if (isWindows) {
       menuItems.add(_createShortCutAction(peer.id));
     }
+    final sysinfo = await _sysinfoAction(peer.id);
+    if (sysinfo != null) {
+      menuItems.add(sysinfo);
+    }
     if (gFFI.abModel.current.canWrite()) {
       menuItems.add(MenuEntryDivider());
       if (isMobile || isDesktop || isWebDesktop) {
@@ -0,0 +1,215 @@
+import 'dart:typed_data';
+import 'dart:ui' as ui;
+
+import 'package:flutter/material.dart';
+import 'package:get/get.dart';
+
+import '../../common.dart';
+import '../../models/platform_model.dart';
+
+const _kAnnotationColors = [
+  Colors.red,
+  Colors.yellow,
+  Colors.green,
+  Colors.blue
+];
+
+/// A pen line or a rectangle, in pixels of the screenshot.
+class _Stroke {
+  final Color color;
+  final bool rect;
+  final List<Offset> points;
+
+  _Stroke(this.color, this.rect, Offset start) : points = [start];
+
+  void paint(Canvas canvas, double width) {
+    final paint = Paint()
+      ..color = color
+      ..strokeWidth = width
+      ..strokeCap = StrokeCap.round
+      ..style = PaintingStyle.stroke;
+    if (rect) {
+      canvas.drawRect(Rect.fromPoints(points.first, points.last), paint);
+    } else {
+      canvas.drawPoints(ui.PointMode.polygon, points, paint);
+    }
+  }
+}
+
+void _paintAnnotated(Canvas canvas, ui.Image image, List<_Stroke> strokes) {
+  canvas.drawImage(image, Offset.zero, Paint());
+  // Lines stay readable on large screens.
+  final width = (image.width / 400).clamp(3.0, 12.0);
+  for (final stroke in strokes) {
+    stroke.paint(canvas, width);
+  }
+}
+
+class _AnnotationPainter extends CustomPainter {
+  final ui.Image image;
+  final List<_Stroke> strokes;
+
+  _AnnotationPainter(this.image, this.strokes);
+
+  @override
+  void paint(Canvas canvas, Size size) {
+    canvas.scale(size.width / image.width);
+    _paintAnnotated(canvas, image, strokes);
+  }
+
+  @override
+  bool shouldRepaint(covariant CustomPainter oldDelegate) => true;
+}
+
+Future<Uint8List?> _render(ui.Image image, List<_Stroke> strokes) async {
+  final recorder = ui.PictureRecorder();
+  _paintAnnotated(Canvas(recorder), image, strokes);
+  final annotated =
+      await recorder.endRecording().toImage(image.width, image.height);
+  final data = await annotated.toByteData(format: ui.ImageByteFormat.png);
+  return data?.buffer.asUint8List();
+}
+
+/// Draws on the cached screenshot, the annotated image replaces it.
+Future<void> annotateScreenshot(
+    SessionID sessionId, OverlayDialogManager dialogManager) async {
+  final png = bind.sessionGetScreenshot(sessionId: sessionId);
+  if (png.isEmpty) return;
+  final image = await decodeImageFromList(png);
+  final strokes = <_Stroke>[];
+  final color = _kAnnotationColors.first.obs;
+  final rect = false.obs;
+  await dialogManager.show<void>((setState, close, context) {
+    submit() async {
+      final annotated = await _render(image, strokes);
+      if (annotated != null) {
+        bind.sessionSetScreenshot(sessionId: sessionId, data: annotated);
+      }
+      close();
+    }
+
+    final maxSize = MediaQuery.of(context).size * 0.75;
+    final scale = [
+      maxSize.width / image.width,
+      maxSize.height / image.height,
+      1.0
+    ].reduce((a, b) => a < b ? a : b);
+    toImage(Offset p) => p / scale;
+
+    return CustomAlertDialog(
+      title: Text(translate('Annotate')),
+      contentBoxConstraints: BoxConstraints(maxWidth: maxSize.width),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: [
+          Obx(() => Row(
+                children: [
+                  ..._kAnnotationColors.map((c) => IconButton(
+                        icon: Icon(
+                            c == color.value
+                                ? Icons.circle
+                                : Icons.circle_outlined,
+                            color: c),
+                        onPressed: () => color.value = c,
+                      )),
+                  IconButton(
+                    icon: Icon(Icons.edit,
+                        color: rect.value ? null : MyTheme.accent),
+                    tooltip: translate('Pen'),
+                    onPressed: () => rect.value = false,
+                  ),
+                  IconButton(
+                    icon: Icon(Icons.crop_square,
+                        color: rect.value ? MyTheme.accent : null),
+                    tooltip: translate('Rectangle'),
+                    onPressed: () => rect.value = true,
+                  ),
+                  IconButton(
+                    icon: Icon(Icons.undo),
+                    tooltip: translate('Undo'),
+                    onPressed: () => setState(() {
+                      if (strokes.isNotEmpty) strokes.removeLast();
+                    }),
+                  ),
+                ],
+              )),
+          GestureDetector(
+            onPanStart: (d) => setState(() => strokes.add(
+                _Stroke(color.value, rect.value, toImage(d.localPosition)))),
+            onPanUpdate: (d) => setState(() {
+              final stroke = strokes.last;
+              if (stroke.rect && stroke.points.length > 1) {
+                stroke.points.removeLast();
+              }
+              stroke.points.add(toImage(d.localPosition));
+            }),
+            child: CustomPaint(
+              size: Size(image.width * scale, image.height * scale),
+              painter: _AnnotationPainter(image, strokes),
+            ),
+          ),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
+/// Lets the user drag a rectangle on the cached screenshot, in its pixels.
+Future<Rect?> selectScreenshotRegion(
+    SessionID sessionId, OverlayDialogManager dialogManager) async {
+  final png = bind.sessionGetScreenshot(sessionId: sessionId);
+  if (png.isEmpty) return null;
+  final image = await decodeImageFromList(png);
+  final strokes = <_Stroke>[];
+  return await dialogManager.show<Rect?>((setState, close, context) {
+    submit() {
+      if (strokes.isEmpty) return;
+      final points = strokes.first.points;
+      final bounds = Offset.zero &
+          Size(image.width.toDouble(), image.height.toDouble());
+      final rect =
+          Rect.fromPoints(points.first, points.last).intersect(bounds);
+      close(rect.isEmpty ? null : rect);
+    }
+
+    final maxSize = MediaQuery.of(context).size * 0.75;
+    final scale = [
+      maxSize.width / image.width,
+      maxSize.height / image.height,
+      1.0
+    ].reduce((a, b) => a < b ? a : b);
+    toImage(Offset p) => p / scale;
+
+    return CustomAlertDialog(
+      title: Text(translate('Select the text to copy')),
+      contentBoxConstraints: BoxConstraints(maxWidth: maxSize.width),
+      content: GestureDetector(
+        onPanStart: (d) => setState(() {
+          strokes.clear();
+          strokes.add(_Stroke(MyTheme.accent, true, toImage(d.localPosition)));
+        }),
+        onPanUpdate: (d) => setState(() {
+          final points = strokes.last.points;
+          if (points.length > 1) points.removeLast();
+          points.add(toImage(d.localPosition));
+        }),
+        child: CustomPaint(
+          size: Size(image.width * scale, image.height * scale),
+          painter: _AnnotationPainter(image, strokes),
+        ),
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('Copy', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
@@ -1,4 +1,3 @@
-import 'dart:async';
 import 'dart:convert';
 
 import 'package:flutter/material.dart';
@@ -147,6 +146,14 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
           }
         }));
   }
+  // clipboard history
+  if (isDefaultConn &&
+      perms['keyboard'] != false &&
+      perms['clipboard'] != false) {
+    v.add(TTextMenu(
+        child: Text(translate('Clipboard history')),
+        onPressed: () => showClipboardHistoryDialog(ffi)));
+  }
   // reset canvas
   if (isDefaultConn && isMobile) {
     v.add(TTextMenu(
@@ -243,7 +250,8 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
   if (isDefaultConn &&
       ffi.ffiModel.keyboard &&
       ffi.ffiModel.permissions['block_input'] != false &&
-      pi.platform == kPeerPlatformWindows) // privacy-mode != true ??
+      (pi.platform == kPeerPlatformWindows ||
+          pi.platform == kPeerPlatformLinux)) // privacy-mode != true ??
   {
     v.add(TTextMenu(
         child: Obx(() => Text(translate(
@@ -297,6 +305,35 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
         ),
         onPressed: () => ffi.recordingModel.toggle()));
   }
+  if (!isWeb && ffi.recordingModel.start) {
+    v.add(TTextMenu(
+        child: Text(translate(ffi.recordingModel.paused
+            ? 'Resume session recording'
+            : 'Pause session recording')),
+        onPressed: () => ffi.recordingModel.togglePause()));
+  }
+  // Which displays a new recording covers. There is no single display to
+  // pick while all of them are shown.
+  if (!isWeb && !ffi.recordingModel.start && pi.displays.length > 1) {
+    final recordDisplays =
+        bind.mainGetPeerOptionSync(id: id, key: kPeerOptionRecordDisplays);
+    if (recordDisplays.isNotEmpty || pi.currentDisplay != kAllDisplayValue) {
+      v.add(TTextMenu(
+          child: Text(translate(recordDisplays.isEmpty
+              ? 'Record only this display'
+              : 'Record all displays')),
+          onPressed: () => bind.sessionPeerOption(
+              sessionId: sessionId,
+              name: kPeerOptionRecordDisplays,
+              value: recordDisplays.isEmpty ? '${pi.currentDisplay}' : '')));
+    }
+  }
+  if (!isWeb &&
+      bind.mainGetLocalOption(key: kOptionReplayBufferSeconds).isNotEmpty) {
+    v.add(TTextMenu(
+        child: Text(translate('Save replay')),
+        onPressed: () => bind.sessionSaveReplay(sessionId: sessionId)));
+  }
 
   // to-do:
   // 1. Web desktop
@@ -311,24 +348,13 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
             : translate('Take screenshot')),
         onPressed: ffi.ffiModel.timerScreenshot != null
             ? null
-            : () {
-                if (pi.currentDisplay == kAllDisplayValue) {
-                  msgBox(
-                      sessionId,
-                      'custom-nook-nocancel-hasclose-info',
-                      'Take screenshot',
-                      'screenshot-merged-screen-not-supported-tip',
-                      '',
-                      ffi.dialogManager);
-                } else {
-                  bind.sessionTakeScreenshot(
-                      sessionId: sessionId, display: pi.currentDisplay);
-                  ffi.ffiModel.timerScreenshot =
-                      Timer(Duration(seconds: 30), () {
-                    ffi.ffiModel.timerScreenshot = null;
-                  });
-                }
-              },
+            : () => ffi.ffiModel.takeScreenshot(),
+      ));
+      v.add(TTextMenu(
+        child: Text(translate('Copy text from screen')),
+        onPressed: ffi.ffiModel.timerScreenshot != null
+            ? null
+            : () => ffi.ffiModel.takeScreenshot(copyText: true),
       ));
     }
   }
@@ -496,6 +522,24 @@ Future<List<TToggleMenu>> toolbarCursor(
               }
             : null));
   }
+  // remote cursor only, pixel accurate but it lags behind the mouse
+  if (pi.platform != kPeerPlatformAndroid &&
+      !ffi.canvasModel.cursorEmbedded &&
+      !pi.isWayland &&
+      ShowRemoteCursorState.find(id).value) {
+    final option = kOptionHideLocalCursor;
+    final peerState = PeerBoolOption.find(id, option);
+    v.add(TToggleMenu(
+      child: Text(translate('Hide local cursor')),
+      value: peerState.value,
+      onChanged: (value) async {
+        if (value == null) return;
+        await bind.sessionToggleOption(sessionId: sessionId, value: option);
+        peerState.value =
+            bind.sessionGetToggleOptionSync(sessionId: sessionId, arg: option);
+      },
+    ));
+  }
   // follow remote cursor
   if (pi.platform != kPeerPlatformAndroid &&
       !ffi.canvasModel.cursorEmbedded &&
@@ -578,6 +622,29 @@ Future<List<TToggleMenu>> toolbarCursor(
   return v;
 }
 
+/// Sizes of the cursor on top of the view style, in percent.
+Future<List<TRadioMenu<String>>> toolbarCursorScale(
+    BuildContext context, String id, FFI ffi) async {
+  if (isMobile || ffi.ffiModel.pi.platform == kPeerPlatformAndroid) return [];
+  final sessionId = ffi.sessionId;
+  final state = PeerStringOption.find(id, kOptionCursorScale);
+  final groupValue = state.value.isEmpty ? '100' : state.value;
+  onChanged(String? value) async {
+    if (value == null) return;
+    await bind.sessionPeerOption(
+        sessionId: sessionId, name: kOptionCursorScale, value: value);
+    state.value = value;
+  }
+
+  return ['100', '150', '200', '300']
+      .map((value) => TRadioMenu<String>(
+          child: Text('$value%'),
+          value: value,
+          groupValue: groupValue,
+          onChanged: onChanged))
+      .toList();
+}
+
 Future<List<TToggleMenu>> toolbarDisplayToggle(
     BuildContext context, String id, FFI ffi) async {
   List<TToggleMenu> v = [];
@@ -597,6 +664,18 @@ Future<List<TToggleMenu>> toolbarDisplayToggle(
         ffi.qualityMonitorModel.checkShowQualityMonitor(sessionId);
       },
       child: Text(translate('Show quality monitor'))));
+  // blend frames in between while the frame rate is low
+  if (!isWeb) {
+    final option = kOptionSmoothLowFps;
+    v.add(TToggleMenu(
+        value:
+            bind.sessionGetToggleOptionSync(sessionId: sessionId, arg: option),
+        onChanged: (value) async {
+          if (value == null) return;
+          await bind.sessionToggleOption(sessionId: sessionId, value: option);
+        },
+        child: Text(translate('Smooth low frame rate'))));
+  }
   // mute
   if (isDefaultConn && perms['audio'] != false) {
     final option = 'disable-audio';
@@ -874,6 +953,17 @@ List<TToggleMenu> toolbarKeyboardToggles(FFI ffi) {
         onChanged: enabled ? onChanged : null,
         child: Text(translate('swap-left-right-mouse'))));
   }
+
+  // relative mouse mode
+  if (isDesktop && ffiModel.keyboard && pi.platform != kPeerPlatformAndroid) {
+    final inputModel = ffi.inputModel;
+    final enabled = !ffi.ffiModel.viewOnly;
+    v.add(TToggleMenu(
+        value: inputModel.relativeMouseMode.value,
+        onChanged: enabled ? (_) => inputModel.toggleRelativeMouseMode() : null,
+        child: Text(
+            '${translate('Relative mouse mode')} (Ctrl+Alt+Shift+M)')));
+  }
   return v;
 }
 
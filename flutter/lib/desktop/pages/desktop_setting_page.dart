@@ -1010,6 +1010,8 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
                 enabled: enabled, fakeValue: fakeValue),
             _OptionCheckBox(context, 'Enable audio', kOptionEnableAudio,
                 enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(context, 'Enable voice call', kOptionEnableVoiceCall,
+                enabled: enabled, fakeValue: fakeValue),
             _OptionCheckBox(context, 'Enable camera', kOptionEnableCamera,
                 enabled: enabled, fakeValue: fakeValue),
             _OptionCheckBox(context, 'Enable terminal', kOptionEnableTerminal,
@@ -1023,13 +1025,19 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
             _OptionCheckBox(
                 context, 'Enable recording session', kOptionEnableRecordSession,
                 enabled: enabled, fakeValue: fakeValue),
-            if (isWindows)
+            if (isWindows || isLinux)
               _OptionCheckBox(context, 'Enable blocking user input',
                   kOptionEnableBlockInput,
                   enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(context, 'Input from one controller at a time',
+                kOptionInputArbitration,
+                enabled: enabled, fakeValue: fakeValue),
             _OptionCheckBox(context, 'Enable remote configuration modification',
                 kOptionAllowRemoteConfigModification,
                 enabled: enabled, fakeValue: fakeValue),
+            // Not a permission, the access mode leaves it as it is.
+            _OptionCheckBox(context, 'Share system information',
+                kOptionAllowShareSysinfo, enabled: enabled),
           ],
         ),
       ]);
@@ -1241,6 +1249,8 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
     return [
       _OptionCheckBox(context, 'Enable direct IP access', kOptionDirectServer,
           update: update, enabled: !locked),
+      _OptionCheckBox(context, 'LAN-only mode', kOptionLanOnly,
+          update: update, enabled: !locked),
       () {
         // Simple temp wrapper for PR check
         tmpWrapper() {
@@ -1703,6 +1713,7 @@ class _DisplayState extends State<_Display> {
       imageQuality(context),
       codec(context),
       if (isDesktop) trackpadSpeed(context),
+      if (isDesktop) keyboardRemap(context),
       if (!isWeb) privacyModeImpl(context),
       other(context),
     ]).marginOnly(bottom: _kListViewBottomMargin);
@@ -1832,6 +1843,32 @@ class _DisplayState extends State<_Display> {
     ]);
   }
 
+  // e.g. "Meta=Control, CapsLock=Escape, RWin=", nothing on the right keeps
+  // the key from the peer.
+  Widget keyboardRemap(BuildContext context) {
+    final controller = TextEditingController(
+        text: bind.mainGetLocalOption(key: kOptionKeyboardRemapRules));
+    final isOptFixed = isOptionFixed(kOptionKeyboardRemapRules);
+    save() => bind.mainSetLocalOption(
+        key: kOptionKeyboardRemapRules, value: controller.text.trim());
+    return _Card(title: 'Key remapping', children: [
+      Focus(
+        onFocusChange: (focused) {
+          if (!focused) save();
+        },
+        child: TextField(
+          controller: controller,
+          enabled: !isOptFixed,
+          decoration: InputDecoration(
+            hintText: 'Meta=Control, CapsLock=Escape, RWin=',
+            contentPadding: EdgeInsets.symmetric(horizontal: 8, vertical: 12),
+          ),
+          onSubmitted: (_) => save(),
+        ).workaroundFreezeLinuxMint(),
+      ).marginOnly(left: _kContentHMargin, right: _kContentHMargin),
+    ]);
+  }
+
   Widget codec(BuildContext context) {
     onChanged(String value) async {
       await bind.mainSetUserDefaultOption(
@@ -814,6 +814,15 @@ class _FileManagerViewState extends State<FileManagerView> {
                           color: Theme.of(context).cardColor,
                           hoverColor: Theme.of(context).hoverColor,
                         )),
+                    if (!isWeb)
+                      MenuButton(
+                        tooltip: translate('Sync folders'),
+                        onPressed: () => _ffi.fileModel.syncFolders(),
+                        child: Icon(Icons.sync_rounded,
+                            color: Theme.of(context).tabBarTheme.labelColor),
+                        color: Theme.of(context).cardColor,
+                        hoverColor: Theme.of(context).hoverColor,
+                      ),
                     menu(isLocal: isLocal),
                   ],
                 ),
@@ -956,6 +965,50 @@ class _FileManagerViewState extends State<FileManagerView> {
     );
   }
 
+  // The other file transfer sessions of this window, the selected remote files
+  // can be relayed to them.
+  List<FFI> relayTargets() {
+    if (!Get.isRegistered<DesktopTabController>()) return [];
+    return Get.find<DesktopTabController>()
+        .state
+        .value
+        .tabs
+        .where((tab) =>
+            tab.key != _ffi.id && Get.isRegistered<FFI>(tag: 'ft_${tab.key}'))
+        .map((tab) => Get.find<FFI>(tag: 'ft_${tab.key}'))
+        .toList();
+  }
+
+  // Packed on the remote side and unpacked into the current local folder, one
+  // job instead of one per file.
+  void downloadArchive(Entry dir) {
+    final local = _ffi.fileModel.localController;
+    bind.sessionDownloadArchive(
+        sessionId: _ffi.sessionId,
+        actId: _ffi.fileModel.jobController.addTransferJob(dir, true),
+        path: dir.path,
+        to: PathUtil.join(local.directory.value.path, dir.name,
+            local.options.value.isWindows),
+        includeHidden: controller.options.value.showHidden);
+    selectedItems.clear();
+  }
+
+  // Into the current remote folder of the target.
+  void relayFiles(FFI target) {
+    final to = target.fileModel.remoteController;
+    for (var item in selectedItems.items) {
+      bind.sessionRelayFiles(
+          sessionId: _ffi.sessionId,
+          actId: JobController.jobID.next(),
+          path: item.path,
+          toSessionId: target.sessionId,
+          to: PathUtil.join(to.directory.value.path, item.name,
+              to.options.value.isWindows),
+          includeHidden: controller.options.value.showHidden);
+    }
+    selectedItems.clear();
+  }
+
   Widget menu({bool isLocal = false}) {
     var menuPos = RelativeRect.fill;
 
@@ -985,6 +1038,25 @@ class _FileManagerViewState extends State<FileManagerView> {
           padding: kDesktopMenuPadding,
           dismissOnClicked: true)
     ];
+    if (!isLocal &&
+        selectedItems.items.length == 1 &&
+        selectedItems.items.first.isDirectory) {
+      items.add(MenuEntryButton(
+          childBuilder: (style) =>
+              Text(translate("Download as archive"), style: style),
+          proc: () => downloadArchive(selectedItems.items.first),
+          padding: kDesktopMenuPadding,
+          dismissOnClicked: true));
+    }
+    if (!isLocal && selectedItems.items.isNotEmpty) {
+      items.addAll(relayTargets().map((target) => MenuEntryButton(
+          childBuilder: (style) => Text(
+              '${translate("Send to")} ${target.id}',
+              style: style),
+          proc: () => relayFiles(target),
+          padding: kDesktopMenuPadding,
+          dismissOnClicked: true)));
+    }
 
     return Listener(
       onPointerDown: (e) {
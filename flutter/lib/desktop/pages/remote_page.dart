@@ -1,5 +1,6 @@
 import 'dart:async';
 
+import 'package:desktop_drop/desktop_drop.dart';
 import 'package:desktop_multi_window/desktop_multi_window.dart';
 import 'package:flutter/material.dart';
 import 'package:flutter/services.dart';
@@ -80,6 +81,8 @@ class _RemotePageState extends State<RemotePage>
   final _cursorOverImage = false.obs;
   late RxBool _showRemoteCursor;
   late RxBool _zoomCursor;
+  late RxBool _hideLocalCursor;
+  late RxString _cursorScale;
   late RxBool _remoteCursorMoved;
   late RxBool _keyboardEnabled;
 
@@ -102,6 +105,8 @@ class _RemotePageState extends State<RemotePage>
 
   void _initStates(String id) {
     _zoomCursor = PeerBoolOption.find(id, kOptionZoomCursor);
+    _hideLocalCursor = PeerBoolOption.find(id, kOptionHideLocalCursor);
+    _cursorScale = PeerStringOption.find(id, kOptionCursorScale);
     _showRemoteCursor = ShowRemoteCursorState.find(id);
     _keyboardEnabled = KeyboardEnabledState.find(id);
     _remoteCursorMoved = RemoteCursorMovedState.find(id);
@@ -149,6 +154,10 @@ class _RemotePageState extends State<RemotePage>
           sessionId: sessionId, arg: 'show-remote-cursor');
       _zoomCursor.value = bind.sessionGetToggleOptionSync(
           sessionId: sessionId, arg: kOptionZoomCursor);
+      _hideLocalCursor.value = bind.sessionGetToggleOptionSync(
+          sessionId: sessionId, arg: kOptionHideLocalCursor);
+      _cursorScale.value =
+          bind.mainGetPeerOptionSync(id: widget.id, key: kOptionCursorScale);
     });
     DesktopMultiWindow.addListener(this);
     // if (!_isCustomCursorInited) {
@@ -327,7 +336,18 @@ class _RemotePageState extends State<RemotePage>
                     }
                   },
                   inputModel: _ffi.inputModel,
-                  child: getBodyForDesktop(context))),
+                  // Dropped files are uploaded to the drop folder of the
+                  // remote side.
+                  child: DropTarget(
+                      onDragDone: (detail) {
+                        if (_ffi.ffiModel.permissions['file'] == false) {
+                          return;
+                        }
+                        bind.sessionDropFiles(
+                            sessionId: sessionId,
+                            paths: detail.files.map((e) => e.path).toList());
+                      },
+                      child: getBodyForDesktop(context)))),
           Stack(
             children: [
               _ffi.ffiModel.pi.isSet.isTrue &&
@@ -501,6 +521,7 @@ class _RemotePageState extends State<RemotePage>
       }, child: LayoutBuilder(builder: (context, constraints) {
         final c = Provider.of<CanvasModel>(context, listen: false);
         Future.delayed(Duration.zero, () => c.updateViewStyle());
+        c.tryFitResolutionToWindow();
         final peerDisplay = CurrentDisplayState.find(widget.id);
         return Obx(
           () => _ffi.ffiModel.pi.isSet.isFalse
@@ -511,6 +532,8 @@ class _RemotePageState extends State<RemotePage>
                   return ImagePaint(
                     id: widget.id,
                     zoomCursor: _zoomCursor,
+                    cursorScale: _cursorScale,
+                    remoteCursorOnly: _remoteCursorOnly,
                     cursorOverImage: _cursorOverImage,
                     keyboardEnabled: _keyboardEnabled,
                     remoteCursorMoved: _remoteCursorMoved,
@@ -525,13 +548,16 @@ class _RemotePageState extends State<RemotePage>
 
     if (!_ffi.canvasModel.cursorEmbedded) {
       paints
-          .add(Obx(() => _showRemoteCursor.isFalse || _remoteCursorMoved.isFalse
+          .add(Obx(() => _showRemoteCursor.isFalse ||
+                  (_remoteCursorMoved.isFalse && !_remoteCursorOnly())
               ? Offstage()
               : CursorPaint(
                   id: widget.id,
                   zoomCursor: _zoomCursor,
+                  cursorScale: _cursorScale,
                 )));
     }
+    paints.add(InkPaint(ffi: _ffi));
     paints.add(
       Positioned(
         top: 10,
@@ -545,14 +571,23 @@ class _RemotePageState extends State<RemotePage>
     );
   }
 
+  // The remote cursor is drawn in place of the local one.
+  bool _remoteCursorOnly() =>
+      _showRemoteCursor.isTrue && _hideLocalCursor.isTrue;
+
   @override
   bool get wantKeepAlive => true;
 }
 
+double _cursorScaleFactor(RxString cursorScale) =>
+    (int.tryParse(cursorScale.value) ?? 100) / 100;
+
 class ImagePaint extends StatefulWidget {
   final FFI ffi;
   final String id;
   final RxBool zoomCursor;
+  final RxString cursorScale;
+  final bool Function() remoteCursorOnly;
   final RxBool cursorOverImage;
   final RxBool keyboardEnabled;
   final RxBool remoteCursorMoved;
@@ -563,6 +598,8 @@ class ImagePaint extends StatefulWidget {
       required this.ffi,
       required this.id,
       required this.zoomCursor,
+      required this.cursorScale,
+      required this.remoteCursorOnly,
       required this.cursorOverImage,
       required this.keyboardEnabled,
       required this.remoteCursorMoved,
@@ -606,7 +643,7 @@ class _ImagePaintState extends State<ImagePaint> {
                 cursorScale = s;
               }
             }
-            return cursorScale;
+            return cursorScale * _cursorScaleFactor(widget.cursorScale);
           }
 
           return MouseRegion(
@@ -615,7 +652,8 @@ class _ImagePaintState extends State<ImagePaint> {
                       ? SystemMouseCursors.none
                       : keyboardEnabled.isTrue
                           ? (() {
-                              if (remoteCursorMoved.isTrue) {
+                              if (remoteCursorMoved.isTrue ||
+                                  widget.remoteCursorOnly()) {
                                 _lastRemoteCursorMoved = true;
                                 return SystemMouseCursors.none;
                               } else {
@@ -832,14 +870,37 @@ class _ImagePaintState extends State<ImagePaint> {
   }
 }
 
+// Where the image of the current display is painted, scrolled into view in
+// the original view style.
+Offset? _imageOrigin(CanvasModel c) {
+  double cx = c.x;
+  double cy = c.y;
+  if (c.viewStyle.style == kRemoteViewStyleOriginal &&
+      c.scrollStyle == ScrollStyle.scrollbar) {
+    final rect = c.parent.target!.ffiModel.rect;
+    if (rect == null) return null;
+    if (cx < 0) {
+      final imageWidth = rect.width * c.scale;
+      cx = -imageWidth * c.scrollX;
+    }
+    if (cy < 0) {
+      final imageHeight = rect.height * c.scale;
+      cy = -imageHeight * c.scrollY;
+    }
+  }
+  return Offset(cx, cy);
+}
+
 class CursorPaint extends StatelessWidget {
   final String id;
   final RxBool zoomCursor;
+  final RxString cursorScale;
 
   const CursorPaint({
     Key? key,
     required this.id,
     required this.zoomCursor,
+    required this.cursorScale,
   }) : super(key: key);
 
   @override
@@ -855,35 +916,24 @@ class CursorPaint extends StatelessWidget {
       }
     }
 
-    double cx = c.x;
-    double cy = c.y;
-    if (c.viewStyle.style == kRemoteViewStyleOriginal &&
-        c.scrollStyle == ScrollStyle.scrollbar) {
-      final rect = c.parent.target!.ffiModel.rect;
-      if (rect == null) {
-        // unreachable!
-        debugPrint('unreachable! The displays rect is null.');
-        return Container();
-      }
-      if (cx < 0) {
-        final imageWidth = rect.width * c.scale;
-        cx = -imageWidth * c.scrollX;
-      }
-      if (cy < 0) {
-        final imageHeight = rect.height * c.scale;
-        cy = -imageHeight * c.scrollY;
-      }
+    final origin = _imageOrigin(c);
+    if (origin == null) {
+      // unreachable!
+      debugPrint('unreachable! The displays rect is null.');
+      return Container();
     }
+    final cx = origin.dx;
+    final cy = origin.dy;
 
-    double x = (m.x - hotx) * c.scale + cx;
-    double y = (m.y - hoty) * c.scale + cy;
     double scale = 1.0;
     final isViewOriginal = c.viewStyle.style == kRemoteViewStyleOriginal;
     if (zoomCursor.value || isViewOriginal) {
-      x = m.x - hotx + cx / c.scale;
-      y = m.y - hoty + cy / c.scale;
       scale = c.scale;
     }
+    scale *= _cursorScaleFactor(cursorScale);
+    // Keep the hotspot on the remote position whatever the scale.
+    double x = (m.x * c.scale + cx) / scale - hotx;
+    double y = (m.y * c.scale + cy) / scale - hoty;
 
     return CustomPaint(
       painter: ImagePainter(
@@ -895,3 +945,80 @@ class CursorPaint extends StatelessWidget {
     );
   }
 }
+
+/// The marks drawn on the peer in view only mode, see [InkModel].
+class InkPaint extends StatelessWidget {
+  final FFI ffi;
+
+  const InkPaint({Key? key, required this.ffi}) : super(key: key);
+
+  @override
+  Widget build(BuildContext context) {
+    final c = Provider.of<CanvasModel>(context);
+    return IgnorePointer(
+      child: AnimatedBuilder(
+        animation: ffi.inkModel,
+        builder: (context, _) {
+          final rect = ffi.ffiModel.rect;
+          final origin = _imageOrigin(c);
+          if (rect == null || origin == null || ffi.inkModel.marks.isEmpty) {
+            return Offstage();
+          }
+          return CustomPaint(
+            size: Size.infinite,
+            painter: _InkPainter(
+              model: ffi.inkModel,
+              toScreen: (p) => (p - rect.topLeft) * c.scale + origin,
+            ),
+          );
+        },
+      ),
+    );
+  }
+}
+
+class _InkPainter extends CustomPainter {
+  static const _arrowHeadLength = 18.0;
+  static const _arrowHeadAngle = 0.5;
+  final InkModel model;
+  final Offset Function(Offset) toScreen;
+
+  _InkPainter({required this.model, required this.toScreen});
+
+  @override
+  void paint(Canvas canvas, Size size) {
+    for (final mark in model.marks) {
+      if (mark.points.isEmpty) continue;
+      final paint = Paint()
+        ..color = MyTheme.accent.withOpacity(model.alpha(mark))
+        ..style = PaintingStyle.stroke
+        ..strokeWidth = 3
+        ..strokeCap = StrokeCap.round
+        ..strokeJoin = StrokeJoin.round;
+      final points = mark.points.map(toScreen).toList();
+      final start = points.first;
+      final end = points.last;
+      switch (mark.shape) {
+        case InkShape.free:
+          canvas.drawPath(Path()..addPolygon(points, false), paint);
+          break;
+        case InkShape.arrow:
+          final angle = (start - end).direction;
+          canvas.drawLine(start, end, paint);
+          for (final side in [-_arrowHeadAngle, _arrowHeadAngle]) {
+            canvas.drawLine(
+                end,
+                end + Offset.fromDirection(angle + side, _arrowHeadLength),
+                paint);
+          }
+          break;
+        case InkShape.circle:
+          canvas.drawCircle(start, (end - start).distance, paint);
+          break;
+      }
+    }
+  }
+
+  @override
+  bool shouldRepaint(covariant CustomPainter oldDelegate) => true;
+}
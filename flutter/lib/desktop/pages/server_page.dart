@@ -695,17 +695,34 @@ class _PrivilegeBoardState extends State<_PrivilegeBoard> {
                       buildPermissionIcon(
                         client.keyboard,
                         Icons.keyboard,
-                        (enabled) {
-                          bind.cmSwitchPermission(
-                              connId: client.id,
-                              name: "keyboard",
-                              enabled: enabled);
-                          setState(() {
-                            client.keyboard = enabled;
-                          });
-                        },
+                        client.viewOnlyForced
+                            ? null
+                            : (enabled) {
+                                bind.cmSwitchPermission(
+                                    connId: client.id,
+                                    name: "keyboard",
+                                    enabled: enabled);
+                                setState(() {
+                                  client.keyboard = enabled;
+                                });
+                              },
                         translate('Enable keyboard/mouse'),
                       ),
+                      // can't be turned off until the connection is closed
+                      buildPermissionIcon(
+                        client.viewOnlyForced,
+                        Icons.visibility_rounded,
+                        client.viewOnlyForced
+                            ? null
+                            : (_) {
+                                bind.cmForceViewOnly(connId: client.id);
+                                setState(() {
+                                  client.viewOnlyForced = true;
+                                  client.keyboard = false;
+                                });
+                              },
+                        translate('Force view-only'),
+                      ),
                       buildPermissionIcon(
                         client.clipboard,
                         Icons.assignment_rounded,
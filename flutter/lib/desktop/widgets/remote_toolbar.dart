@@ -955,6 +955,7 @@ class _DisplayMenuState extends State<_DisplayMenu> {
             child: Text(translate("Virtual display")),
           ),
         if (ffi.connType == ConnType.defaultConn) cursorToggles(),
+        if (ffi.connType == ConnType.defaultConn) cursorScale(),
         Divider(),
         toggles(),
       ];
@@ -1204,6 +1205,26 @@ class _DisplayMenuState extends State<_DisplayMenu> {
         });
   }
 
+  cursorScale() {
+    return futureBuilder(
+        future: toolbarCursorScale(context, id, ffi),
+        hasData: (data) {
+          final v = data as List<TRadioMenu<String>>;
+          if (v.isEmpty) return Offstage();
+          return _SubmenuButton(
+              ffi: widget.ffi,
+              child: Text(translate('Cursor size')),
+              menuChildren: v
+                  .map((e) => RdoMenuButton(
+                      value: e.value,
+                      groupValue: e.groupValue,
+                      onChanged: e.onChanged,
+                      child: e.child,
+                      ffi: ffi))
+                  .toList());
+        });
+  }
+
   toggles() {
     return futureBuilder(
         future: toolbarDisplayToggle(context, id, ffi),
@@ -1468,6 +1489,7 @@ class _ResolutionsMenuState extends State<_ResolutionsMenu> {
             _OriginalResolutionMenuButton(context, showOriginalBtn),
             _FitLocalResolutionMenuButton(context, showFitLocalBtn),
             _customResolutionMenuButton(context, isVirtualDisplay),
+            _fitWindowMenuButton(),
             _menuDivider(showOriginalBtn, showFitLocalBtn, isVirtualDisplay),
           ] +
           _supportedResolutionMenuButtons(),
@@ -1627,6 +1649,21 @@ class _ResolutionsMenuState extends State<_ResolutionsMenu> {
     );
   }
 
+  Widget _fitWindowMenuButton() {
+    return CkbMenuButton(
+      value: bind.sessionGetToggleOptionSync(
+          sessionId: ffi.sessionId, arg: kOptionFitResolutionToWindow),
+      onChanged: (value) async {
+        if (value == null) return;
+        await bind.sessionToggleOption(
+            sessionId: ffi.sessionId, value: kOptionFitResolutionToWindow);
+        if (value) ffi.canvasModel.tryFitResolutionToWindow();
+      },
+      ffi: ffi,
+      child: Text(translate('Fit resolution to window')),
+    );
+  }
+
   Widget _customResolutionMenuButton(BuildContext context, isVirtualDisplay) {
     return Offstage(
       offstage: !isVirtualDisplay,
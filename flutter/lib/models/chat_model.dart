@@ -1,4 +1,5 @@
 import 'dart:async';
+import 'dart:convert';
 
 import 'package:dash_chat_2/dash_chat_2.dart';
 import 'package:desktop_multi_window/desktop_multi_window.dart';
@@ -334,6 +335,7 @@ class ChatModel with ChangeNotifier {
         firstName: peerName,
       );
       _messages[key] = MessageBody(chatUser, []);
+      _loadHistory(key);
     } else {
       if (peerName != null && peerName.isNotEmpty) {
         _messages[key]?.chatUser.firstName = peerName;
@@ -462,8 +464,62 @@ class ChatModel with ChangeNotifier {
     updateConnIdOfKey(key);
     if (!_messages.containsKey(key)) {
       _messages[key] = MessageBody(message.user, []);
+      _loadHistory(key);
     }
     _messages[key]?.insert(message);
+    _saveHistory(key);
+  }
+
+  // The chat with a controlled peer is kept in its peer options,
+  // `chatMessages` is newest first.
+  static const _maxHistory = 100;
+  // Not saved before it is loaded, or the history would be overwritten.
+  final Set<MessageKey> _historyLoaded = {};
+
+  _loadHistory(MessageKey key) async {
+    if (!key.isOut || parent.target == null) return;
+    final history = await bind.sessionGetPeerOption(
+        sessionId: sessionId, name: kPeerOptionChatHistory);
+    final body = _messages[key];
+    if (body == null) return;
+    final peer = ChatUser(
+        id: key.peerId, firstName: parent.target?.ffiModel.pi.username);
+    if (history.isNotEmpty) {
+      try {
+        final loaded = (jsonDecode(history) as List<dynamic>)
+            .map((m) => ChatMessage(
+                  text: m['text'],
+                  user: m['out'] == true ? me : peer,
+                  createdAt: DateTime.fromMillisecondsSinceEpoch(m['ts']),
+                ))
+            .toList();
+        // Messages sent or received while loading are newer, they stay first.
+        body.chatMessages.addAll(loaded);
+      } catch (e) {
+        debugPrint('Failed to load chat history: $e');
+      }
+    }
+    _historyLoaded.add(key);
+    // The messages that came in while loading were not saved yet.
+    _saveHistory(key);
+    notifyListeners();
+  }
+
+  _saveHistory(MessageKey key) {
+    final body = _messages[key];
+    if (!_historyLoaded.contains(key) || body == null) return;
+    final history = body.chatMessages
+        .take(_maxHistory)
+        .map((m) => {
+              'out': m.user == me,
+              'text': m.text,
+              'ts': m.createdAt.millisecondsSinceEpoch,
+            })
+        .toList();
+    bind.sessionPeerOption(
+        sessionId: sessionId,
+        name: kPeerOptionChatHistory,
+        value: jsonEncode(history));
   }
 
   updateConnIdOfKey(MessageKey key) {
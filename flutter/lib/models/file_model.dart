@@ -113,6 +113,50 @@ class FileModel {
     fileFetcher.tryCompleteEmptyDirsTask(evt['value'], evt['is_local']);
   }
 
+  /// Two-way sync of the current local and remote folders, the differences
+  /// are copied as transfer jobs of their own.
+  void syncFolders() {
+    bind.sessionSyncFolders(
+        sessionId: sessionId,
+        actId: JobController.jobID.next(),
+        local: localController.directory.value.path,
+        remote: remoteController.directory.value.path,
+        includeHidden: remoteController.options.value.showHidden);
+  }
+
+  void receiveFolderSync(Map<String, dynamic> evt) {
+    final Map<String, dynamic> plan = jsonDecode(evt['plan'] ?? '{}');
+    final List<dynamic> upload = plan['upload'] ?? [];
+    final List<dynamic> download = plan['download'] ?? [];
+    final List<dynamic> conflicts = plan['conflicts'] ?? [];
+    parent.target?.dialogManager.show((setState, close, context) {
+      return CustomAlertDialog(
+        title: Text(translate('Sync folders')),
+        content: SizedBox(
+          width: 400,
+          child: SingleChildScrollView(
+            child: Column(
+              mainAxisSize: MainAxisSize.min,
+              crossAxisAlignment: CrossAxisAlignment.start,
+              children: [
+                Text('${translate('Upload')}: ${upload.length}'),
+                Text('${translate('Download')}: ${download.length}'),
+                if (conflicts.isNotEmpty)
+                  Text(translate('Changed on both sides, not copied:'))
+                      .paddingOnly(top: 10),
+                ...conflicts.map((c) => Text(c['name'] ?? '',
+                    style: TextStyle(color: Colors.red))),
+              ],
+            ),
+          ),
+        ),
+        actions: [dialogButton('OK', onPressed: close)],
+        onSubmit: close,
+        onCancel: close,
+      );
+    });
+  }
+
   Future<void> postOverrideFileConfirm(Map<String, dynamic> evt) async {
     evtLoop.pushEvent(
         _FileDialogEvent(WeakReference(this), FileDialogType.overwrite, evt));
@@ -357,6 +357,9 @@ class InputModel {
   final isPhysicalMouse = false.obs;
   int _lastButtons = 0;
   Offset lastMousePos = Offset.zero;
+  // The peer gets movements instead of positions, toggled by Ctrl+Alt+Shift+M.
+  final relativeMouseMode = false.obs;
+  var _relativeMoveUnsent = Offset.zero;
 
   bool _queryOtherWindowCoords = false;
   Rect? _windowRect;
@@ -496,8 +499,30 @@ class InputModel {
   }
 
   KeyEventResult handleRawKeyEvent(RawKeyEvent e) {
+    if (e is RawKeyDownEvent &&
+        !e.repeat &&
+        !isViewCamera &&
+        _isScreenshotHotkey(e.logicalKey, e.isControlPressed, e.isAltPressed,
+            e.isShiftPressed)) {
+      parent.target?.ffiModel.takeScreenshot();
+      return KeyEventResult.handled;
+    }
+    if (e is RawKeyDownEvent &&
+        !e.repeat &&
+        _isSaveReplayHotkey(e.logicalKey, e.isControlPressed, e.isAltPressed,
+            e.isShiftPressed)) {
+      bind.sessionSaveReplay(sessionId: sessionId);
+      return KeyEventResult.handled;
+    }
     if (isViewOnly) return KeyEventResult.handled;
     if (isViewCamera) return KeyEventResult.handled;
+    if (e is RawKeyDownEvent &&
+        !e.repeat &&
+        _isRelativeMouseModeHotkey(e.logicalKey, e.isControlPressed,
+            e.isAltPressed, e.isShiftPressed)) {
+      toggleRelativeMouseMode();
+      return KeyEventResult.handled;
+    }
     if (!isInputSourceFlutter) {
       if (isDesktop) {
         return KeyEventResult.handled;
@@ -551,8 +576,28 @@ class InputModel {
   }
 
   KeyEventResult handleKeyEvent(KeyEvent e) {
+    final keyboard = HardwareKeyboard.instance;
+    if (e is KeyDownEvent &&
+        !isViewCamera &&
+        _isScreenshotHotkey(e.logicalKey, keyboard.isControlPressed,
+            keyboard.isAltPressed, keyboard.isShiftPressed)) {
+      parent.target?.ffiModel.takeScreenshot();
+      return KeyEventResult.handled;
+    }
+    if (e is KeyDownEvent &&
+        _isSaveReplayHotkey(e.logicalKey, keyboard.isControlPressed,
+            keyboard.isAltPressed, keyboard.isShiftPressed)) {
+      bind.sessionSaveReplay(sessionId: sessionId);
+      return KeyEventResult.handled;
+    }
     if (isViewOnly) return KeyEventResult.handled;
     if (isViewCamera) return KeyEventResult.handled;
+    if (e is KeyDownEvent &&
+        _isRelativeMouseModeHotkey(e.logicalKey, keyboard.isControlPressed,
+            keyboard.isAltPressed, keyboard.isShiftPressed)) {
+      toggleRelativeMouseMode();
+      return KeyEventResult.handled;
+    }
     if (!isInputSourceFlutter) {
       if (isDesktop) {
         return KeyEventResult.handled;
@@ -878,6 +923,49 @@ class InputModel {
         msg: json.encode(modify({'x': '$x2', 'y': '$y2'})));
   }
 
+  void toggleRelativeMouseMode() {
+    relativeMouseMode.value = !relativeMouseMode.value;
+    _relativeMoveUnsent = Offset.zero;
+  }
+
+  bool _isRelativeMouseModeHotkey(
+          LogicalKeyboardKey key, bool ctrl, bool alt, bool shift) =>
+      key == LogicalKeyboardKey.keyM && ctrl && alt && shift;
+
+  bool _isScreenshotHotkey(
+          LogicalKeyboardKey key, bool ctrl, bool alt, bool shift) =>
+      isDesktop && key == LogicalKeyboardKey.keyS && ctrl && alt && shift;
+
+  // Only taken while the replay buffer is on, the keys reach the peer otherwise.
+  bool _isSaveReplayHotkey(
+          LogicalKeyboardKey key, bool ctrl, bool alt, bool shift) =>
+      isDesktop &&
+      key == LogicalKeyboardKey.keyR &&
+      ctrl &&
+      alt &&
+      shift &&
+      bind.mainGetLocalOption(key: kOptionReplayBufferSeconds).isNotEmpty;
+
+  /// Sends the movement of [e] in pixels of the peer, false if it is not a plain move.
+  bool _tryMoveRelative(PointerEvent e) {
+    if (!relativeMouseMode.value || !keyboardPerm || isViewOnly) return false;
+    if (isViewCamera) return false;
+    if (e.buttons != _lastButtons) return false;
+    final scale = parent.target?.canvasModel.scale ?? 1.0;
+    if (scale <= 0) return false;
+    _relativeMoveUnsent += e.delta / scale;
+    final x = _relativeMoveUnsent.dx.truncate();
+    final y = _relativeMoveUnsent.dy.truncate();
+    _relativeMoveUnsent -= Offset(x.toDouble(), y.toDouble());
+    if (x != 0 || y != 0) {
+      bind.sessionSendMouse(
+          sessionId: sessionId,
+          msg: json.encode(
+              modify({'type': 'move_relative', 'x': '$x', 'y': '$y'})));
+    }
+    return true;
+  }
+
   void onPointHoverImage(PointerHoverEvent e) {
     _stopFling = true;
     if (isViewOnly && !showMyCursor) return;
@@ -886,6 +974,7 @@ class InputModel {
       isPhysicalMouse.value = true;
     }
     if (isPhysicalMouse.value) {
+      if (_tryMoveRelative(e)) return;
       handleMouse(_getMouseEvent(e, _kMouseEventMove), e.position, edgeScroll: useEdgeScroll);
     }
   }
@@ -1074,6 +1163,7 @@ class InputModel {
       _queryOtherWindowCoords = false;
     }
     if (isPhysicalMouse.value) {
+      if (_tryMoveRelative(e)) return;
       handleMouse(_getMouseEvent(e, _kMouseEventMove), e.position, edgeScroll: useEdgeScroll);
     }
   }
@@ -1306,12 +1396,28 @@ class InputModel {
     final evtToPeer =
         processEventToPeer(evt, offset, onExit: onExit, moveCanvas: moveCanvas, edgeScroll: edgeScroll);
     if (evtToPeer != null) {
+      if (isViewOnly && showMyCursor) _addInk(evtToPeer);
       bind.sessionSendMouse(
           sessionId: sessionId, msg: json.encode(modify(evtToPeer)));
     }
     return evtToPeer;
   }
 
+  // Left drags draw on the peer, mirror them so the controlling side sees the
+  // marks too. Shift draws an arrow and ctrl a circle.
+  void _addInk(Map<String, dynamic> evt) {
+    if (evt['buttons'] != 'left') return;
+    final start = evt['type'] == kMouseEventTypeDown;
+    if (!start && evt['type'] != kMouseEventTypeDefault) return;
+    final shape = shift
+        ? InkShape.arrow
+        : ctrl
+            ? InkShape.circle
+            : InkShape.free;
+    parent.target?.inkModel.add(double.parse(evt['x']),
+        double.parse(evt['y']), shape, start);
+  }
+
   Point? handlePointerDevicePos(
     String kind,
     double x,
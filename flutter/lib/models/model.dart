@@ -42,6 +42,7 @@ import 'package:vector_math/vector_math.dart' show Vector2;
 import '../common.dart';
 import '../utils/image.dart' as img;
 import '../common/widgets/dialog.dart';
+import '../common/widgets/screenshot_annotation.dart';
 import 'input_model.dart';
 import 'platform_model.dart';
 import 'package:flutter_hbb/utils/scale.dart';
@@ -130,6 +131,8 @@ class FfiModel with ChangeNotifier {
   bool isRefreshing = false;
 
   Timer? timerScreenshot;
+  // Copy the text of a region instead of asking what to do with the screenshot.
+  bool _screenshotCopyText = false;
 
   Rect? get rect => _rect;
   bool get isOriginalResolutionSet =>
@@ -346,6 +349,8 @@ class FfiModel with ChangeNotifier {
         parent.target?.fileModel.receiveFileDir(evt);
       } else if (name == 'empty_dirs') {
         parent.target?.fileModel.receiveEmptyDirs(evt);
+      } else if (name == 'folder_sync') {
+        parent.target?.fileModel.receiveFolderSync(evt);
       } else if (name == 'job_progress') {
         parent.target?.fileModel.jobController.tryUpdateJobProgress(evt);
       } else if (name == 'job_done') {
@@ -377,6 +382,8 @@ class FfiModel with ChangeNotifier {
       } else if (name == 'show_elevation') {
         final show = evt['show'].toString() == 'true';
         parent.target?.serverModel.setShowElevation(show);
+      } else if (name == 'show_connection') {
+        parent.target?.serverModel.showConnection(evt);
       } else if (name == 'cancel_msgbox') {
         cancelMsgBox(evt, sessionId);
       } else if (name == 'switch_back') {
@@ -457,6 +464,29 @@ class FfiModel with ChangeNotifier {
     };
   }
 
+  /// Asks the peer for a lossless capture of the current display.
+  void takeScreenshot({bool copyText = false}) {
+    if (timerScreenshot != null) return;
+    final ffi = parent.target;
+    if (ffi == null) return;
+    if (_pi.currentDisplay == kAllDisplayValue) {
+      msgBox(
+          sessionId,
+          'custom-nook-nocancel-hasclose-info',
+          'Take screenshot',
+          'screenshot-merged-screen-not-supported-tip',
+          '',
+          ffi.dialogManager);
+    } else {
+      _screenshotCopyText = copyText;
+      bind.sessionTakeScreenshot(
+          sessionId: sessionId, display: _pi.currentDisplay);
+      timerScreenshot = Timer(Duration(seconds: 30), () {
+        timerScreenshot = null;
+      });
+    }
+  }
+
   _handleScreenshot(
       Map<String, dynamic> evt, SessionID sessionId, String peerId) {
     timerScreenshot?.cancel();
@@ -465,8 +495,12 @@ class FfiModel with ChangeNotifier {
     final msgBoxType = 'custom-nook-nocancel-hasclose';
     final msgBoxTitle = 'Take screenshot';
     final dialogManager = parent.target!.dialogManager;
+    final copyText = _screenshotCopyText;
+    _screenshotCopyText = false;
     if (msg.isNotEmpty) {
       msgBox(sessionId, msgBoxType, msgBoxTitle, msg, '', dialogManager);
+    } else if (copyText) {
+      _copyScreenshotText(sessionId, dialogManager);
     } else {
       final msgBoxText = 'screenshot-action-tip';
 
@@ -507,7 +541,16 @@ class FfiModel with ChangeNotifier {
         close();
       }
 
+      annotate() {
+        close();
+        Future.delayed(Duration.zero, () async {
+          await annotateScreenshot(sessionId, dialogManager);
+          _handleScreenshot(evt, sessionId, peerId);
+        });
+      }
+
       final List<Widget> buttons = [
+        dialogButton('Annotate', onPressed: annotate, isOutline: true),
         dialogButton('${translate('Save as')}...', onPressed: saveAs),
         dialogButton('Copy to clipboard', onPressed: copyToClipboard),
         dialogButton('Cancel', onPressed: cancel),
@@ -525,6 +568,26 @@ class FfiModel with ChangeNotifier {
     }
   }
 
+  _copyScreenshotText(
+      SessionID sessionId, OverlayDialogManager dialogManager) async {
+    final rect = await selectScreenshotRegion(sessionId, dialogManager);
+    if (rect == null) {
+      bind.sessionHandleScreenshot(sessionId: sessionId, action: '2');
+      return;
+    }
+    final region = [rect.left, rect.top, rect.width, rect.height]
+        .map((v) => v.round())
+        .join(',');
+    final res = await bind.sessionHandleScreenshot(
+        sessionId: sessionId, action: '3:$region');
+    if (res.isNotEmpty) {
+      msgBox(sessionId, 'custom-nook-nocancel-hasclose-error',
+          'Copy text from screen', res, '', dialogManager);
+    } else {
+      showToast(translate('Copied'));
+    }
+  }
+
   _handlePrinterRequest(
       Map<String, dynamic> evt, SessionID sessionId, String peerId) {
     final id = evt['id'];
@@ -875,8 +938,18 @@ class FfiModel with ChangeNotifier {
       enterUserLoginAndPasswordDialog(
           sessionId, dialogManager, 'terminal-admin-login-tip', false);
     } else if (type == 'restarting') {
-      showMsgBox(sessionId, type, title, text, link, false, dialogManager,
-          hasCancel: false);
+      // Give the peer time to come back, a failed attempt then keeps
+      // retrying until it has registered with the rendezvous server again.
+      _timer?.cancel();
+      _reconnects = 1;
+      msgBox(sessionId, type, title, text, link, dialogManager,
+          hasCancel: true,
+          onSubmit: () => reconnect(dialogManager, sessionId, false),
+          submitTimeout: 60,
+          submitText: 'Reconnect');
+    } else if (type == 'connecting') {
+      dialogManager.dismissAll();
+      dialogManager.showLoading(translate(text), onCancel: closeConnection);
     } else if (type == 'wait-remote-accept-nook') {
       showWaitAcceptDialog(sessionId, type, title, text, dialogManager);
     } else if (type == 'on-uac' || type == 'on-foreground-elevated') {
@@ -1093,9 +1166,14 @@ class FfiModel with ChangeNotifier {
       _pi.primaryDisplay = currentDisplay;
     }
 
+    // A reconnect starts on the display of the peer, go back to the one in use.
+    int? restoreDisplay;
     if (bind.peerGetSessionsCount(
             id: peerId, connType: parent.target!.connType.index) <=
         1) {
+      if (_pi.displays.isNotEmpty && _pi.currentDisplay != currentDisplay) {
+        restoreDisplay = _pi.currentDisplay;
+      }
       _pi.currentDisplay = currentDisplay;
     }
 
@@ -1148,6 +1226,13 @@ class FfiModel with ChangeNotifier {
         // now replaced to _updateCurDisplay
         updateCurDisplay(sessionId);
       }
+      if (connType == ConnType.defaultConn &&
+          restoreDisplay != null &&
+          (restoreDisplay == kAllDisplayValue ||
+              restoreDisplay < _pi.displays.length)) {
+        openMonitorInTheSameTab(restoreDisplay, parent.target!, _pi,
+            updateCursorPos: false);
+      }
       if (displays.isNotEmpty) {
         _reconnects = 1;
         waitForFirstImage.value = true;
@@ -2007,6 +2092,54 @@ class CanvasModel with ChangeNotifier {
 
   updateSize() => _size = getSize();
 
+  Timer? _fitResolutionTimer;
+
+  // Asks the peer for the resolution of the view once the window stops resizing.
+  tryFitResolutionToWindow() {
+    _fitResolutionTimer?.cancel();
+    if (!bind.sessionGetToggleOptionSync(
+        sessionId: sessionId, arg: kOptionFitResolutionToWindow)) {
+      return;
+    }
+    _fitResolutionTimer = Timer(const Duration(seconds: 1), () {
+      final ffiModel = parent.target?.ffiModel;
+      final pi = ffiModel?.pi;
+      final rect = ffiModel?.rect;
+      if (ffiModel == null || pi == null || rect == null) return;
+      if (!ffiModel.keyboard || pi.currentDisplay == kAllDisplayValue) return;
+      // Resolutions of the peer are in its logical pixels.
+      final ratio =
+          ui.window.devicePixelRatio / pi.scaleOfDisplay(pi.currentDisplay);
+      final size = getSize();
+      final w = (size.width * ratio).toInt() ~/ 2 * 2;
+      final h = (size.height * ratio).toInt() ~/ 2 * 2;
+      Resolution? target;
+      if (ffiModel.isVirtualDisplayResolution) {
+        target = Resolution(w, h);
+      } else {
+        for (final r in pi.resolutions) {
+          if (r.width <= w &&
+              r.height <= h &&
+              (target == null ||
+                  r.width * r.height > target.width * target.height)) {
+            target = r;
+          }
+        }
+      }
+      if (target == null || target.width <= 0 || target.height <= 0) return;
+      final scale = pi.scaleOfDisplay(pi.currentDisplay);
+      if (target.width == (rect.width / scale).toInt() &&
+          target.height == (rect.height / scale).toInt()) {
+        return;
+      }
+      bind.sessionChangeResolution(
+          sessionId: sessionId,
+          display: pi.currentDisplay,
+          width: target.width,
+          height: target.height);
+    });
+  }
+
   updateViewStyle({refreshMousePos = true, notify = true}) async {
     final style = await bind.sessionGetViewStyle(sessionId: sessionId);
     if (style == null) {
@@ -3186,6 +3319,9 @@ class QualityMonitorData {
   String? targetBitrate;
   String? codecFormat;
   String? chroma;
+  String? decodeTime;
+  String? renderTime;
+  String? relay;
 }
 
 class QualityMonitorModel with ChangeNotifier {
@@ -3248,6 +3384,17 @@ class QualityMonitorModel with ChangeNotifier {
       if (evt.containsKey('chroma') && (evt['chroma'] as String).isNotEmpty) {
         _data.chroma = evt['chroma'];
       }
+      if (evt.containsKey('decode_time') &&
+          (evt['decode_time'] as String).isNotEmpty) {
+        _data.decodeTime = evt['decode_time'];
+      }
+      if (evt.containsKey('render_time') &&
+          (evt['render_time'] as String).isNotEmpty) {
+        _data.renderTime = evt['render_time'];
+      }
+      if (evt.containsKey('relay') && (evt['relay'] as String).isNotEmpty) {
+        _data.relay = evt['relay'];
+      }
       notifyListeners();
     } catch (e) {
       //
@@ -3260,6 +3407,8 @@ class RecordingModel with ChangeNotifier {
   RecordingModel(this.parent);
   bool _start = false;
   bool get start => _start;
+  bool _paused = false;
+  bool get paused => _paused;
 
   toggle() async {
     if (isIOS) return;
@@ -3274,10 +3423,80 @@ class RecordingModel with ChangeNotifier {
     await bind.sessionRecordScreen(sessionId: sessionId, start: value);
   }
 
+  togglePause() async {
+    final sessionId = parent.target?.sessionId;
+    if (sessionId == null || !_start) return;
+    final pi = parent.target?.ffiModel.pi;
+    if (pi == null) return;
+    bool value = !_paused;
+    await bind.sessionPauseRecord(sessionId: sessionId, paused: value);
+    if (!value) {
+      // The recording continues from a key frame, asked for once it takes
+      // frames again.
+      await sessionRefreshVideo(sessionId, pi);
+    }
+    _paused = value;
+    notifyListeners();
+  }
+
   updateStatus(bool status) {
     _start = status;
+    _paused = false;
+    notifyListeners();
+  }
+}
+
+enum InkShape { free, arrow, circle }
+
+class InkMark {
+  final InkShape shape;
+  // In remote coordinates, arrows and circles keep the start and the end.
+  final List<Offset> points = [];
+  DateTime lastTime = DateTime.now();
+  InkMark(this.shape);
+}
+
+/// The marks drawn in view only mode with "Show my cursor", the same as the
+/// peer draws them on its screen.
+class InkModel with ChangeNotifier {
+  static const _duration = Duration(seconds: 2);
+  final List<InkMark> marks = [];
+  Timer? _timer;
+
+  // A button down carries no position, the mark begins at the next move.
+  void add(double x, double y, InkShape shape, bool start) {
+    if (start || marks.isEmpty) marks.add(InkMark(shape));
+    _timer ??= Timer.periodic(Duration(milliseconds: 50), (_) => _fade());
+    if (start) return;
+    final mark = marks.last;
+    if (mark.shape != InkShape.free && mark.points.length > 1) {
+      mark.points.removeRange(1, mark.points.length);
+    }
+    mark.points.add(Offset(x, y));
+    mark.lastTime = DateTime.now();
+    notifyListeners();
+  }
+
+  double alpha(InkMark mark) {
+    final elapsed = DateTime.now().difference(mark.lastTime);
+    return 1.0 - min(elapsed.inMilliseconds / _duration.inMilliseconds, 1.0);
+  }
+
+  void _fade() {
+    final now = DateTime.now();
+    marks.removeWhere((m) => now.difference(m.lastTime) >= _duration);
+    if (marks.isEmpty) {
+      _timer?.cancel();
+      _timer = null;
+    }
     notifyListeners();
   }
+
+  void clear() {
+    _timer?.cancel();
+    _timer = null;
+    marks.clear();
+  }
 }
 
 class ElevationModel with ChangeNotifier {
@@ -3329,6 +3548,7 @@ class FFI {
   late final PeerTabModel peerTabModel; // global
   late final QualityMonitorModel qualityMonitorModel; // session
   late final RecordingModel recordingModel; // session
+  late final InkModel inkModel; // session
   late final InputModel inputModel; // session
   late final ElevationModel elevationModel; // session
   late final CmFileModel cmFileModel; // cm
@@ -3358,6 +3578,7 @@ class FFI {
     groupModel = GroupModel(WeakReference(this));
     qualityMonitorModel = QualityMonitorModel(WeakReference(this));
     recordingModel = RecordingModel(WeakReference(this));
+    inkModel = InkModel();
     inputModel = InputModel(WeakReference(this));
     elevationModel = ElevationModel(WeakReference(this));
     cmFileModel = CmFileModel(WeakReference(this));
@@ -3640,6 +3861,7 @@ class FFI {
     cursorModel.clear();
     ffiModel.clear();
     canvasModel.clear();
+    inkModel.clear();
     inputModel.resetModifiers();
     if (closeSession) {
       await bind.sessionClose(sessionId: sessionId);
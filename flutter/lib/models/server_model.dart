@@ -754,6 +754,15 @@ class ServerModel with ChangeNotifier {
     tabController.jumpTo(index);
   }
 
+  void showConnection(Map<String, dynamic> evt) async {
+    final id = int.tryParse(evt['id'].toString());
+    if (desktopType != DesktopType.cm || hideCm) return;
+    if (id == null || !_clients.any((c) => c.id == id)) return;
+    await showCmWindow();
+    jumpTo(id);
+    windowOnTop(null);
+  }
+
   void setShowElevation(bool show) {
     if (_showElevation != show) {
       _showElevation = show;
@@ -835,6 +844,8 @@ class Client {
   bool fromSwitch = false;
   bool inVoiceCall = false;
   bool incomingVoiceCall = false;
+  // set by the cm, not reported by the connection
+  bool viewOnlyForced = false;
 
   RxInt unreadChatMessageCount = 0.obs;
 
@@ -47,14 +47,22 @@ pub struct AomEncoderConfig {
     pub height: u32,
     pub quality: f32,
     pub keyframe_interval: Option<usize>,
+    /// Number of temporal layers, 1 disables SVC, at most `MAX_TEMPORAL_LAYERS`.
+    pub temporal_layers: u32,
 }
 
+pub const MAX_TEMPORAL_LAYERS: u32 = 3;
+
 pub struct AomEncoder {
     ctx: aom_codec_ctx_t,
     width: usize,
     height: usize,
     i444: bool,
     yuvfmt: EncodeYuvFormat,
+    temporal_layers: u32,
+    keyframe_interval: Option<usize>,
+    frame_index: u64, // Frames since the last keyframe, with temporal layers
+    temporal_layer_id: u32,
 }
 
 // https://webrtc.googlesource.com/src/+/refs/heads/main/modules/video_coding/codecs/av1/libaom_av1_encoder.cc
@@ -104,7 +112,10 @@ mod webrtc {
         c.g_timebase.num = 1;
         c.g_timebase.den = kTimeBaseDen as _;
         c.g_input_bit_depth = kBitDepth;
-        if let Some(keyframe_interval) = cfg.keyframe_interval {
+        if cfg.temporal_layers > 1 {
+            // Keyframes are placed by AomEncoder::encode, on temporal layer 0.
+            c.kf_mode = aom_kf_mode::AOM_KF_DISABLED;
+        } else if let Some(keyframe_interval) = cfg.keyframe_interval {
             c.kf_min_dist = 0;
             c.kf_max_dist = keyframe_interval as _;
         } else {
@@ -197,6 +208,84 @@ mod webrtc {
 
         Ok(())
     }
+
+    // https://aomedia.googlesource.com/aom/+/refs/heads/main/examples/svc_encoder_rtc.cc
+    // Single spatial layer, L1T2 or L1T3.
+    pub fn set_svc_params(
+        ctx: *mut aom_codec_ctx_t,
+        cfg: &aom_codec_enc_cfg,
+        temporal_layers: u32,
+    ) -> ResultType<()> {
+        // Cumulative share of the target bitrate, in percent.
+        let (bitrate_pct, framerate_factor): (&[u32], &[i32]) = match temporal_layers {
+            2 => (&[60, 100], &[2, 1]),
+            _ => (&[50, 70, 100], &[4, 2, 1]),
+        };
+        let mut params: aom_svc_params_t = unsafe { std::mem::zeroed() };
+        params.number_spatial_layers = 1;
+        params.number_temporal_layers = temporal_layers as _;
+        params.scaling_factor_num[0] = 1;
+        params.scaling_factor_den[0] = 1;
+        for tl in 0..temporal_layers as usize {
+            params.max_quantizers[tl] = cfg.rc_max_quantizer as _;
+            params.min_quantizers[tl] = cfg.rc_min_quantizer as _;
+            params.layer_target_bitrate[tl] = (cfg.rc_target_bitrate * bitrate_pct[tl] / 100) as _;
+            params.framerate_factor[tl] = framerate_factor[tl];
+        }
+        call_aom!(aom_codec_control(
+            ctx,
+            aome_enc_control_id::AV1E_SET_SVC_PARAMS as i32,
+            &mut params as *mut aom_svc_params_t,
+        ));
+        Ok(())
+    }
+
+    // Temporal layer of the frame, and its reference structure.
+    // Frames of the top layer are never referenced, and every layer only references
+    // lower or equal layers, so dropping all frames above a layer keeps the stream decodable.
+    //
+    // L1T2: 0 1 0 1 ...
+    // L1T3: 0 2 1 2 0 2 1 2 ...
+    pub fn set_svc_frame(
+        ctx: *mut aom_codec_ctx_t,
+        temporal_layers: u32,
+        frame_index: u64,
+    ) -> Result<u32> {
+        const LAST: usize = 0;
+        const GOLDEN: usize = 3;
+        let (layer, reference, refresh) = match (temporal_layers, frame_index % 4) {
+            (2, i) if i % 2 == 0 => (0, LAST, Some(0)),
+            (2, _) => (1, LAST, None),
+            (_, 0) => (0, LAST, Some(0)),
+            (_, 2) => (1, LAST, Some(1)),
+            (_, 1) => (2, LAST, None),
+            (_, _) => (2, GOLDEN, None),
+        };
+
+        let mut layer_id: aom_svc_layer_id_t = unsafe { std::mem::zeroed() };
+        layer_id.spatial_layer_id = 0;
+        layer_id.temporal_layer_id = layer as _;
+        call_aom!(aom_codec_control(
+            ctx,
+            aome_enc_control_id::AV1E_SET_SVC_LAYER_ID as i32,
+            &mut layer_id as *mut aom_svc_layer_id_t,
+        ));
+
+        // LAST reads slot 0, GOLDEN reads slot 1, the others are unused.
+        let mut ref_config: aom_svc_ref_frame_config_t = unsafe { std::mem::zeroed() };
+        ref_config.ref_idx[LAST] = 0;
+        ref_config.ref_idx[GOLDEN] = 1;
+        ref_config.reference[reference] = 1;
+        if let Some(slot) = refresh {
+            ref_config.refresh[slot] = 1;
+        }
+        call_aom!(aom_codec_control(
+            ctx,
+            aome_enc_control_id::AV1E_SET_SVC_REF_FRAME_CONFIG as i32,
+            &mut ref_config as *mut aom_svc_ref_frame_config_t,
+        ));
+        Ok(layer)
+    }
 }
 
 impl EncoderApi for AomEncoder {
@@ -220,12 +309,20 @@ impl EncoderApi for AomEncoder {
                     AOM_ENCODER_ABI_VERSION as _
                 ));
                 webrtc::set_controls(&mut ctx, &c)?;
+                let temporal_layers = config.temporal_layers.clamp(1, MAX_TEMPORAL_LAYERS);
+                if temporal_layers > 1 {
+                    webrtc::set_svc_params(&mut ctx, &c, temporal_layers)?;
+                }
                 Ok(Self {
                     ctx,
                     width: config.width as _,
                     height: config.height as _,
                     i444,
                     yuvfmt: Self::get_yuvfmt(config.width, config.height, i444),
+                    temporal_layers,
+                    keyframe_interval: config.keyframe_interval,
+                    frame_index: 0,
+                    temporal_layer_id: 0,
                 })
             }
             _ => Err(anyhow!("encoder type mismatch")),
@@ -263,6 +360,9 @@ impl EncoderApi for AomEncoder {
         c.rc_max_quantizer = q_max;
         c.rc_target_bitrate = Self::bitrate(self.width as _, self.height as _, ratio);
         call_aom!(aom_codec_enc_config_set(&mut self.ctx, &c));
+        if self.temporal_layers > 1 {
+            webrtc::set_svc_params(&mut self.ctx, &c, self.temporal_layers)?;
+        }
         Ok(())
     }
 
@@ -284,6 +384,10 @@ impl EncoderApi for AomEncoder {
     }
 
     fn disable(&self) {}
+
+    fn temporal_layer_id(&self) -> u32 {
+        self.temporal_layer_id
+    }
 }
 
 impl AomEncoder {
@@ -307,6 +411,19 @@ impl AomEncoder {
             stride_align as _,
             data.as_ptr() as _,
         ));
+        let mut flags: aom_enc_frame_flags_t = 0;
+        if self.temporal_layers > 1 {
+            // A keyframe restarts the pattern, so it is on layer 0 and reaches every user.
+            if let Some(interval) = self.keyframe_interval {
+                if self.frame_index >= interval as u64 {
+                    self.frame_index = 0;
+                    flags |= AOM_EFLAG_FORCE_KF as aom_enc_frame_flags_t;
+                }
+            }
+            self.temporal_layer_id =
+                webrtc::set_svc_frame(&mut self.ctx, self.temporal_layers, self.frame_index)?;
+            self.frame_index += 1;
+        }
         let pts = webrtc::kTimeBaseDen / 1000 * ms;
         let duration = webrtc::kTimeBaseDen / 1000;
         call_aom!(aom_codec_encode(
@@ -314,7 +431,7 @@ impl AomEncoder {
             &image,
             pts as _,
             duration as _, // Duration
-            0,             // Flags
+            flags,
         ));
 
         Ok(EncodeFrames {
@@ -80,6 +80,16 @@ pub trait EncoderApi {
     fn is_hardware(&self) -> bool;
 
     fn disable(&self);
+
+    // Temporal layer of the last encoded frame, 0 if SVC is not used.
+    fn temporal_layer_id(&self) -> u32 {
+        0
+    }
+
+    // Spend more bits on the area (x, y, width, height) than on the rest, `None` to treat the frame alike.
+    fn set_roi(&mut self, _roi: Option<(usize, usize, usize, usize)>) -> ResultType<()> {
+        Ok(())
+    }
 }
 
 pub struct Encoder {
@@ -284,8 +294,16 @@ impl Encoder {
         if auto_codec == CodecFormat::VP9 || auto_codec == CodecFormat::AV1 {
             let mut system = System::new();
             system.refresh_memory();
-            if vp8_useable && system.total_memory() <= 4 * 1024 * 1024 * 1024 {
-                // 4 Gb
+            // 4 Gb
+            let low_memory = system.total_memory() <= 4 * 1024 * 1024 * 1024;
+            let slow_vp9 = auto_codec == CodecFormat::VP9
+                && benchmark_fps(
+                    CodecFormat::VP9,
+                    BENCHMARK_WIDTH as _,
+                    BENCHMARK_HEIGHT as _,
+                )
+                    .is_some_and(|fps| fps < AUTO_VP9_MIN_FPS);
+            if vp8_useable && (low_memory || slow_vp9) {
                 auto_codec = CodecFormat::VP8
             }
         }
@@ -1036,122 +1054,208 @@ fn disable_av1() -> bool {
     std::mem::size_of::<usize>() == 4
 }
 
+// Size of the frames of the encoder benchmark
+const BENCHMARK_WIDTH: u32 = 1920;
+const BENCHMARK_HEIGHT: u32 = 1080;
+// Milliseconds per benchmark frame of the software encoders, e.g. "vp8=6,vp9=9,av1=21"
+const OPTION_ENCODE_BENCHMARK: &str = "encode-benchmark";
+// Auto selection prefers VP8 when VP9 can't reach this fps on the benchmark frames
+const AUTO_VP9_MIN_FPS: u32 = 30;
+
+/// Tests whether AV1 is fast enough to be picked automatically and benchmarks the software
+/// encoders, once per installation.
 #[cfg(not(target_os = "ios"))]
-pub fn test_av1() {
+pub fn test_encoders() {
     use hbb_common::config::keys::OPTION_AV1_TEST;
-    use hbb_common::rand::Rng;
     use std::{sync::Once, time::Duration};
 
-    if disable_av1() || !Config::get_option(OPTION_AV1_TEST).is_empty() {
-        log::info!("skip test av1");
+    let test_av1 = !disable_av1() && Config::get_option(OPTION_AV1_TEST).is_empty();
+    let benchmark = Config::get_option(OPTION_ENCODE_BENCHMARK).is_empty();
+    if !test_av1 && !benchmark {
+        log::info!("skip test encoders");
         return;
     }
 
     static ONCE: Once = Once::new();
     ONCE.call_once(|| {
-        let f = || {
-            let (width, height, quality, keyframe_interval, i444) = (1920, 1080, 1.0, None, false);
-            let frame_count = 10;
-            let block_size = 300;
-            let move_step = 50;
-            let generate_fake_data =
-                |frame_index: u32, dst_fmt: EncodeYuvFormat| -> ResultType<Vec<u8>> {
-                    let mut rng = hbb_common::rand::thread_rng();
-                    let mut bgra = vec![0u8; (width * height * 4) as usize];
-                    let gradient = frame_index as f32 / frame_count as f32;
-                    // floating block
-                    let x0 = (frame_index * move_step) % (width - block_size);
-                    let y0 = (frame_index * move_step) % (height - block_size);
-                    // Fill the block with random colors
-                    for y in 0..block_size {
-                        for x in 0..block_size {
-                            let index = (((y0 + y) * width + x0 + x) * 4) as usize;
-                            if index + 3 < bgra.len() {
-                                let noise = rng.gen_range(0..255) as f32 / 255.0;
-                                let value = (255.0 * gradient + noise * 50.0) as u8;
-                                bgra[index] = value;
-                                bgra[index + 1] = value;
-                                bgra[index + 2] = value;
-                                bgra[index + 3] = 255;
-                            }
-                        }
-                    }
-                    let dst_stride_y = dst_fmt.stride[0];
-                    let dst_stride_uv = dst_fmt.stride[1];
-                    let mut dst = vec![0u8; (dst_fmt.h * dst_stride_y * 2) as usize];
-                    let dst_y = dst.as_mut_ptr();
-                    let dst_u = dst[dst_fmt.u..].as_mut_ptr();
-                    let dst_v = dst[dst_fmt.v..].as_mut_ptr();
-                    let res = unsafe {
-                        crate::ARGBToI420(
-                            bgra.as_ptr(),
-                            (width * 4) as _,
-                            dst_y,
-                            dst_stride_y as _,
-                            dst_u,
-                            dst_stride_uv as _,
-                            dst_v,
-                            dst_stride_uv as _,
-                            width as _,
-                            height as _,
-                        )
-                    };
-                    if res != 0 {
-                        bail!("ARGBToI420 failed: {}", res);
-                    }
-                    Ok(dst)
-                };
-            let Ok(mut av1) = AomEncoder::new(
-                EncoderCfg::AOM(AomEncoderConfig {
-                    width,
-                    height,
-                    quality,
-                    keyframe_interval,
+        let new_vpx = |codec: VpxVideoCodecId| {
+            VpxEncoder::new(
+                EncoderCfg::VPX(VpxEncoderConfig {
+                    width: BENCHMARK_WIDTH,
+                    height: BENCHMARK_HEIGHT,
+                    quality: 1.0,
+                    codec,
+                    keyframe_interval: None,
                 }),
-                i444,
-            ) else {
-                return false;
+                false,
+            )
+        };
+        // One encoder after the other, running them at the same time would slow down each one.
+        std::thread::spawn(move || {
+            let av1 = if disable_av1() {
+                None
+            } else {
+                AomEncoder::new(
+                    EncoderCfg::AOM(AomEncoderConfig {
+                        width: BENCHMARK_WIDTH,
+                        height: BENCHMARK_HEIGHT,
+                        quality: 1.0,
+                        keyframe_interval: None,
+                        temporal_layers: 1,
+                    }),
+                    false,
+                )
+                .ok()
+                .and_then(|mut e| measure_encode(&mut e, "av1"))
             };
-            let mut key_frame_time = Duration::ZERO;
-            let mut non_key_frame_time_sum = Duration::ZERO;
-            let pts = Instant::now();
-            let yuvfmt = av1.yuvfmt();
-            for i in 0..frame_count {
-                let Ok(yuv) = generate_fake_data(i, yuvfmt.clone()) else {
-                    return false;
-                };
-                let start = Instant::now();
-                if av1
-                    .encode(pts.elapsed().as_millis() as _, &yuv, super::STRIDE_ALIGN)
-                    .is_err()
+            if test_av1 {
+                let v = av1.is_some_and(|(key, non_key)| {
+                    key < Duration::from_millis(90) && non_key < Duration::from_millis(30)
+                });
+                Config::set_option(
+                    OPTION_AV1_TEST.to_string(),
+                    if v { "Y" } else { "N" }.to_string(),
+                );
+            }
+            if benchmark {
+                let mut results = vec![];
+                for (name, codec) in [("vp8", VpxVideoCodecId::VP8), ("vp9", VpxVideoCodecId::VP9)]
                 {
-                    log::debug!("av1 encode failed");
-                    if i == 0 {
-                        return false;
+                    if let Some((_, t)) = new_vpx(codec)
+                        .ok()
+                        .and_then(|mut e| measure_encode(&mut e, name))
+                    {
+                        results.push(format!("{}={}", name, t.as_millis()));
                     }
                 }
-                if i == 0 {
-                    key_frame_time = start.elapsed();
-                } else {
-                    non_key_frame_time_sum += start.elapsed();
+                if let Some((_, t)) = av1 {
+                    results.push(format!("av1={}", t.as_millis()));
                 }
+                Config::set_option(OPTION_ENCODE_BENCHMARK.to_string(), results.join(","));
             }
-            let non_key_frame_time = non_key_frame_time_sum / (frame_count - 1);
-            log::info!(
-                "av1 time: key: {:?}, non-key: {:?}, consume: {:?}",
-                key_frame_time,
-                non_key_frame_time,
-                pts.elapsed()
-            );
-            key_frame_time < Duration::from_millis(90)
-                && non_key_frame_time < Duration::from_millis(30)
-        };
-        std::thread::spawn(move || {
-            let v = f();
-            Config::set_option(
-                OPTION_AV1_TEST.to_string(),
-                if v { "Y" } else { "N" }.to_string(),
-            );
         });
     });
 }
+
+/// The fps the software encoder of `format` reached in the benchmark, scaled to `width` x `height`.
+pub fn benchmark_fps(format: CodecFormat, width: usize, height: usize) -> Option<u32> {
+    let name = match format {
+        CodecFormat::VP8 => "vp8",
+        CodecFormat::VP9 => "vp9",
+        CodecFormat::AV1 => "av1",
+        _ => return None,
+    };
+    let ms = Config::get_option(OPTION_ENCODE_BENCHMARK)
+        .split(',')
+        .find_map(|v| {
+            let (k, v) = v.split_once('=')?;
+            if k.trim() == name {
+                v.trim().parse::<f32>().ok()
+            } else {
+                None
+            }
+        })?;
+    let scale = (width * height) as f32 / (BENCHMARK_WIDTH * BENCHMARK_HEIGHT) as f32;
+    Some((1000. / (ms.max(1.) * scale)) as u32)
+}
+
+// Encode time of the first, key frame and the average of the others
+#[cfg(not(target_os = "ios"))]
+fn measure_encode(
+    encoder: &mut dyn EncoderApi,
+    name: &str,
+) -> Option<(std::time::Duration, std::time::Duration)> {
+    use std::time::Duration;
+
+    let frame_count = 10;
+    let mut key_frame_time = Duration::ZERO;
+    let mut non_key_frame_time_sum = Duration::ZERO;
+    let pts = Instant::now();
+    let yuvfmt = encoder.yuvfmt();
+    for i in 0..frame_count {
+        let Ok(yuv) = generate_fake_data(i, frame_count, yuvfmt.clone()) else {
+            return None;
+        };
+        let start = Instant::now();
+        if encoder
+            .encode_to_message(EncodeInput::YUV(&yuv), pts.elapsed().as_millis() as _)
+            .is_err()
+        {
+            log::debug!("{name} encode failed");
+            if i == 0 {
+                return None;
+            }
+        }
+        if i == 0 {
+            key_frame_time = start.elapsed();
+        } else {
+            non_key_frame_time_sum += start.elapsed();
+        }
+    }
+    let non_key_frame_time = non_key_frame_time_sum / (frame_count - 1);
+    log::info!(
+        "{name} time: key: {:?}, non-key: {:?}, consume: {:?}",
+        key_frame_time,
+        non_key_frame_time,
+        pts.elapsed()
+    );
+    Some((key_frame_time, non_key_frame_time))
+}
+
+// A gray background with a noisy block moving across it
+#[cfg(not(target_os = "ios"))]
+fn generate_fake_data(
+    frame_index: u32,
+    frame_count: u32,
+    dst_fmt: EncodeYuvFormat,
+) -> ResultType<Vec<u8>> {
+    use hbb_common::rand::Rng;
+
+    let (width, height) = (BENCHMARK_WIDTH, BENCHMARK_HEIGHT);
+    let block_size = 300;
+    let move_step = 50;
+    let mut rng = hbb_common::rand::thread_rng();
+    let mut bgra = vec![0u8; (width * height * 4) as usize];
+    let gradient = frame_index as f32 / frame_count as f32;
+    // floating block
+    let x0 = (frame_index * move_step) % (width - block_size);
+    let y0 = (frame_index * move_step) % (height - block_size);
+    // Fill the block with random colors
+    for y in 0..block_size {
+        for x in 0..block_size {
+            let index = (((y0 + y) * width + x0 + x) * 4) as usize;
+            if index + 3 < bgra.len() {
+                let noise = rng.gen_range(0..255) as f32 / 255.0;
+                let value = (255.0 * gradient + noise * 50.0) as u8;
+                bgra[index] = value;
+                bgra[index + 1] = value;
+                bgra[index + 2] = value;
+                bgra[index + 3] = 255;
+            }
+        }
+    }
+    let dst_stride_y = dst_fmt.stride[0];
+    let dst_stride_uv = dst_fmt.stride[1];
+    let mut dst = vec![0u8; (dst_fmt.h * dst_stride_y * 2) as usize];
+    let dst_y = dst.as_mut_ptr();
+    let dst_u = dst[dst_fmt.u..].as_mut_ptr();
+    let dst_v = dst[dst_fmt.v..].as_mut_ptr();
+    let res = unsafe {
+        crate::ARGBToI420(
+            bgra.as_ptr(),
+            (width * 4) as _,
+            dst_y,
+            dst_stride_y as _,
+            dst_u,
+            dst_stride_uv as _,
+            dst_v,
+            dst_stride_uv as _,
+            width as _,
+            height as _,
+        )
+    };
+    if res != 0 {
+        bail!("ARGBToI420 failed: {}", res);
+    }
+    Ok(dst)
+}
@@ -12,13 +12,39 @@ use std::{
     fs::{File, OpenOptions},
     io,
     ops::{Deref, DerefMut},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::mpsc::Sender,
-    time::Instant,
+    time::{Duration, Instant},
 };
 use webm::mux::{self, Segment, Track, VideoTrack, Writer};
 
 const MIN_SECS: u64 = 1;
+// Local options when recording outgoing sessions, server options for incoming ones.
+// Zero or empty turns each of them off.
+pub const OPTION_RECORD_SPLIT_MINUTES: &str = "record-split-minutes";
+pub const OPTION_RECORD_SPLIT_MB: &str = "record-split-mb";
+pub const OPTION_RECORD_MIN_FREE_MB: &str = "record-min-free-mb";
+const SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const MB: u64 = 1024 * 1024;
+
+/// When a recording moves on to a new file or stops.
+#[derive(Debug, Clone, Default)]
+pub struct RecordLimits {
+    pub split_after: Option<Duration>,
+    pub split_bytes: Option<u64>,
+    pub min_free_bytes: Option<u64>,
+}
+
+impl RecordLimits {
+    pub fn from_options(get_option: impl Fn(&str) -> String) -> Self {
+        let get = |k| get_option(k).parse::<u64>().ok().filter(|v| *v > 0);
+        Self {
+            split_after: get(OPTION_RECORD_SPLIT_MINUTES).map(|m| Duration::from_secs(m * 60)),
+            split_bytes: get(OPTION_RECORD_SPLIT_MB).map(|m| m * MB),
+            min_free_bytes: get(OPTION_RECORD_MIN_FREE_MB).map(|m| m * MB),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct RecorderContext {
@@ -28,6 +54,7 @@ pub struct RecorderContext {
     pub display_idx: usize,
     pub camera: bool,
     pub tx: Option<Sender<RecordState>>,
+    pub limits: RecordLimits,
 }
 
 #[derive(Debug, Clone)]
@@ -93,6 +120,15 @@ pub struct Recorder {
     ctx2: Option<RecorderContext2>,
     pts: Option<i64>,
     check_failed: bool,
+    paused: bool,
+    // Frames are skipped after a resume until a key frame, the file can't be decoded otherwise.
+    resuming: bool,
+    // Time spent paused, taken off the pts so the recording continues without a gap.
+    pts_offset: i64,
+    file_start: Instant,
+    file_bytes: u64,
+    last_space_check: Option<Instant>,
+    disk_full: bool,
 }
 
 impl Deref for Recorder {
@@ -117,9 +153,28 @@ impl Recorder {
             ctx2: None,
             pts: None,
             check_failed: false,
+            paused: false,
+            resuming: false,
+            pts_offset: 0,
+            file_start: Instant::now(),
+            file_bytes: 0,
+            last_space_check: None,
+            disk_full: false,
         })
     }
 
+    /// Whether the recording stopped because the disk ran low on space.
+    pub fn disk_full(&self) -> bool {
+        self.disk_full
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            self.paused = paused;
+            self.resuming = !paused;
+        }
+    }
+
     fn check(&mut self, w: usize, h: usize, format: CodecFormat) -> ResultType<()> {
         match self.ctx2 {
             Some(ref ctx2) => {
@@ -165,6 +220,9 @@ impl Recorder {
             };
             // pts is None when new inner is created
             self.pts = None;
+            self.pts_offset = 0;
+            self.file_start = Instant::now();
+            self.file_bytes = 0;
             self.send_state(RecordState::NewFile(ctx2.filename.clone()));
         }
         Ok(())
@@ -187,6 +245,13 @@ impl Recorder {
         if self.check_failed {
             bail!("check failed");
         }
+        if self.paused || self.disk_full {
+            return Ok(());
+        }
+        self.check_space();
+        if self.disk_full {
+            return Ok(());
+        }
         let format = CodecFormat::from(frame);
         if format == CodecFormat::Unknown {
             bail!("unsupported frame type");
@@ -197,40 +262,48 @@ impl Recorder {
             log::error!("check failed: {:?}", res);
             res?;
         }
-        match frame {
-            video_frame::Union::Vp8s(vp8s) => {
-                for f in vp8s.frames.iter() {
-                    self.check_pts(f.pts, f.key, w, h, format)?;
-                    self.as_mut().map(|x| x.write_video(f));
-                }
-            }
-            video_frame::Union::Vp9s(vp9s) => {
-                for f in vp9s.frames.iter() {
-                    self.check_pts(f.pts, f.key, w, h, format)?;
-                    self.as_mut().map(|x| x.write_video(f));
+        let frames = match frame {
+            video_frame::Union::Vp8s(vp8s) => &vp8s.frames,
+            video_frame::Union::Vp9s(vp9s) => &vp9s.frames,
+            video_frame::Union::Av1s(av1s) => &av1s.frames,
+            #[cfg(feature = "hwcodec")]
+            video_frame::Union::H264s(h264s) => &h264s.frames,
+            #[cfg(feature = "hwcodec")]
+            video_frame::Union::H265s(h265s) => &h265s.frames,
+            _ => bail!("unsupported frame type"),
+        };
+        for f in frames.iter() {
+            if self.resuming {
+                if !f.key {
+                    continue;
                 }
-            }
-            video_frame::Union::Av1s(av1s) => {
-                for f in av1s.frames.iter() {
-                    self.check_pts(f.pts, f.key, w, h, format)?;
-                    self.as_mut().map(|x| x.write_video(f));
+                self.resuming = false;
+                // Continue 1ms after the last frame written.
+                if let Some(last) = self.pts {
+                    if f.pts > last {
+                        self.pts_offset += f.pts - last - 1;
+                    }
                 }
             }
-            #[cfg(feature = "hwcodec")]
-            video_frame::Union::H264s(h264s) => {
-                for f in h264s.frames.iter() {
-                    self.check_pts(f.pts, f.key, w, h, format)?;
-                    self.as_mut().map(|x| x.write_video(f));
-                }
+            // Files only start on a key frame.
+            if f.key && self.pts.is_some() && self.should_split() {
+                log::info!(
+                    "split record file {:?}",
+                    self.ctx2.as_ref().map(|c| &c.filename)
+                );
+                self.inner = None;
+                self.ctx2 = None;
+                self.check(w, h, format)?;
             }
-            #[cfg(feature = "hwcodec")]
-            video_frame::Union::H265s(h265s) => {
-                for f in h265s.frames.iter() {
-                    self.check_pts(f.pts, f.key, w, h, format)?;
-                    self.as_mut().map(|x| x.write_video(f));
-                }
+            self.check_pts(f.pts, f.key, w, h, format)?;
+            self.file_bytes += f.data.len() as u64;
+            if self.pts_offset == 0 {
+                self.as_mut().map(|x| x.write_video(f));
+            } else {
+                let mut f = f.clone();
+                f.pts -= self.pts_offset;
+                self.as_mut().map(|x| x.write_video(&f));
             }
-            _ => bail!("unsupported frame type"),
         }
         self.send_state(RecordState::NewFrame);
         Ok(())
@@ -265,11 +338,60 @@ impl Recorder {
         Ok(())
     }
 
+    fn should_split(&self) -> bool {
+        let limits = &self.ctx.limits;
+        limits
+            .split_after
+            .map_or(false, |d| self.file_start.elapsed() >= d)
+            || limits.split_bytes.map_or(false, |b| self.file_bytes >= b)
+    }
+
+    fn check_space(&mut self) {
+        let Some(min) = self.ctx.limits.min_free_bytes else {
+            return;
+        };
+        if self
+            .last_space_check
+            .map_or(false, |t| t.elapsed() < SPACE_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_space_check = Some(Instant::now());
+        if let Some(free) = free_space(&self.ctx.dir) {
+            if free < min {
+                log::warn!(
+                    "Only {} MB free in {}, stop recording",
+                    free / MB,
+                    self.ctx.dir
+                );
+                self.disk_full = true;
+                // Dropping the writer finishes the file.
+                self.inner = None;
+            }
+        }
+    }
+
     fn send_state(&self, state: RecordState) {
         self.ctx.tx.as_ref().map(|tx| tx.send(state));
     }
 }
 
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn free_space(dir: &str) -> Option<u64> {
+    use hbb_common::sysinfo::Disks;
+    // The disk with the longest mount point that holds `dir`.
+    Disks::new_with_refreshed_list()
+        .iter()
+        .filter(|d| Path::new(dir).starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn free_space(_dir: &str) -> Option<u64> {
+    None
+}
+
 struct WebmRecorder {
     vt: VideoTrack,
     webm: Option<Segment<Writer<File>>>,
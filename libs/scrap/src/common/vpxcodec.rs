@@ -20,6 +20,10 @@ use std::{ptr, slice};
 generate_call_macro!(call_vpx, false);
 generate_call_ptr_macro!(call_vpx_ptr);
 
+// Quantizer offsets of the region of interest and of the rest of the frame
+const ROI_DELTA_Q: c_int = -15;
+const ROI_PERIPHERY_DELTA_Q: c_int = 10;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum VpxVideoCodecId {
     VP8,
@@ -39,6 +43,7 @@ pub struct VpxEncoder {
     id: VpxVideoCodecId,
     i444: bool,
     yuvfmt: EncodeYuvFormat,
+    roi: Option<(usize, usize, usize, usize)>,
 }
 
 pub struct VpxDecoder {
@@ -165,6 +170,7 @@ impl EncoderApi for VpxEncoder {
                     id: config.codec,
                     i444,
                     yuvfmt: Self::get_yuvfmt(config.width, config.height, i444),
+                    roi: None,
                 })
             }
             _ => Err(anyhow!("encoder type mismatch")),
@@ -228,6 +234,38 @@ impl EncoderApi for VpxEncoder {
     }
 
     fn disable(&self) {}
+
+    fn set_roi(&mut self, roi: Option<(usize, usize, usize, usize)>) -> ResultType<()> {
+        if self.id != VpxVideoCodecId::VP9 || roi == self.roi {
+            return Ok(());
+        }
+        // One segment id per 8x8 block, libvpx copies the map
+        let rows = (self.height + 7) / 8;
+        let cols = (self.width + 7) / 8;
+        let mut map = vec![0u8; rows * cols];
+        let mut roi_map: vpx_roi_map_t = unsafe { std::mem::zeroed() };
+        // 0 would force intra prediction, -1 leaves the reference frame to the encoder
+        roi_map.ref_frame = [-1; 8];
+        if let Some((x, y, w, h)) = roi {
+            let (left, right) = ((x / 8).min(cols), ((x + w + 7) / 8).min(cols));
+            for row in (y / 8).min(rows)..((y + h + 7) / 8).min(rows) {
+                map[row * cols + left..row * cols + right].fill(1);
+            }
+            roi_map.enabled = 1;
+            roi_map.roi_map = map.as_mut_ptr();
+            roi_map.rows = rows as _;
+            roi_map.cols = cols as _;
+            roi_map.delta_q[0] = ROI_PERIPHERY_DELTA_Q;
+            roi_map.delta_q[1] = ROI_DELTA_Q;
+        }
+        call_vpx!(vpx_codec_control_(
+            &mut self.ctx,
+            VP9E_SET_ROI_MAP as _,
+            &mut roi_map as *mut vpx_roi_map_t
+        ));
+        self.roi = roi;
+        Ok(())
+    }
 }
 
 impl VpxEncoder {
@@ -7,11 +7,16 @@ use hbb_common::{
     ResultType,
 };
 use serde_derive::{Deserialize, Serialize};
-use std::sync::Mutex;
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use totp_rs::{Algorithm, Secret, TOTP};
 
 lazy_static::lazy_static! {
     static ref CURRENT_2FA: Mutex<Option<(TOTPInfo, TOTP)>> = Mutex::new(None);
+    // Time step of the last accepted login code.
+    static ref LAST_USED_STEP: Mutex<u64> = Mutex::new(0);
 }
 
 const ISSUER: &str = "RustDesk";
@@ -111,6 +116,24 @@ pub fn get_2fa(raw: Option<String>) -> Option<TOTP> {
         .unwrap_or_default()
 }
 
+/// Checks a code entered to log in. A code is accepted only once, as RFC 6238 asks,
+/// so an observed code can not be replayed for the rest of its time window.
+pub fn check_login_code(totp: &TOTP, code: &str) -> bool {
+    let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return false;
+    };
+    let current = now.as_secs() / totp.step;
+    let skew = totp.skew as u64;
+    let mut last_used = LAST_USED_STEP.lock().unwrap();
+    for step in current.saturating_sub(skew)..=current + skew {
+        if step > *last_used && totp.generate(step * totp.step) == code {
+            *last_used = step;
+            return true;
+        }
+    }
+    false
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TelegramBot {
     #[serde(skip)]
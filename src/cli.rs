@@ -13,24 +13,36 @@ use hbb_common::{
 };
 use std::sync::{Arc, RwLock};
 
+// Exit statuses of the command line tool, for scripts.
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_AUTH: i32 = 3;
+
 #[derive(Clone)]
 pub struct Session {
     id: String,
     lc: Arc<RwLock<LoginConfigHandler>>,
     sender: mpsc::UnboundedSender<Data>,
     password: String,
+    // Set when the password was given up front, nobody is there to type another one.
+    headless: bool,
 }
 
 impl Session {
-    pub fn new(id: &str, sender: mpsc::UnboundedSender<Data>) -> Self {
-        let mut password = "".to_owned();
-        if PeerConfig::load(id).password.is_empty() {
-            password = rpassword::prompt_password("Enter password: ").unwrap();
-        }
+    pub fn new(id: &str, sender: mpsc::UnboundedSender<Data>, password: Option<String>) -> Self {
+        let headless = password.is_some();
+        let password = match password {
+            Some(password) => password,
+            None if PeerConfig::load(id).password.is_empty() => {
+                rpassword::prompt_password("Enter password: ").unwrap()
+            }
+            None => "".to_owned(),
+        };
         let session = Self {
             id: id.to_owned(),
             sender,
             password,
+            headless,
             lc: Default::default(),
         };
         session.lc.write().unwrap().initialize(
@@ -60,6 +72,9 @@ impl Interface for Session {
             }
             "re-input-password" => {
                 log::error!("{}: {}", title, text);
+                if self.headless {
+                    std::process::exit(EXIT_AUTH);
+                }
                 match rpassword::prompt_password("Enter password: ") {
                     Ok(password) => {
                         let login_data = Data::Login((password, true));
@@ -123,13 +138,15 @@ impl Interface for Session {
     }
 }
 
+/// Whether `id` can be reached and asks for a password.
 #[tokio::main(flavor = "current_thread")]
-pub async fn connect_test(id: &str, key: String, token: String) {
+pub async fn connect_test(id: &str, key: String, token: String, password: Option<String>) -> bool {
     let (sender, mut receiver) = mpsc::unbounded_channel::<Data>();
-    let handler = Session::new(&id, sender);
+    let handler = Session::new(&id, sender, password);
     match crate::client::Client::start(id, &key, &token, ConnType::PORT_FORWARD, handler).await {
         Err(err) => {
             log::error!("Failed to connect {}: {}", &id, err);
+            false
         }
         Ok((mut stream, direct)) => {
             log::info!("direct: {}", direct);
@@ -139,20 +156,23 @@ pub async fn connect_test(id: &str, key: String, token: String) {
                     res = hbb_common::timeout(READ_TIMEOUT, stream.next()) => match res {
                         Err(_) => {
                             log::error!("Timeout");
-                            break;
+                            return false;
                         }
                         Ok(Some(Ok(bytes))) => {
                             if let Ok(msg_in) = Message::parse_from_bytes(&bytes) {
                                 match msg_in.union {
                                     Some(message::Union::Hash(hash)) => {
                                         log::info!("Got hash");
-                                        break;
+                                        return true;
                                     }
                                     _ => {}
                                 }
                             }
                         }
-                        _ => {}
+                        _ => {
+                            log::error!("Connection closed");
+                            return false;
+                        }
                     }
                 }
             }
@@ -160,6 +180,7 @@ pub async fn connect_test(id: &str, key: String, token: String) {
     }
 }
 
+/// Forwards `port` until the connection ends, false if it could not be set up.
 #[tokio::main(flavor = "current_thread")]
 pub async fn start_one_port_forward(
     id: String,
@@ -168,11 +189,12 @@ pub async fn start_one_port_forward(
     remote_port: i32,
     key: String,
     token: String,
-) {
+    password: Option<String>,
+) -> bool {
     crate::common::test_rendezvous_server();
     crate::common::test_nat_type();
     let (sender, mut receiver) = mpsc::unbounded_channel::<Data>();
-    let handler = Session::new(&id, sender);
+    let handler = Session::new(&id, sender, password);
     if let Err(err) = crate::port_forward::listen(
         handler.id.clone(),
         handler.password.clone(),
@@ -188,6 +210,8 @@ pub async fn start_one_port_forward(
     .await
     {
         log::error!("Failed to listen on {}: {}", port, err);
+        return false;
     }
     log::info!("port forward (:{}) exit", port);
+    true
 }
@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     ffi::c_void,
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     ops::Deref,
     str::FromStr,
     sync::{
@@ -75,7 +75,7 @@ use hbb_common::{
 pub use helper::*;
 use scrap::{
     codec::Decoder,
-    record::{Recorder, RecorderContext},
+    record::{RecordLimits, Recorder, RecorderContext},
     CodecFormat, ImageFormat, ImageRgb, ImageTexture,
 };
 
@@ -89,15 +89,29 @@ use crate::ui_session_interface::SessionPermissionConfig;
 
 pub use super::lang::*;
 
+pub mod clipboard_history;
 pub mod file_trait;
+pub mod folder_sync;
 pub mod helper;
 pub mod io_loop;
+pub mod relay;
+pub mod replay;
 pub mod screenshot;
+pub mod smoothing;
 
 pub const MILLI1: Duration = Duration::from_millis(1);
 pub const SEC30: Duration = Duration::from_secs(30);
 pub const VIDEO_QUEUE_SIZE: usize = 120;
 const MAX_DECODE_FAIL_COUNTER: usize = 3;
+// With CGNAT on IPv4, the relay would win the race almost every time,
+// so it waits a bit to prefer a direct IPv6 path when both sides have one.
+const IPV6_HEAD_START: Duration = Duration::from_millis(1_000);
+const IPV6_MIN_CONNECT_TIMEOUT: u64 = 3_000;
+// A symmetric NAT often maps the next connections of the peer to the ports right after the one
+// the rendezvous server saw, these are tried too while our own mapping is stable.
+const PREDICTED_PORTS: u16 = 8;
+// Comma separated indexes of the displays to record, all of them if empty.
+const OPTION_RECORD_DISPLAYS: &str = "record-displays";
 
 #[cfg(target_os = "linux")]
 pub const LOGIN_MSG_DESKTOP_NOT_INITED: &str = "Desktop env is not inited";
@@ -251,11 +265,11 @@ impl Client {
             bail!("Incoming only mode");
         }
         // to-do: remember the port for each peer, so that we can retry easier
+        // `connect_tcp` goes through the configured proxy, if any.
         if hbb_common::is_ip_str(peer) {
             return Ok((
                 (
-                    connect_tcp_local(check_port(peer, RELAY_PORT + 1), None, CONNECT_TIMEOUT)
-                        .await?,
+                    connect_tcp(check_port(peer, RELAY_PORT + 1), CONNECT_TIMEOUT).await?,
                     true,
                     None,
                     None,
@@ -269,7 +283,7 @@ impl Client {
         if hbb_common::is_domain_port_str(peer) {
             return Ok((
                 (
-                    connect_tcp_local(peer, None, CONNECT_TIMEOUT).await?,
+                    connect_tcp(peer, CONNECT_TIMEOUT).await?,
                     true,
                     None,
                     None,
@@ -279,6 +293,9 @@ impl Client {
                 false,
             ));
         }
+        if crate::is_lan_only() {
+            bail!("LAN-only mode, connect to the ip address of the peer");
+        }
 
         let other_server = interface.get_lch().read().unwrap().other_server.clone();
         let (peer, other_server, key, token) = if let Some((a, b, c)) = other_server.as_ref() {
@@ -546,6 +563,7 @@ impl Client {
                             }
                         }
                         signed_id_pk = rr.pk().into();
+                        let ipv6_attempt = !connect_futures.is_empty();
                         let fut = Self::create_relay(
                             &peer,
                             rr.uuid,
@@ -556,6 +574,9 @@ impl Client {
                         );
                         connect_futures.push(
                             async move {
+                                if ipv6_attempt {
+                                    hbb_common::sleep(IPV6_HEAD_START.as_secs_f32()).await;
+                                }
                                 let conn = fut.await?;
                                 Ok((conn, None, if use_ws() { "WebSocket" } else { "Relay" }))
                             }
@@ -696,11 +717,35 @@ impl Client {
             }
             .boxed(),
         );
+        // The nat types sent in the punch hole messages, as the peer decides with them. A forced
+        // relay is sent as symmetric, the peer relays then.
+        if !is_local
+            && !interface.is_force_relay()
+            && crate::use_birthday_punch(peer_nat_type as i32, my_nat_type)
+        {
+            let ports: Vec<u16> = {
+                use hbb_common::rand::Rng;
+                let mut rng = hbb_common::rand::thread_rng();
+                (1..=PREDICTED_PORTS)
+                    .filter_map(|i| peer.port().checked_add(i))
+                    .chain((0..crate::BIRTHDAY_PORTS).map(|_| rng.gen_range(1024..=u16::MAX)))
+                    .collect()
+            };
+            log::info!(
+                "try {} predicted and {} random ports of the peer",
+                PREDICTED_PORTS,
+                crate::BIRTHDAY_PORTS
+            );
+            connect_futures
+                .push(probe_ports(peer.ip(), ports, local_addr, connect_timeout).boxed());
+        }
         if let Some(udp_socket_nat) = udp_socket_nat {
             connect_futures.push(udp_nat_connect(udp_socket_nat, "UDP", connect_timeout).boxed());
         }
         if let Some(udp_socket_v6) = udp_socket_v6 {
-            connect_futures.push(udp_nat_connect(udp_socket_v6, "IPv6", connect_timeout).boxed());
+            // The timeout above is tuned for IPv4 NAT types, which don't apply to IPv6.
+            let ipv6_timeout = connect_timeout.max(IPV6_MIN_CONNECT_TIMEOUT);
+            connect_futures.push(udp_nat_connect(udp_socket_v6, "IPv6", ipv6_timeout).boxed());
         }
         // Run all connection attempts concurrently, return the first successful one
         let (mut conn, kcp, mut typ) = match select_ok(connect_futures).await {
@@ -709,11 +754,29 @@ impl Client {
         };
 
         let mut direct = !conn.is_err();
+        // Left from an earlier connection of this session otherwise.
+        interface.get_lch().write().unwrap().relay = None;
         if interface.is_force_relay() || conn.is_err() {
             if !relay_server.is_empty() {
+                let pinned = interface
+                    .get_lch()
+                    .read()
+                    .unwrap()
+                    .get_option(relay::PEER_OPTION_RELAY_SERVER);
+                let (relay_server, rtt) = relay::select(relay_server, &pinned).await;
+                if let Some(rtt) = rtt {
+                    let mut lc = interface.get_lch().write().unwrap();
+                    if relay_server != pinned {
+                        lc.set_option(
+                            relay::PEER_OPTION_RELAY_SERVER.to_owned(),
+                            relay_server.clone(),
+                        );
+                    }
+                    lc.relay = Some((relay_server.clone(), rtt));
+                }
                 conn = Self::request_relay(
                     peer_id,
-                    relay_server.to_owned(),
+                    relay_server,
                     rendezvous_server,
                     !signed_id_pk.is_empty(),
                     key,
@@ -1540,6 +1603,7 @@ pub struct VideoHandler {
     pub texture: ImageTexture,
     recorder: Arc<Mutex<Option<Recorder>>>,
     record: bool,
+    replay: Option<replay::ReplayBuffer>,
     _display: usize, // useful for debug
     fail_counter: usize,
     first_frame: bool,
@@ -1572,6 +1636,11 @@ impl VideoHandler {
             texture: Default::default(),
             recorder: Default::default(),
             record: false,
+            replay: LocalConfig::get_option(replay::OPTION_REPLAY_BUFFER_SECONDS)
+                .parse::<u64>()
+                .ok()
+                .filter(|s| *s > 0)
+                .map(|s| replay::ReplayBuffer::new(Duration::from_secs(s))),
             _display,
             fail_counter: 0,
             first_frame: true,
@@ -1616,16 +1685,19 @@ impl VideoHandler {
                     }
                 }
                 self.first_frame = false;
+                let (w, h) = if *pixelbuffer {
+                    (self.rgb.w, self.rgb.h)
+                } else {
+                    (self.texture.w, self.texture.h)
+                };
                 if self.record {
                     self.recorder.lock().unwrap().as_mut().map(|r| {
-                        let (w, h) = if *pixelbuffer {
-                            (self.rgb.w, self.rgb.h)
-                        } else {
-                            (self.texture.w, self.texture.h)
-                        };
                         r.write_frame(frame, w, h).ok();
                     });
                 }
+                if let Some(replay) = self.replay.as_mut() {
+                    replay.push(frame, w, h, std::time::Instant::now());
+                }
                 res
             }
             _ => Ok(false),
@@ -1658,6 +1730,7 @@ impl VideoHandler {
                 display_idx,
                 camera,
                 tx: None,
+                limits: RecordLimits::from_options(LocalConfig::get_option),
             })
             .map_or(Default::default(), |r| Arc::new(Mutex::new(Some(r))));
         } else {
@@ -1666,6 +1739,57 @@ impl VideoHandler {
 
         self.record = start;
     }
+
+    /// Whether the recording stopped because the disk ran low on space.
+    pub fn record_disk_full(&self) -> bool {
+        self.record
+            && self
+                .recorder
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map_or(false, |r| r.disk_full())
+    }
+
+    pub fn pause_record(&mut self, paused: bool) {
+        if let Some(r) = self.recorder.lock().unwrap().as_mut() {
+            r.set_paused(paused);
+        }
+    }
+
+    /// Whether the replay buffer needs a key frame to stay within its span.
+    pub fn replay_needs_key_frame(&mut self) -> bool {
+        self.replay.as_mut().map_or(false, |r| {
+            r.take_key_frame_request(std::time::Instant::now())
+        })
+    }
+
+    /// Save the replay buffer to a new file in the recording directory, `on_error` is called
+    /// from the saving thread if it fails.
+    pub fn save_replay(
+        &self,
+        id: String,
+        display_idx: usize,
+        camera: bool,
+        on_error: impl FnOnce(String) + Send + 'static,
+    ) -> ResultType<()> {
+        let Some(replay) = self.replay.as_ref() else {
+            bail!("Replay buffer is disabled");
+        };
+        replay.save(
+            RecorderContext {
+                server: false,
+                id,
+                dir: crate::ui_interface::video_save_directory(false),
+                display_idx,
+                camera,
+                tx: None,
+                limits: Default::default(),
+            },
+            on_error,
+        );
+        Ok(())
+    }
 }
 
 // The source of sent password
@@ -1754,7 +1878,10 @@ pub struct LoginConfigHandler {
     shared_password: Option<String>, // Store the shared password
     pub enable_trusted_devices: bool,
     pub record_state: bool,
+    pub record_paused: bool,
     pub record_permission: bool,
+    // The relay picked among the configured ones and its connect time.
+    pub relay: Option<(String, Duration)>,
 }
 
 impl Deref for LoginConfigHandler {
@@ -1857,12 +1984,14 @@ impl LoginConfigHandler {
         }
 
         self.direct = None;
+        self.relay = None;
         self.received = false;
         self.switch_uuid = switch_uuid;
         self.adapter_luid = adapter_luid;
         self.selected_windows_session_id = None;
         self.shared_password = shared_password;
         self.record_state = false;
+        self.record_paused = false;
         self.record_permission = true;
 
         // `std::env::remove_var("IS_TERMINAL_ADMIN");` is called in `session_add_sync()` - `flutter_ffi.rs`.
@@ -2455,6 +2584,15 @@ impl LoginConfigHandler {
         }
     }
 
+    /// Whether `display` is recorded.
+    pub fn records_display(&self, display: usize) -> bool {
+        let displays = self.get_option(OPTION_RECORD_DISPLAYS);
+        displays.is_empty()
+            || displays
+                .split(',')
+                .any(|d| d.trim().parse::<usize>().ok() == Some(display))
+    }
+
     #[inline]
     pub fn get_custom_resolution(&self, display: i32) -> Option<(i32, i32)> {
         self.config
@@ -2548,6 +2686,18 @@ impl LoginConfigHandler {
                 .options
                 .insert("force-always-relay".to_owned(), "Y".to_owned());
         }
+        // Kept for the peer card and `--get-sysinfo <id>`, dropped once the peer stops sharing it.
+        let sysinfo = serde_json::from_str::<serde_json::Value>(&pi.platform_additions)
+            .ok()
+            .and_then(|additions| additions.get("sysinfo").map(|x| x.to_string()));
+        match sysinfo {
+            Some(sysinfo) => {
+                config.options.insert("sysinfo".to_owned(), sysinfo);
+            }
+            None => {
+                config.options.remove("sysinfo");
+            }
+        }
         #[cfg(feature = "flutter")]
         {
             // sync connected password to personal ab automatically if it is not shared password
@@ -2766,6 +2916,8 @@ pub enum MediaData {
     AudioFormat(AudioFormat),
     Reset,
     RecordScreen(bool),
+    RecordPause(bool),
+    SaveReplay,
 }
 
 pub type MediaSender = mpsc::Sender<MediaData>;
@@ -2781,6 +2933,7 @@ pub fn start_video_thread<F, T>(
     video_receiver: mpsc::Receiver<MediaData>,
     video_queue: Arc<RwLock<ArrayQueue<VideoFrame>>>,
     fps: Arc<RwLock<Option<usize>>>,
+    frame_times: Arc<RwLock<FrameTimes>>,
     chroma: Arc<RwLock<Option<Chroma>>>,
     discard_queue: Arc<RwLock<bool>>,
     video_callback: F,
@@ -2797,11 +2950,40 @@ pub fn start_video_thread<F, T>(
         sync_cpu_usage();
         get_hwcodec_config();
         let mut video_handler = None;
+        let mut smoother = None;
+        // When to show the frame decoded last, its display and decoding time.
+        let mut pending_frame: Option<(std::time::Instant, usize, Duration)> = None;
         let mut count = 0;
         let mut duration = std::time::Duration::ZERO;
         let mut skip_beginning = 0;
         loop {
-            if let Ok(data) = video_receiver.recv() {
+            // A frame waiting behind its blended one is shown at its time, or before the next
+            // message is handled, without blocking the decoding.
+            let data = match pending_frame {
+                Some((at, ..)) => {
+                    let wait = at.saturating_duration_since(std::time::Instant::now());
+                    match video_receiver.recv_timeout(wait) {
+                        Ok(data) => Some(data),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match video_receiver.recv() {
+                    Ok(data) => Some(data),
+                    Err(_) => break,
+                },
+            };
+            if let Some((_, display, decoded)) = pending_frame.take() {
+                if let Some(handler) = video_handler.as_mut() {
+                    let render_start = std::time::Instant::now();
+                    video_callback(display, &mut handler.rgb, handler.texture.texture, true);
+                    frame_times
+                        .write()
+                        .unwrap()
+                        .add(decoded, render_start.elapsed());
+                }
+            }
+            if let Some(data) = data {
                 match data {
                     MediaData::VideoFrame(_) | MediaData::VideoQueue => {
                         let vf = match data {
@@ -2829,12 +3011,15 @@ pub fn start_video_thread<F, T>(
                         let format = CodecFormat::from(&vf);
                         if video_handler.is_none() {
                             let mut handler = VideoHandler::new(format, display);
-                            let record_state = session.lc.read().unwrap().record_state;
-                            let record_permission = session.lc.read().unwrap().record_permission;
-                            let id = session.lc.read().unwrap().id.clone();
-                            if record_state && record_permission {
-                                handler.record_screen(true, id, display, is_view_camera);
+                            let lc = session.lc.read().unwrap();
+                            if lc.record_state
+                                && lc.record_permission
+                                && lc.records_display(display)
+                            {
+                                handler.record_screen(true, lc.id.clone(), display, is_view_camera);
+                                handler.pause_record(lc.record_paused);
                             }
+                            drop(lc);
                             video_handler = Some(handler);
                         }
                         if let Some(handler) = video_handler.as_mut() {
@@ -2843,12 +3028,43 @@ pub fn start_video_thread<F, T>(
                             let format_changed = handler.decoder.format() != format;
                             match handler.handle_frame(vf, &mut pixelbuffer, &mut tmp_chroma) {
                                 Ok(true) => {
-                                    video_callback(
-                                        display,
-                                        &mut handler.rgb,
-                                        handler.texture.texture,
-                                        pixelbuffer,
-                                    );
+                                    let decoded = start.elapsed();
+                                    let smooth = pixelbuffer
+                                        && session.get_toggle_option(
+                                            smoothing::OPTION_SMOOTH_LOW_FPS.to_owned(),
+                                        );
+                                    if smooth {
+                                        let smoother = smoother.get_or_insert_with(|| {
+                                            smoothing::Smoother::new(&handler.rgb)
+                                        });
+                                        if let Some((blended, delay)) =
+                                            smoother.smooth(&handler.rgb, start)
+                                        {
+                                            video_callback(
+                                                display,
+                                                blended,
+                                                std::ptr::null_mut(),
+                                                true,
+                                            );
+                                            let at = std::time::Instant::now() + delay;
+                                            pending_frame = Some((at, display, decoded));
+                                        }
+                                    } else {
+                                        smoother = None;
+                                    }
+                                    if pending_frame.is_none() {
+                                        let render_start = std::time::Instant::now();
+                                        video_callback(
+                                            display,
+                                            &mut handler.rgb,
+                                            handler.texture.texture,
+                                            pixelbuffer,
+                                        );
+                                        frame_times
+                                            .write()
+                                            .unwrap()
+                                            .add(decoded, render_start.elapsed());
+                                    }
 
                                     // chroma
                                     if tmp_chroma.is_some() && last_chroma != tmp_chroma {
@@ -2883,6 +3099,20 @@ pub fn start_video_thread<F, T>(
                                 }
                                 _ => {}
                             }
+                            if handler.record_disk_full() {
+                                let id = session.lc.read().unwrap().id.clone();
+                                handler.record_screen(false, id, display, is_view_camera);
+                                session.record_screen(false);
+                                session.msgbox(
+                                    "custom-nook-nocancel-hasclose",
+                                    "Session recording",
+                                    "Recording stopped, the disk is almost full.",
+                                    "",
+                                );
+                            }
+                            if handler.replay_needs_key_frame() {
+                                session.refresh_video(display as _);
+                            }
                         }
 
                         // check invalid decoders
@@ -2912,15 +3142,45 @@ pub fn start_video_thread<F, T>(
                         }
                     }
                     MediaData::RecordScreen(start) => {
-                        let id = session.lc.read().unwrap().id.clone();
+                        let lc = session.lc.read().unwrap();
+                        let start = start && lc.records_display(display);
+                        let id = lc.id.clone();
+                        drop(lc);
                         if let Some(handler) = video_handler.as_mut() {
                             handler.record_screen(start, id, display, is_view_camera);
                         }
                     }
+                    MediaData::RecordPause(paused) => {
+                        if let Some(handler) = video_handler.as_mut() {
+                            handler.pause_record(paused);
+                        }
+                    }
+                    MediaData::SaveReplay => {
+                        if let Some(handler) = video_handler.as_ref() {
+                            let id = session.lc.read().unwrap().id.clone();
+                            let report = |session: Session<T>| {
+                                move |err: String| {
+                                    log::error!("Failed to save replay: {}", err);
+                                    session.msgbox(
+                                        "custom-nook-nocancel-hasclose",
+                                        "Replay",
+                                        &err,
+                                        "",
+                                    );
+                                }
+                            };
+                            if let Err(e) = handler.save_replay(
+                                id,
+                                display,
+                                is_view_camera,
+                                report(session.clone()),
+                            ) {
+                                report(session.clone())(e.to_string());
+                            }
+                        }
+                    }
                     _ => {}
                 }
-            } else {
-                break;
             }
         }
         log::info!("Video decoder loop exits");
@@ -3670,6 +3930,13 @@ pub enum Data {
     Message(Message),
     SendFiles((i32, JobType, String, String, i32, bool, bool)),
     RemoveDirAll((i32, String, bool, bool)),
+    SyncFolders((i32, String, String, bool)),
+    // (id, remote directory, local directory, include hidden)
+    DownloadArchive((i32, String, String, bool)),
+    // (id, remote path, destination session, destination path, include hidden)
+    RelayFiles((i32, String, Uuid, String, bool)),
+    // (id, downloaded path, destination path, include hidden)
+    RelayUpload((i32, String, String, bool)),
     ConfirmDeleteFiles((i32, i32)),
     SetNoConfirm(i32),
     RemoveDir((i32, String)),
@@ -3685,6 +3952,8 @@ pub enum Data {
     AddJob((i32, JobType, String, String, i32, bool, bool)),
     ResumeJob((i32, bool)),
     RecordScreen(bool),
+    RecordPause(bool),
+    SaveReplay,
     ElevateDirect,
     ElevateWithLogon(String, String),
     NewVoiceCall,
@@ -4175,3 +4444,23 @@ async fn udp_nat_connect(
         })?;
     Ok((res.1, Some(res.0), typ))
 }
+
+// Probes the ports a batch at a time, which share the timeout, so few sockets are open at once.
+async fn probe_ports(
+    ip: IpAddr,
+    ports: Vec<u16>,
+    local_addr: SocketAddr,
+    ms_timeout: u64,
+) -> ResultType<(Stream, Option<KcpStream>, &'static str)> {
+    let batches = ports.chunks(crate::BIRTHDAY_BATCH);
+    let ms_timeout = ms_timeout / batches.len().max(1) as u64;
+    for batch in batches {
+        let probes = batch.iter().map(|port| {
+            connect_tcp_local(SocketAddr::new(ip, *port), Some(local_addr), ms_timeout).boxed()
+        });
+        if let Ok((conn, _)) = select_ok(probes).await {
+            return Ok((conn, None, "TCP"));
+        }
+    }
+    bail!("No probed port of the peer answered");
+}
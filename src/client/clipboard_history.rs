@@ -0,0 +1,134 @@
+use hbb_common::{
+    compress::decompress,
+    config::Config,
+    get_time,
+    message_proto::{Clipboard, ClipboardFormat},
+};
+use serde_json::json;
+use std::collections::VecDeque;
+
+// Number of copies kept per session, "0" turns the history off.
+const OPTION_CLIPBOARD_HISTORY_SIZE: &str = "clipboard-history-size";
+const DEFAULT_SIZE: usize = 20;
+const PREVIEW_CHARS: usize = 200;
+
+pub struct Entry {
+    // Copied on the remote side, otherwise sent from here.
+    pub remote: bool,
+    pub time: i64,
+    pub clipboards: Vec<Clipboard>,
+}
+
+/// The last copies exchanged in a session, newest first.
+#[derive(Default)]
+pub struct ClipboardHistory {
+    entries: VecDeque<Entry>,
+}
+
+impl ClipboardHistory {
+    pub fn push(&mut self, clipboards: Vec<Clipboard>, remote: bool) {
+        let size = Config::get_option(OPTION_CLIPBOARD_HISTORY_SIZE)
+            .parse()
+            .unwrap_or(DEFAULT_SIZE);
+        self.push_(clipboards, remote, size);
+    }
+
+    fn push_(&mut self, clipboards: Vec<Clipboard>, remote: bool, size: usize) {
+        if clipboards.is_empty() {
+            return;
+        }
+        // Pasting an entry sends it again, it moves to the front instead of showing up twice.
+        if let Some(pos) = self.entries.iter().position(|e| e.clipboards == clipboards) {
+            self.entries.remove(pos);
+        }
+        self.entries.push_front(Entry {
+            remote,
+            time: get_time(),
+            clipboards,
+        });
+        self.entries.truncate(size);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Entry> {
+        self.entries.get(index)
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, e)| {
+                json!({
+                    "index": index,
+                    "remote": e.remote,
+                    "time": e.time,
+                    "formats": e.clipboards.iter().map(format_name).collect::<Vec<_>>(),
+                    "preview": preview(&e.clipboards),
+                })
+            })
+            .collect();
+        serde_json::to_string(&entries).unwrap_or_default()
+    }
+}
+
+fn format_name(c: &Clipboard) -> &'static str {
+    match c.format.enum_value() {
+        Ok(ClipboardFormat::Text) => "text",
+        Ok(ClipboardFormat::Rtf) => "rtf",
+        Ok(ClipboardFormat::Html) => "html",
+        Ok(ClipboardFormat::ImageRgba)
+        | Ok(ClipboardFormat::ImagePng)
+        | Ok(ClipboardFormat::ImageSvg) => "image",
+        _ => "special",
+    }
+}
+
+fn preview(clipboards: &[Clipboard]) -> String {
+    let Some(c) = clipboards
+        .iter()
+        .find(|c| c.format.enum_value() == Ok(ClipboardFormat::Text))
+    else {
+        return "".to_owned();
+    };
+    let content = if c.compress {
+        decompress(&c.content)
+    } else {
+        c.content.to_vec()
+    };
+    String::from_utf8_lossy(&content)
+        .chars()
+        .take(PREVIEW_CHARS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Vec<Clipboard> {
+        vec![Clipboard {
+            content: s.as_bytes().to_vec().into(),
+            format: ClipboardFormat::Text.into(),
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn test_clipboard_history() {
+        let mut history = ClipboardHistory::default();
+        history.push_(text("a"), false, 2);
+        history.push_(text("b"), true, 2);
+        history.push_(vec![], true, 2);
+        assert_eq!(preview(&history.get(0).unwrap().clipboards), "b");
+        assert!(history.get(0).unwrap().remote);
+        history.push_(text("a"), false, 2);
+        assert_eq!(preview(&history.get(0).unwrap().clipboards), "a");
+        assert_eq!(preview(&history.get(1).unwrap().clipboards), "b");
+        history.push_(text("c"), false, 2);
+        assert!(history.get(2).is_none());
+        assert_eq!(preview(&history.get(1).unwrap().clipboards), "a");
+        history.push_(text("d"), false, 0);
+        assert!(history.get(0).is_none());
+    }
+}
@@ -161,6 +161,31 @@ pub trait FileManager: Interface {
         )));
     }
 
+    fn sync_folders(&self, id: i32, local: String, remote: String, include_hidden: bool) {
+        self.send(Data::SyncFolders((id, local, remote, include_hidden)));
+    }
+
+    fn download_archive(&self, id: i32, path: String, to: String, include_hidden: bool) {
+        self.send(Data::DownloadArchive((id, path, to, include_hidden)));
+    }
+
+    fn relay_files(
+        &self,
+        id: i32,
+        path: String,
+        to_session_id: uuid::Uuid,
+        to: String,
+        include_hidden: bool,
+    ) {
+        self.send(Data::RelayFiles((
+            id,
+            path,
+            to_session_id,
+            to,
+            include_hidden,
+        )));
+    }
+
     fn resume_job(&self, id: i32, is_remote: bool) {
         self.send(Data::ResumeJob((id, is_remote)));
     }
@@ -0,0 +1,124 @@
+use hbb_common::message_proto::{FileEntry, FileType};
+use serde_derive::Serialize;
+use std::collections::HashMap;
+
+/// A two-way sync of a local and a remote folder, waiting for the remote file list.
+pub struct SyncJob {
+    pub local: String,
+    pub remote: String,
+    pub include_hidden: bool,
+    pub local_entries: Vec<FileEntry>,
+    // Seconds since the epoch, 0 if the folders were never synced.
+    pub last_sync: u64,
+}
+
+/// Relative names, separated by `/`, of the files to copy.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SyncPlan {
+    pub upload: Vec<String>,
+    pub download: Vec<String>,
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Conflict {
+    pub name: String,
+    pub local_size: u64,
+    pub local_modified: u64,
+    pub remote_size: u64,
+    pub remote_modified: u64,
+}
+
+/// Peer option keeping the time of the last sync of the two folders.
+pub fn option_key(local: &str, remote: &str) -> String {
+    format!("folder-sync:{}|{}", local, remote)
+}
+
+/// Files only on one side are copied to the other, deletions are not synced.
+///
+/// A file that differs in size or modification time is copied from the side that changed it
+/// since the last sync. Changed on both sides, or on neither, it is a conflict left to the user.
+/// Identical content with a different time is still skipped by the digest check of the transfer.
+pub fn compare(local: &[FileEntry], remote: &[FileEntry], last_sync: u64) -> SyncPlan {
+    let local = files_by_name(local);
+    let mut remote = files_by_name(remote);
+    let mut plan = SyncPlan::default();
+    for (name, l) in local {
+        let Some(r) = remote.remove(&name) else {
+            plan.upload.push(name);
+            continue;
+        };
+        if l.size == r.size && l.modified_time == r.modified_time {
+            continue;
+        }
+        match (l.modified_time > last_sync, r.modified_time > last_sync) {
+            (true, false) => plan.upload.push(name),
+            (false, true) => plan.download.push(name),
+            _ => plan.conflicts.push(Conflict {
+                name,
+                local_size: l.size,
+                local_modified: l.modified_time,
+                remote_size: r.size,
+                remote_modified: r.modified_time,
+            }),
+        }
+    }
+    plan.download.extend(remote.into_keys());
+    plan.upload.sort();
+    plan.download.sort();
+    plan.conflicts.sort_by(|a, b| a.name.cmp(&b.name));
+    plan
+}
+
+fn files_by_name(entries: &[FileEntry]) -> HashMap<String, &FileEntry> {
+    entries
+        .iter()
+        .filter(|e| {
+            !matches!(
+                e.entry_type.enum_value(),
+                Ok(FileType::Dir) | Ok(FileType::DirLink) | Ok(FileType::DirDrive)
+            )
+        })
+        .map(|e| (e.name.replace('\\', "/"), e))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64, modified_time: u64) -> FileEntry {
+        FileEntry {
+            name: name.to_owned(),
+            size,
+            modified_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compare_folders() {
+        let local = vec![
+            file("a", 1, 10),
+            file("b\\c", 1, 10),
+            file("d", 1, 30),
+            file("e", 1, 10),
+            file("f", 1, 30),
+        ];
+        let remote = vec![
+            file("b/c", 1, 10),
+            file("d", 2, 10),
+            file("e", 2, 30),
+            file("f", 2, 30),
+            file("g", 1, 10),
+        ];
+        let plan = compare(&local, &remote, 20);
+        assert_eq!(plan.upload, vec!["a", "d"]);
+        assert_eq!(plan.download, vec!["e", "g"]);
+        assert_eq!(plan.conflicts.len(), 1);
+        assert_eq!(plan.conflicts[0].name, "f");
+        // Never synced, every difference is a conflict.
+        let plan = compare(&local, &remote, 0);
+        assert_eq!(plan.conflicts.len(), 3);
+    }
+}
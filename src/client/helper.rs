@@ -3,7 +3,7 @@ use hbb_common::{
     message_proto::{Message, VoiceCallRequest, VoiceCallResponse},
 };
 use scrap::CodecFormat;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 #[derive(Debug, Default)]
 pub struct QualityStatus {
@@ -13,6 +13,37 @@ pub struct QualityStatus {
     pub target_bitrate: Option<i32>,
     pub codec_format: Option<CodecFormat>,
     pub chroma: Option<String>,
+    // Averages in ms of the stages on this side, the slowest display.
+    pub decode_time: Option<i32>,
+    pub render_time: Option<i32>,
+    // The picked relay server and its connect time.
+    pub relay: Option<String>,
+}
+
+/// Time spent on received frames, between two quality status updates.
+#[derive(Debug, Default)]
+pub struct FrameTimes {
+    decode: Duration,
+    render: Duration,
+    count: u32,
+}
+
+impl FrameTimes {
+    pub fn add(&mut self, decode: Duration, render: Duration) {
+        self.decode += decode;
+        self.render += render;
+        self.count += 1;
+    }
+
+    /// The average decode and render times in ms, and starts over.
+    pub fn take(&mut self) -> Option<(i32, i32)> {
+        let times = std::mem::take(self);
+        if times.count == 0 {
+            return None;
+        }
+        let avg = |d: Duration| (d / times.count).as_millis() as i32;
+        Some((avg(times.decode), avg(times.render)))
+    }
 }
 
 #[inline]
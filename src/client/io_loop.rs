@@ -4,10 +4,13 @@ use crate::clipboard::{update_clipboard, ClipboardSide};
 use crate::{audio_service, clipboard::CLIPBOARD_INTERVAL, ConnInner, CLIENT_SERVER};
 use crate::{
     client::{
-        self, new_voice_call_request, Client, Data, Interface, MediaData, MediaSender,
+        self,
+        folder_sync::{self, SyncJob},
+        new_voice_call_request, Client, Data, FrameTimes, Interface, MediaData, MediaSender,
         QualityStatus, MILLI1, SEC30,
     },
     common::get_default_sound_input,
+    transfer_limit::{self, TransferLimiter},
     ui_session_interface::{InvokeUiSession, Session},
 };
 #[cfg(feature = "unix-file-copy-paste")]
@@ -53,6 +56,35 @@ use std::{
     },
 };
 
+// The controlled side sends `TestDelay` about every second, so a direct connection that
+// stays silent this long is considered lost and the session reconnects through the relay.
+const DIRECT_PATH_TIMEOUT: Duration = Duration::from_secs(8);
+
+struct RelayDownload {
+    to_session_id: uuid::Uuid,
+    to: String,
+    dir: PathBuf,
+    path: String,
+    include_hidden: bool,
+}
+
+// Only the flutter ui keeps several sessions in one process.
+#[cfg(feature = "flutter")]
+fn send_to_session(session_id: &uuid::Uuid, data: Data) -> bool {
+    match crate::flutter::sessions::get_session_by_session_id(session_id) {
+        Some(session) => {
+            session.send(data);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(not(feature = "flutter"))]
+fn send_to_session(_session_id: &uuid::Uuid, _data: Data) -> bool {
+    false
+}
+
 pub struct Remote<T: InvokeUiSession> {
     handler: Session<T>,
     audio_sender: MediaSender,
@@ -64,6 +96,14 @@ pub struct Remote<T: InvokeUiSession> {
     read_jobs: Vec<fs::TransferJob>,
     write_jobs: Vec<fs::TransferJob>,
     remove_jobs: HashMap<i32, RemoveJob>,
+    sync_jobs: HashMap<i32, SyncJob>,
+    // Archives being downloaded, by job id, with the directory to unpack them to.
+    archive_jobs: HashMap<i32, (PathBuf, PathBuf)>,
+    // Downloads going on to another session, by job id.
+    relay_downloads: HashMap<i32, RelayDownload>,
+    // Uploads of relayed files, by job id, with the folder to remove once done.
+    relay_uploads: HashMap<i32, PathBuf>,
+    transfer_limiter: TransferLimiter,
     timer: crate::RustDeskInterval,
     last_update_jobs_status: (Instant, HashMap<i32, u64>),
     is_connected: bool,
@@ -111,6 +151,11 @@ impl<T: InvokeUiSession> Remote<T> {
             read_jobs: Vec::new(),
             write_jobs: Vec::new(),
             remove_jobs: Default::default(),
+            sync_jobs: Default::default(),
+            archive_jobs: Default::default(),
+            relay_downloads: Default::default(),
+            relay_uploads: Default::default(),
+            transfer_limiter: Default::default(),
             timer: crate::rustdesk_interval(time::interval(SEC30)),
             last_update_jobs_status: (Instant::now(), Default::default()),
             is_connected: false,
@@ -226,7 +271,11 @@ impl<T: InvokeUiSession> Remote<T> {
                             if let Some(res) = res {
                                 match res {
                                     Err(err) => {
-                                        self.handler.on_establish_connection_error(err.to_string());
+                                        if self.can_reconnect_through_relay(direct, received) {
+                                            self.reconnect_through_relay(&err.to_string());
+                                        } else {
+                                            self.handler.on_establish_connection_error(err.to_string());
+                                        }
                                         break;
                                     }
                                     Ok(ref bytes) => {
@@ -269,9 +318,13 @@ impl<T: InvokeUiSession> Remote<T> {
                                 break;
                             }
                             if !self.read_jobs.is_empty() {
-                                if let Err(err) = fs::handle_read_jobs(&mut self.read_jobs, &mut peer).await {
-                                    self.handler.msgbox("error", "Connection Error", &err.to_string(), "");
-                                    break;
+                                let transferred = self.read_jobs.iter().map(|j| j.transferred()).sum();
+                                let limit = transfer_limit::parse_limit(&LocalConfig::get_option(transfer_limit::OPTION_FILE_TRANSFER_SPEED_LIMIT));
+                                if self.transfer_limiter.ready(transferred, limit) {
+                                    if let Err(err) = fs::handle_read_jobs(&mut self.read_jobs, &mut peer).await {
+                                        self.handler.msgbox("error", "Connection Error", &err.to_string(), "");
+                                        break;
+                                    }
                                 }
                                 self.update_jobs_status();
                             } else {
@@ -279,6 +332,10 @@ impl<T: InvokeUiSession> Remote<T> {
                             }
                         }
                         _ = status_timer.tick() => {
+                            if last_recv_time.elapsed() >= DIRECT_PATH_TIMEOUT && self.can_reconnect_through_relay(direct, received) {
+                                self.reconnect_through_relay("timeout");
+                                break;
+                            }
                             let elapsed = fps_instant.elapsed().as_millis();
                             if elapsed < 1000 {
                                 continue;
@@ -296,6 +353,11 @@ impl<T: InvokeUiSession> Remote<T> {
                                 *v.frame_count.write().unwrap() = 0;
                             });
                             self.fps_control(direct, fps.clone());
+                            let frame_times = self
+                                .video_threads
+                                .values()
+                                .filter_map(|v| v.frame_times.write().unwrap().take())
+                                .reduce(|a, b| (a.0.max(b.0), a.1.max(b.1)));
                             let chroma = self.chroma.read().unwrap().clone();
                             let chroma = match chroma {
                                 Some(Chroma::I444) => "4:4:4",
@@ -308,11 +370,22 @@ impl<T: InvokeUiSession> Remote<T> {
                             } else {
                                 Some(self.video_format.clone())
                             };
+                            let relay = self
+                                .handler
+                                .lc
+                                .read()
+                                .unwrap()
+                                .relay
+                                .as_ref()
+                                .map(|(server, rtt)| format!("{server} {}ms", rtt.as_millis()));
                             self.handler.update_quality_status(QualityStatus {
                                 speed: Some(speed),
                                 fps,
                                 chroma,
                                 codec_format,
+                                decode_time: frame_times.map(|t| t.0),
+                                render_time: frame_times.map(|t| t.1),
+                                relay,
                                 ..Default::default()
                             });
                         }
@@ -354,6 +427,31 @@ impl<T: InvokeUiSession> Remote<T> {
         }
     }
 
+    // Only an established direct session of a remote control or camera connection reconnects,
+    // file transfer jobs and terminals are not restored by a reconnect.
+    fn can_reconnect_through_relay(&self, direct: bool, received: bool) -> bool {
+        direct
+            && received
+            && (self.handler.is_default() || self.handler.is_view_camera())
+            && !self.handler.is_restarting_remote_device()
+    }
+
+    // A new session through the relay, the old one is closed rather than handed over.
+    fn reconnect_through_relay(&self, reason: &str) {
+        log::info!(
+            "Direct connection to {} lost ({}), reconnect through relay",
+            self.handler.get_id(),
+            reason
+        );
+        self.handler.msgbox(
+            "connecting",
+            "Connecting...",
+            "The direct connection was lost, reconnecting through the relay server.",
+            "",
+        );
+        self.handler.reconnect(true);
+    }
+
     #[cfg(any(target_os = "windows", feature = "unix-file-copy-paste"))]
     async fn handle_local_clipboard_msg(
         &self,
@@ -407,6 +505,52 @@ impl<T: InvokeUiSession> Remote<T> {
         }
     }
 
+    // Copies the differences as jobs of their own, the conflicts are left to the user.
+    fn start_folder_sync(&mut self, id: i32, job: SyncJob, remote_entries: &[FileEntry]) {
+        let plan = folder_sync::compare(&job.local_entries, remote_entries, job.last_sync);
+        let local_sep = self.handler.get_path_sep(false);
+        let remote_sep = self.handler.get_path_sep(true);
+        let join = |root: &str, sep: &str, name: &str| {
+            format!(
+                "{}{}{}",
+                root.trim_end_matches(['/', '\\']),
+                sep,
+                name.replace('/', sep)
+            )
+        };
+        let transfers = plan
+            .upload
+            .iter()
+            .map(|name| (name, false))
+            .chain(plan.download.iter().map(|name| (name, true)));
+        for (name, is_remote) in transfers {
+            let local = join(&job.local, local_sep, name);
+            let remote = join(&job.remote, remote_sep, name);
+            let (path, to) = if is_remote {
+                (remote, local)
+            } else {
+                (local, remote)
+            };
+            let job_id = fs::get_next_job_id();
+            fs::update_next_job_id(job_id + 1);
+            allow_err!(self.sender.send(Data::SendFiles((
+                job_id,
+                fs::JobType::Generic,
+                path,
+                to,
+                0,
+                job.include_hidden,
+                is_remote,
+            ))));
+        }
+        self.handler.lc.write().unwrap().set_option(
+            folder_sync::option_key(&job.local, &job.remote),
+            (get_time() / 1000).to_string(),
+        );
+        self.handler
+            .update_folder_sync(id, serde_json::to_string(&plan).unwrap_or_default());
+    }
+
     fn handle_job_status(&mut self, id: i32, file_num: i32, err: Option<String>) {
         if let Some(job) = self.remove_jobs.get_mut(&id) {
             if job.no_confirm {
@@ -427,6 +571,19 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
             }
         }
+        if let Some((archive, to)) = self.archive_jobs.remove(&id) {
+            if err.is_none() {
+                // Unpacking takes a while for big directories, the job is done once it is.
+                let handler = self.handler.clone();
+                std::thread::spawn(move || match crate::file_archive::extract(&archive, &to) {
+                    Ok(()) => handler.job_done(id, file_num),
+                    Err(err) => handler.job_error(id, err.to_string(), file_num),
+                });
+                return;
+            }
+            std::fs::remove_file(&archive).ok();
+        }
+        self.on_relay_job_done(id, err.is_none());
         if let Some(err) = err {
             self.handler.job_error(id, err, file_num);
         } else {
@@ -434,6 +591,30 @@ impl<T: InvokeUiSession> Remote<T> {
         }
     }
 
+    // Relayed files go on to the destination session once downloaded, and are removed once
+    // uploaded from there.
+    fn on_relay_job_done(&mut self, id: i32, ok: bool) {
+        if let Some(relay) = self.relay_downloads.remove(&id) {
+            if ok {
+                let upload_id = fs::get_next_job_id();
+                let upload =
+                    Data::RelayUpload((upload_id, relay.path, relay.to, relay.include_hidden));
+                if send_to_session(&relay.to_session_id, upload) {
+                    fs::update_next_job_id(upload_id + 1);
+                    return;
+                }
+                log::warn!(
+                    "Destination session {} of relay job {} is gone",
+                    relay.to_session_id,
+                    id
+                );
+            }
+            allow_err!(std::fs::remove_dir_all(&relay.dir));
+        } else if let Some(dir) = self.relay_uploads.remove(&id) {
+            allow_err!(std::fs::remove_dir_all(&dir));
+        }
+    }
+
     fn stop_voice_call(&mut self) {
         let voice_call_sender = std::mem::replace(&mut self.stop_voice_call_sender, None);
         if let Some(stopper) = voice_call_sender {
@@ -563,6 +744,20 @@ impl<T: InvokeUiSession> Remote<T> {
                         }
                         _ => {}
                     },
+                    Some(message::Union::Clipboard(cb)) => {
+                        self.handler
+                            .clipboard_history
+                            .lock()
+                            .unwrap()
+                            .push(vec![cb.clone()], false);
+                    }
+                    Some(message::Union::MultiClipboards(mcb)) => {
+                        self.handler
+                            .clipboard_history
+                            .lock()
+                            .unwrap()
+                            .push(mcb.clipboards.clone(), false);
+                    }
                     _ => {}
                 }
                 allow_err!(peer.send(&msg).await);
@@ -810,6 +1005,102 @@ impl<T: InvokeUiSession> Remote<T> {
                     }
                 }
             }
+            Data::SyncFolders((id, local, remote, include_hidden)) => {
+                match fs::get_recursive_files(&local, include_hidden) {
+                    Err(err) => {
+                        self.handle_job_status(id, -1, Some(err.to_string()));
+                    }
+                    Ok(local_entries) => {
+                        let last_sync = self
+                            .handler
+                            .lc
+                            .read()
+                            .unwrap()
+                            .get_option(&folder_sync::option_key(&local, &remote))
+                            .parse()
+                            .unwrap_or(0);
+                        let mut msg_out = Message::new();
+                        let mut file_action = FileAction::new();
+                        file_action.set_all_files(ReadAllFiles {
+                            id,
+                            path: remote.clone(),
+                            include_hidden,
+                            ..Default::default()
+                        });
+                        msg_out.set_file_action(file_action);
+                        allow_err!(peer.send(&msg_out).await);
+                        self.sync_jobs.insert(
+                            id,
+                            SyncJob {
+                                local,
+                                remote,
+                                include_hidden,
+                                local_entries,
+                                last_sync,
+                            },
+                        );
+                    }
+                }
+            }
+            Data::DownloadArchive((id, path, to, include_hidden)) => {
+                let archive = std::env::temp_dir()
+                    .join(format!("rustdesk-archive-{}.tar.zst", uuid::Uuid::new_v4()));
+                let local = get_string(&archive);
+                self.archive_jobs.insert(id, (archive, PathBuf::from(to)));
+                allow_err!(self.sender.send(Data::SendFiles((
+                    id,
+                    fs::JobType::Generic,
+                    crate::file_archive::request_path(&path),
+                    local,
+                    0,
+                    include_hidden,
+                    true,
+                ))));
+            }
+            Data::RelayFiles((id, path, to_session_id, to, include_hidden)) => {
+                let name = path
+                    .rsplit(['/', '\\'])
+                    .find(|s| !s.is_empty())
+                    .unwrap_or("relay")
+                    .to_owned();
+                let dir = std::env::temp_dir()
+                    .join("rustdesk-relay")
+                    .join(uuid::Uuid::new_v4().to_string());
+                let local = get_string(&dir.join(name));
+                self.relay_downloads.insert(
+                    id,
+                    RelayDownload {
+                        to_session_id,
+                        to,
+                        dir,
+                        path: local.clone(),
+                        include_hidden,
+                    },
+                );
+                allow_err!(self.sender.send(Data::SendFiles((
+                    id,
+                    fs::JobType::Generic,
+                    path,
+                    local,
+                    0,
+                    include_hidden,
+                    true,
+                ))));
+            }
+            Data::RelayUpload((id, path, to, include_hidden)) => {
+                if let Some(dir) = PathBuf::from(&path).parent() {
+                    self.relay_uploads.insert(id, dir.to_path_buf());
+                }
+                allow_err!(self.sender.send(Data::SendFiles((
+                    id,
+                    fs::JobType::Generic,
+                    path,
+                    to,
+                    0,
+                    include_hidden,
+                    false,
+                ))));
+            }
             Data::RemoveDirAll((id, path, is_remote, include_hidden)) => {
                 let sep = self.handler.get_path_sep(is_remote);
                 if is_remote {
@@ -936,9 +1227,23 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
             }
             Data::RecordScreen(start) => {
-                self.handler.lc.write().unwrap().record_state = start;
+                let mut lc = self.handler.lc.write().unwrap();
+                lc.record_state = start;
+                lc.record_paused = false;
+                drop(lc);
                 self.update_record_state();
             }
+            Data::RecordPause(paused) => {
+                self.handler.lc.write().unwrap().record_paused = paused;
+                for (_, v) in self.video_threads.iter_mut() {
+                    v.video_sender.send(MediaData::RecordPause(paused)).ok();
+                }
+            }
+            Data::SaveReplay => {
+                for (_, v) in self.video_threads.iter_mut() {
+                    v.video_sender.send(MediaData::SaveReplay).ok();
+                }
+            }
             Data::ElevateDirect => {
                 let mut request = ElevationRequest::new();
                 request.set_direct(true);
@@ -1423,6 +1728,11 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
                 Some(message::Union::Clipboard(cb)) => {
                     if !self.handler.lc.read().unwrap().disable_clipboard.v {
+                        self.handler
+                            .clipboard_history
+                            .lock()
+                            .unwrap()
+                            .push(vec![cb.clone()], true);
                         #[cfg(not(any(target_os = "android", target_os = "ios")))]
                         update_clipboard(vec![cb], ClipboardSide::Client);
                         #[cfg(target_os = "ios")]
@@ -1442,6 +1752,11 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
                 Some(message::Union::MultiClipboards(_mcb)) => {
                     if !self.handler.lc.read().unwrap().disable_clipboard.v {
+                        self.handler
+                            .clipboard_history
+                            .lock()
+                            .unwrap()
+                            .push(_mcb.clipboards.clone(), true);
                         #[cfg(not(any(target_os = "android", target_os = "ios")))]
                         update_clipboard(_mcb.clipboards, ClipboardSide::Client);
                         #[cfg(target_os = "android")]
@@ -1476,6 +1791,8 @@ impl<T: InvokeUiSession> Remote<T> {
                                 job.set_finished_size_on_resume();
                             } else if let Some(job) = self.remove_jobs.get_mut(&fd.id) {
                                 job.files = entries;
+                            } else if let Some(job) = self.sync_jobs.remove(&fd.id) {
+                                self.start_folder_sync(fd.id, job, &entries);
                             }
                         }
                         Some(file_response::Union::Digest(digest)) => {
@@ -2308,12 +2625,14 @@ impl<T: InvokeUiSession> Remote<T> {
         let (video_sender, video_receiver) = std::sync::mpsc::channel::<MediaData>();
         let decode_fps = Arc::new(RwLock::new(None));
         let frame_count = Arc::new(RwLock::new(0));
+        let frame_times = Arc::new(RwLock::new(Default::default()));
         let discard_queue = Arc::new(RwLock::new(false));
         let video_thread = VideoThread {
             video_queue: video_queue.clone(),
             video_sender,
             decode_fps: decode_fps.clone(),
             frame_count: frame_count.clone(),
+            frame_times: frame_times.clone(),
             fps_control: Default::default(),
             discard_queue: discard_queue.clone(),
         };
@@ -2324,6 +2643,7 @@ impl<T: InvokeUiSession> Remote<T> {
             video_receiver,
             video_queue,
             decode_fps,
+            frame_times,
             self.chroma.clone(),
             discard_queue,
             move |display: usize,
@@ -2418,6 +2738,7 @@ struct VideoThread {
     video_sender: MediaSender,
     decode_fps: Arc<RwLock<Option<usize>>>,
     frame_count: Arc<RwLock<usize>>,
+    frame_times: Arc<RwLock<FrameTimes>>,
     discard_queue: Arc<RwLock<bool>>,
     fps_control: FpsControl,
 }
@@ -0,0 +1,90 @@
+//! Picks the relay server when several are configured.
+//!
+//! The relay from the rendezvous server and the ones in `relay-servers` are probed with a TCP
+//! connect and the fastest wins. The choice is pinned to the peer, later sessions keep it until
+//! another relay is clearly faster or it stops answering.
+
+use crate::check_port;
+use hbb_common::{
+    config::{LocalConfig, RELAY_PORT},
+    futures::future::join_all,
+    log,
+    socket_client::connect_tcp,
+};
+use std::time::{Duration, Instant};
+
+// Comma separated relay servers to choose from, besides the one of the rendezvous server.
+pub const OPTION_RELAY_SERVERS: &str = "relay-servers";
+pub const PEER_OPTION_RELAY_SERVER: &str = "relay-server";
+const PROBE_TIMEOUT: u64 = 1_000;
+
+fn candidates(default: &str) -> Vec<String> {
+    let mut servers = vec![default.to_owned()];
+    for server in LocalConfig::get_option(OPTION_RELAY_SERVERS)
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+    {
+        if !servers.iter().any(|x| x == server) {
+            servers.push(server.to_owned());
+        }
+    }
+    servers
+}
+
+async fn probe(server: String) -> Option<Duration> {
+    let start = Instant::now();
+    connect_tcp(check_port(server, RELAY_PORT), PROBE_TIMEOUT)
+        .await
+        .ok()?;
+    Some(start.elapsed())
+}
+
+/// The relay to use and its connect time, no time if only `default` is configured or no relay
+/// answered.
+pub async fn select(default: &str, pinned: &str) -> (String, Option<Duration>) {
+    let servers = candidates(default);
+    if servers.len() == 1 {
+        return (default.to_owned(), None);
+    }
+    let rtts = join_all(servers.iter().cloned().map(probe)).await;
+    let probed: Vec<_> = servers
+        .into_iter()
+        .zip(rtts)
+        .filter_map(|(server, rtt)| Some((server, rtt?)))
+        .collect();
+    log::info!("relay servers probed: {:?}", probed);
+    match choose(&probed, pinned) {
+        Some((server, rtt)) => (server.clone(), Some(*rtt)),
+        None => (default.to_owned(), None),
+    }
+}
+
+// The pinned relay is kept unless another one is faster by a quarter.
+fn choose<'a>(probed: &'a [(String, Duration)], pinned: &str) -> Option<&'a (String, Duration)> {
+    let best = probed.iter().min_by_key(|x| x.1)?;
+    match probed.iter().find(|x| x.0 == pinned) {
+        Some(x) if best.1 * 4 >= x.1 * 3 => Some(x),
+        _ => Some(best),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose() {
+        let ms = Duration::from_millis;
+        let probed = vec![
+            ("a".to_owned(), ms(40)),
+            ("b".to_owned(), ms(30)),
+            ("c".to_owned(), ms(50)),
+        ];
+        assert_eq!(choose(&probed, "").unwrap().0, "b");
+        assert_eq!(choose(&probed, "a").unwrap().0, "a");
+        assert_eq!(choose(&probed, "c").unwrap().0, "b");
+        assert_eq!(choose(&probed, "d").unwrap().0, "b");
+        assert!(choose(&[], "a").is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! The last seconds of the video of a display, kept so they can be saved after something
+//! went wrong, without recording the whole session.
+//!
+//! Frames stay encoded and the buffer always starts with a key frame, so saving only has to
+//! pass them through the recorder.
+
+use hbb_common::{
+    log,
+    message_proto::{video_frame, EncodedVideoFrame},
+    ResultType,
+};
+use scrap::record::{Recorder, RecorderContext};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+pub const OPTION_REPLAY_BUFFER_SECONDS: &str = "replay-buffer-seconds";
+// The buffer can only be cut at key frames, this bounds it while it waits for one.
+const MAX_BYTES: usize = 256 * 1024 * 1024;
+
+struct Entry {
+    frame: video_frame::Union,
+    w: usize,
+    h: usize,
+    time: Instant,
+    key: bool,
+    size: usize,
+}
+
+pub struct ReplayBuffer {
+    span: Duration,
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    key_frame_requested: Option<Instant>,
+}
+
+impl ReplayBuffer {
+    pub fn new(span: Duration) -> Self {
+        Self {
+            span,
+            entries: Default::default(),
+            bytes: 0,
+            key_frame_requested: None,
+        }
+    }
+
+    pub fn push(&mut self, frame: &video_frame::Union, w: usize, h: usize, now: Instant) {
+        let frames = encoded_frames(frame);
+        let key = frames.iter().any(|f| f.key);
+        if self.entries.is_empty() && !key {
+            return;
+        }
+        let size = frames.iter().map(|f| f.data.len()).sum();
+        self.bytes += size;
+        self.entries.push_back(Entry {
+            frame: frame.clone(),
+            w,
+            h,
+            time: now,
+            key,
+            size,
+        });
+        self.trim(now);
+    }
+
+    fn trim(&mut self, now: Instant) {
+        // Drop whole groups of frames while the rest still covers the span.
+        while let Some(next_key) = self.entries.iter().skip(1).position(|e| e.key) {
+            let next_key = next_key + 1;
+            let Some(start) = now.checked_sub(self.span) else {
+                break;
+            };
+            if self.entries[next_key].time > start && self.bytes <= MAX_BYTES {
+                break;
+            }
+            self.drop_front(next_key);
+        }
+        if self.bytes > MAX_BYTES {
+            log::warn!("Replay buffer is full without a key frame, cleared");
+            self.drop_front(self.entries.len());
+        }
+    }
+
+    fn drop_front(&mut self, n: usize) {
+        for e in self.entries.drain(..n) {
+            self.bytes -= e.size;
+        }
+    }
+
+    /// Whether to ask the peer for a key frame, once per span while the buffer holds twice
+    /// its span, peers only send key frames when they have to.
+    pub fn take_key_frame_request(&mut self, now: Instant) -> bool {
+        let Some(front) = self.entries.front() else {
+            return false;
+        };
+        if now.saturating_duration_since(front.time) < self.span * 2
+            || self
+                .key_frame_requested
+                .map_or(false, |t| now.saturating_duration_since(t) < self.span)
+        {
+            return false;
+        }
+        self.key_frame_requested = Some(now);
+        true
+    }
+
+    /// Writes the buffer to a new file on another thread, so the video keeps being decoded
+    /// meanwhile. `on_error` is called there if it fails.
+    pub fn save(&self, ctx: RecorderContext, on_error: impl FnOnce(String) + Send + 'static) {
+        // The frame data is shared, not copied.
+        let frames: Vec<_> = self
+            .entries
+            .iter()
+            .map(|e| (e.frame.clone(), e.w, e.h))
+            .collect();
+        std::thread::spawn(move || {
+            if let Err(err) = write(ctx, frames) {
+                on_error(err.to_string());
+            }
+        });
+    }
+}
+
+fn write(ctx: RecorderContext, frames: Vec<(video_frame::Union, usize, usize)>) -> ResultType<()> {
+    let mut recorder = Recorder::new(ctx)?;
+    for (frame, w, h) in frames.iter() {
+        recorder.write_frame(frame, *w, *h)?;
+    }
+    Ok(())
+}
+
+fn encoded_frames(frame: &video_frame::Union) -> &[EncodedVideoFrame] {
+    match frame {
+        video_frame::Union::Vp8s(f) => &f.frames,
+        video_frame::Union::Vp9s(f) => &f.frames,
+        video_frame::Union::Av1s(f) => &f.frames,
+        video_frame::Union::H264s(f) => &f.frames,
+        video_frame::Union::H265s(f) => &f.frames,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::EncodedVideoFrames;
+
+    fn frame(key: bool) -> video_frame::Union {
+        video_frame::Union::Vp9s(EncodedVideoFrames {
+            frames: vec![EncodedVideoFrame {
+                data: vec![0; 10].into(),
+                key,
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_replay_buffer() {
+        let mut buffer = ReplayBuffer::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |s| start + Duration::from_secs(s);
+        buffer.push(&frame(false), 1, 1, at(0));
+        assert!(buffer.entries.is_empty());
+        buffer.push(&frame(true), 1, 1, at(1));
+        for s in 2..17 {
+            buffer.push(&frame(s == 5), 1, 1, at(s));
+        }
+        // Cut at the key frame of 5s once the rest covered 10s.
+        assert_eq!(buffer.entries.len(), 12);
+        assert!(buffer.entries[0].key);
+        assert_eq!(buffer.bytes, 120);
+        assert!(!buffer.take_key_frame_request(at(16)));
+        assert!(buffer.take_key_frame_request(at(25)));
+        assert!(!buffer.take_key_frame_request(at(26)));
+    }
+}
@@ -3,6 +3,8 @@ use crate::clipboard::{update_clipboard, ClipboardSide};
 use hbb_common::{message_proto::*, ResultType};
 use std::sync::Mutex;
 
+pub const OPTION_OCR_LANGUAGES: &str = "ocr-languages";
+
 lazy_static::lazy_static! {
     static ref SCREENSHOT: Mutex<Screenshot> = Default::default();
 }
@@ -11,6 +13,8 @@ pub enum ScreenshotAction {
     SaveAs(String),
     CopyToClipboard,
     Discard,
+    // Recognizes the text of a region, `x,y,w,h` in pixels of the screenshot.
+    CopyText((u32, u32, u32, u32)),
 }
 
 impl Default for ScreenshotAction {
@@ -32,6 +36,18 @@ impl From<&str> for ScreenshotAction {
             }
             Some('1') => Self::CopyToClipboard,
             Some('2') => Self::default(),
+            Some('3') => {
+                let rect: Vec<u32> = value
+                    .get(2..)
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter_map(|v| v.parse().ok())
+                    .collect();
+                match rect[..] {
+                    [x, y, w, h] if w > 0 && h > 0 => Self::CopyText((x, y, w, h)),
+                    _ => Self::default(),
+                }
+            }
             _ => Self::default(),
         }
     }
@@ -43,6 +59,7 @@ impl Into<String> for ScreenshotAction {
             Self::SaveAs(p) => format!("0:{p}"),
             Self::CopyToClipboard => "1".to_owned(),
             Self::Discard => "2".to_owned(),
+            Self::CopyText((x, y, w, h)) => format!("3:{x},{y},{w},{h}"),
         }
     }
 }
@@ -57,6 +74,10 @@ impl Screenshot {
         self.data.replace(data);
     }
 
+    fn get_screenshot(&self) -> Vec<u8> {
+        self.data.as_ref().map(|d| d.to_vec()).unwrap_or_default()
+    }
+
     fn handle_screenshot(&mut self, action: String) -> String {
         let Some(data) = self.data.take() else {
             return "No cached screenshot".to_owned();
@@ -85,15 +106,81 @@ impl Screenshot {
                 }
             }
             ScreenshotAction::Discard => {}
+            ScreenshotAction::CopyText(rect) => {
+                #[cfg(not(any(target_os = "android", target_os = "ios")))]
+                {
+                    let text = recognize_text(&data, rect)?;
+                    let clips = vec![Clipboard {
+                        compress: false,
+                        content: text.into(),
+                        format: ClipboardFormat::Text.into(),
+                        ..Default::default()
+                    }];
+                    update_clipboard(clips, ClipboardSide::Client);
+                }
+                #[cfg(any(target_os = "android", target_os = "ios"))]
+                {
+                    let _ = rect;
+                    hbb_common::bail!("Text recognition is not supported on this platform");
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Runs tesseract on a region of the png, it has to be installed and in `PATH`.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn recognize_text(png: &[u8], (x, y, w, h): (u32, u32, u32, u32)) -> ResultType<String> {
+    use hbb_common::{anyhow::Context, bail, config::LocalConfig};
+    use std::{
+        io::{Cursor, Write},
+        process::{Command, Stdio},
+    };
+
+    let region =
+        image::load_from_memory_with_format(png, image::ImageFormat::Png)?.crop_imm(x, y, w, h);
+    let mut cropped = Cursor::new(Vec::new());
+    region.write_to(&mut cropped, image::ImageOutputFormat::Png)?;
+
+    let mut cmd = Command::new("tesseract");
+    cmd.args(["stdin", "stdout"]);
+    let languages = LocalConfig::get_option(OPTION_OCR_LANGUAGES);
+    if !languages.is_empty() {
+        cmd.args(["-l", &languages]);
+    }
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run tesseract, is it installed?")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(cropped.get_ref())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "Text recognition failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if text.is_empty() {
+        bail!("No text found");
+    }
+    Ok(text)
+}
+
 pub fn set_screenshot(data: bytes::Bytes) {
     SCREENSHOT.lock().unwrap().set_screenshot(data);
 }
 
+/// The cached screenshot, to be annotated before it is handled.
+pub fn get_screenshot() -> Vec<u8> {
+    SCREENSHOT.lock().unwrap().get_screenshot()
+}
+
 pub fn handle_screenshot(action: String) -> String {
     SCREENSHOT.lock().unwrap().handle_screenshot(action)
 }
@@ -0,0 +1,112 @@
+//! Blends an in-between frame while the frame rate is low, to hide the judder of the video
+//! quality control dropping frames under congestion.
+//!
+//! The blended frame is shown first and the received one half an interval later, so the video
+//! is delayed by that much while smoothing. Only frames decoded to RGBA are smoothed, frames
+//! that stay on the GPU are shown as they are.
+
+use scrap::ImageRgb;
+use std::time::{Duration, Instant};
+
+pub const OPTION_SMOOTH_LOW_FPS: &str = "smooth-low-fps";
+// Smooth below this frame rate.
+const MAX_FPS: u32 = 15;
+// Longer gaps are a still screen, not dropped frames.
+const MAX_GAP: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_millis(100);
+
+pub struct Smoother {
+    interval: Option<Duration>,
+    last: Option<Instant>,
+    prev: ImageRgb,
+    prev_valid: bool,
+    blended: ImageRgb,
+}
+
+impl Smoother {
+    pub fn new(rgb: &ImageRgb) -> Self {
+        Self {
+            interval: None,
+            last: None,
+            prev: ImageRgb::new(rgb.fmt(), rgb.align()),
+            prev_valid: false,
+            blended: ImageRgb::new(rgb.fmt(), rgb.align()),
+        }
+    }
+
+    /// The frame to show before `cur` and how long to wait before showing `cur`.
+    pub fn smooth(&mut self, cur: &ImageRgb, now: Instant) -> Option<(&mut ImageRgb, Duration)> {
+        let Some(delay) = self.delay(now) else {
+            self.prev_valid = false;
+            return None;
+        };
+        let blended = self.prev_valid
+            && self.prev.w == cur.w
+            && self.prev.h == cur.h
+            && self.prev.raw.len() == cur.raw.len();
+        if blended {
+            self.blended.w = cur.w;
+            self.blended.h = cur.h;
+            blend(&self.prev.raw, &cur.raw, &mut self.blended.raw);
+        }
+        self.prev.w = cur.w;
+        self.prev.h = cur.h;
+        self.prev.raw.clone_from(&cur.raw);
+        self.prev_valid = true;
+        blended.then_some((&mut self.blended, delay))
+    }
+
+    fn delay(&mut self, now: Instant) -> Option<Duration> {
+        let gap = now.saturating_duration_since(self.last.replace(now)?);
+        if gap > MAX_GAP {
+            self.interval = None;
+            return None;
+        }
+        let interval = match self.interval {
+            Some(interval) => (interval * 7 + gap) / 8,
+            None => gap,
+        };
+        self.interval = Some(interval);
+        if interval * MAX_FPS <= Duration::from_secs(1) {
+            return None;
+        }
+        Some((interval / 2).min(MAX_DELAY))
+    }
+}
+
+fn blend(a: &[u8], b: &[u8], out: &mut Vec<u8>) {
+    out.clear();
+    out.extend(
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| ((*a as u16 + *b as u16) / 2) as u8),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrap::ImageFormat;
+
+    #[test]
+    fn test_smooth() {
+        let mut rgb = ImageRgb::new(ImageFormat::ARGB, 1);
+        rgb.w = 1;
+        rgb.h = 1;
+        let mut smoother = Smoother::new(&rgb);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        rgb.raw = vec![0; 4];
+        assert!(smoother.smooth(&rgb, at(0)).is_none());
+        rgb.raw = vec![100; 4];
+        assert!(smoother.smooth(&rgb, at(100)).is_none());
+        rgb.raw = vec![200; 4];
+        let (blended, delay) = smoother.smooth(&rgb, at(200)).unwrap();
+        assert_eq!(blended.raw, vec![150; 4]);
+        assert_eq!(delay, Duration::from_millis(50));
+        // A still screen, then a fast frame rate.
+        assert!(smoother.smooth(&rgb, at(1000)).is_none());
+        assert!(smoother.smooth(&rgb, at(1020)).is_none());
+        assert!(smoother.smooth(&rgb, at(1040)).is_none());
+    }
+}
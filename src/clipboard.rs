@@ -6,6 +6,9 @@ use std::{
     time::Duration,
 };
 
+#[cfg(not(target_os = "android"))]
+mod policy;
+
 pub const CLIPBOARD_NAME: &'static str = "clipboard";
 #[cfg(feature = "unix-file-copy-paste")]
 pub const FILE_CLIPBOARD_NAME: &'static str = "file-clipboard";
@@ -60,6 +63,11 @@ pub fn check_clipboard(
     let ctx2 = ctx.as_mut()?;
     match ctx2.get(side, force) {
         Ok(content) => {
+            let content = if side == ClipboardSide::Host {
+                policy::apply(content)
+            } else {
+                content
+            };
             if !content.is_empty() {
                 let mut msg = Message::new();
                 let clipboards = proto::create_multi_clipboards(content);
@@ -187,7 +195,7 @@ pub fn check_clipboard_cm() -> ResultType<MultiClipboards> {
         }
     }
     if let Some(ctx) = ctx.as_mut() {
-        let content = ctx.get(ClipboardSide::Host, false)?;
+        let content = policy::apply(ctx.get(ClipboardSide::Host, false)?);
         let clipboards = proto::create_multi_clipboards(content);
         Ok(clipboards)
     } else {
@@ -484,7 +492,13 @@ pub fn get_current_clipboard_msg(
     let mut multi_clipboards = LAST_MULTI_CLIPBOARDS.lock().unwrap();
     if multi_clipboards.clipboards.is_empty() {
         let mut ctx = ClipboardContext::new().ok()?;
-        *multi_clipboards = proto::create_multi_clipboards(ctx.get(side, true).ok()?);
+        let content = ctx.get(side, true).ok()?;
+        let content = if side == ClipboardSide::Host {
+            policy::apply(content)
+        } else {
+            content
+        };
+        *multi_clipboards = proto::create_multi_clipboards(content);
     }
     if multi_clipboards.clipboards.is_empty() {
         return None;
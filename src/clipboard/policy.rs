@@ -0,0 +1,189 @@
+//! Policy for the clipboard content the host sends to its peers.
+
+use arboard::ClipboardData;
+use hbb_common::{config::Config, log, regex::Regex};
+use std::sync::Mutex;
+
+// Largest item in bytes, before compression. Empty or "0" means no limit.
+const OPTION_CLIPBOARD_MAX_SIZE: &str = "clipboard-max-size";
+// "Y" to keep copied file system paths on the host.
+const OPTION_CLIPBOARD_BLOCK_FILE_PATHS: &str = "clipboard-block-file-paths";
+// Matches are replaced in text, html and rtf.
+const OPTION_CLIPBOARD_REDACT_REGEX: &str = "clipboard-redact-regex";
+const REDACTED: &str = "[redacted]";
+
+lazy_static::lazy_static! {
+    static ref REDACT_REGEX: Mutex<Option<(String, Redaction)>> = Default::default();
+}
+
+#[derive(Clone)]
+enum Redaction {
+    Off,
+    Pattern(Regex),
+    // Fail closed, a typo in the pattern must not let the secrets it was written for through.
+    Invalid,
+}
+
+struct Policy {
+    max_size: usize,
+    block_file_paths: bool,
+    redaction: Redaction,
+}
+
+pub fn apply(data: Vec<ClipboardData>) -> Vec<ClipboardData> {
+    let policy = Policy {
+        max_size: Config::get_option(OPTION_CLIPBOARD_MAX_SIZE)
+            .parse()
+            .unwrap_or(0),
+        block_file_paths: Config::get_option(OPTION_CLIPBOARD_BLOCK_FILE_PATHS) == "Y",
+        redaction: redaction(&Config::get_option(OPTION_CLIPBOARD_REDACT_REGEX)),
+    };
+    policy.apply(data)
+}
+
+fn redaction(pattern: &str) -> Redaction {
+    if pattern.is_empty() {
+        return Redaction::Off;
+    }
+    let mut cache = REDACT_REGEX.lock().unwrap();
+    if let Some((cached, redaction)) = cache.as_ref() {
+        if cached == pattern {
+            return redaction.clone();
+        }
+    }
+    let redaction = match Regex::new(pattern) {
+        Ok(regex) => Redaction::Pattern(regex),
+        Err(err) => {
+            log::error!("Invalid {}: {}", OPTION_CLIPBOARD_REDACT_REGEX, err);
+            Redaction::Invalid
+        }
+    };
+    *cache = Some((pattern.to_owned(), redaction.clone()));
+    redaction
+}
+
+impl Policy {
+    fn apply(&self, data: Vec<ClipboardData>) -> Vec<ClipboardData> {
+        // The formats are renderings of the same copy, a blocked path must not leak through html.
+        if self.block_file_paths
+            && data.iter().any(|d| match d {
+                ClipboardData::Text(s) => is_file_path(s),
+                _ => false,
+            })
+        {
+            log::info!("Clipboard with file paths is not sent");
+            return vec![];
+        }
+        let len = data.len();
+        let data: Vec<ClipboardData> = data
+            .into_iter()
+            .filter(|d| {
+                let size = size(d);
+                if self.max_size > 0 && size > self.max_size {
+                    log::info!("Clipboard item of {} bytes is not sent", size);
+                    return false;
+                }
+                true
+            })
+            .filter_map(|d| self.redact(d))
+            .collect();
+        // Only markers like the owner format are left, there is nothing to send.
+        if data.len() < len && data.iter().all(|d| matches!(d, ClipboardData::Special(_))) {
+            return vec![];
+        }
+        data
+    }
+
+    fn redact(&self, data: ClipboardData) -> Option<ClipboardData> {
+        let redact = |s: String| match &self.redaction {
+            Redaction::Off => Some(s),
+            Redaction::Pattern(regex) => Some(regex.replace_all(&s, REDACTED).into_owned()),
+            Redaction::Invalid => None,
+        };
+        match data {
+            ClipboardData::Text(s) => redact(s).map(ClipboardData::Text),
+            ClipboardData::Html(s) => redact(s).map(ClipboardData::Html),
+            ClipboardData::Rtf(s) => redact(s).map(ClipboardData::Rtf),
+            data => Some(data),
+        }
+    }
+}
+
+fn size(data: &ClipboardData) -> usize {
+    match data {
+        ClipboardData::Text(s) | ClipboardData::Html(s) | ClipboardData::Rtf(s) => s.len(),
+        ClipboardData::Image(image) => image.bytes().len(),
+        ClipboardData::Special((_, d)) => d.len(),
+        _ => 0,
+    }
+}
+
+// Copying files in a file manager puts one path per line on the clipboard.
+fn is_file_path(text: &str) -> bool {
+    let mut lines = text.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+    let is_path = |l: &str| {
+        let b = l.as_bytes();
+        l.starts_with("file://")
+            || l.starts_with('/')
+            || l.starts_with("~/")
+            || l.starts_with("\\\\")
+            || (b.len() > 2
+                && b[0].is_ascii_alphabetic()
+                && b[1] == b':'
+                && (b[2] == b'\\' || b[2] == b'/'))
+    };
+    match lines.next() {
+        Some(first) => is_path(first) && lines.all(is_path),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(data: &[ClipboardData]) -> Vec<String> {
+        data.iter()
+            .filter_map(|d| match d {
+                ClipboardData::Text(s) | ClipboardData::Html(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clipboard_policy() {
+        assert!(is_file_path("/home/user/id_rsa"));
+        assert!(is_file_path("C:\\Users\\me\\a.txt\r\nD:/b.txt\n"));
+        assert!(is_file_path("file:///tmp/a"));
+        assert!(!is_file_path("hello /tmp"));
+        assert!(!is_file_path(""));
+
+        let policy = Policy {
+            max_size: 8,
+            block_file_paths: true,
+            redaction: Redaction::Pattern(Regex::new(r"\d{4}").unwrap()),
+        };
+        let data = vec![
+            ClipboardData::Text("pin 1234".to_owned()),
+            ClipboardData::Html("<b>pin 1234</b>".to_owned()),
+        ];
+        assert_eq!(texts(&policy.apply(data)), vec!["pin [redacted]"]);
+        let data = vec![
+            ClipboardData::Text("/etc".to_owned()),
+            ClipboardData::Html("/etc".to_owned()),
+        ];
+        assert!(policy.apply(data).is_empty());
+
+        let policy = Policy {
+            max_size: 0,
+            block_file_paths: false,
+            redaction: Redaction::Invalid,
+        };
+        let data = vec![
+            ClipboardData::Text("secret".to_owned()),
+            ClipboardData::Special(("owner".to_owned(), vec![1])),
+        ];
+        assert!(policy.apply(data).is_empty());
+    }
+}
@@ -62,6 +62,8 @@ pub const PLATFORM_ANDROID: &str = "Android";
 
 pub const TIMER_OUT: Duration = Duration::from_secs(1);
 pub const DEFAULT_KEEP_ALIVE: i32 = 60_000;
+// "Y" for no rendezvous registration, update checks or relays, only direct connections.
+pub const OPTION_LAN_ONLY: &str = "lan-only";
 
 const MIN_VER_MULTI_UI_SESSION: &str = "1.2.4";
 
@@ -71,6 +73,8 @@ pub mod input {
     pub const MOUSE_TYPE_UP: i32 = 2;
     pub const MOUSE_TYPE_WHEEL: i32 = 3;
     pub const MOUSE_TYPE_TRACKPAD: i32 = 4;
+    // x and y are a movement, for pointer lock in games and 3D applications.
+    pub const MOUSE_TYPE_MOVE_RELATIVE: i32 = 5;
 
     pub const MOUSE_BUTTON_LEFT: i32 = 0x01;
     pub const MOUSE_BUTTON_RIGHT: i32 = 0x02;
@@ -542,6 +546,9 @@ impl Drop for CheckTestNatType {
 }
 
 pub fn test_nat_type() {
+    if is_lan_only() {
+        return;
+    }
     test_ipv6_sync();
     use std::sync::atomic::{AtomicBool, Ordering};
     std::thread::spawn(move || {
@@ -696,6 +703,18 @@ pub async fn get_nat_type(ms_timeout: u64) -> i32 {
     crate::ipc::get_nat_type(ms_timeout).await
 }
 
+// A tcp punch to a controlled side behind a symmetric NAT is tried as a birthday attack: it
+// punches from `BIRTHDAY_PORTS` new ports and the controller probes as many random ones of it,
+// `BIRTHDAY_BATCH` sockets at a time.
+pub const BIRTHDAY_PORTS: usize = 32;
+pub const BIRTHDAY_BATCH: usize = 8;
+
+/// Both sides decide with the nat types of the punch hole messages, so they always agree.
+pub fn use_birthday_punch(controlled_nat_type: i32, controller_nat_type: i32) -> bool {
+    controlled_nat_type == NatType::SYMMETRIC as i32
+        && controller_nat_type != NatType::SYMMETRIC as i32
+}
+
 // used for client to test which server is faster in case stop-servic=Y
 #[tokio::main(flavor = "current_thread")]
 async fn test_rendezvous_server_() {
@@ -862,6 +881,32 @@ pub fn get_sysinfo() -> serde_json::Value {
     out
 }
 
+/// Sends the inventory to controllers, it names the host, the user and the disks so it is off
+/// by default.
+pub const OPTION_SHARE_SYSINFO: &str = "allow-share-sysinfo";
+
+/// `get_sysinfo` with the disks, uptime and version, sent to controllers for their inventory
+/// if `OPTION_SHARE_SYSINFO` is set.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub fn get_inventory() -> serde_json::Value {
+    use hbb_common::sysinfo::{Disks, System};
+    let mut out = get_sysinfo();
+    let disks: Vec<_> = Disks::new_with_refreshed_list()
+        .iter()
+        .map(|d| {
+            json!({
+                "mount": d.mount_point().to_string_lossy(),
+                "total": d.total_space(),
+                "available": d.available_space(),
+            })
+        })
+        .collect();
+    out["disks"] = json!(disks);
+    out["uptime"] = json!(System::uptime());
+    out["version"] = json!(crate::VERSION);
+    out
+}
+
 #[inline]
 pub fn check_port<T: std::string::ToString>(host: T, port: i32) -> String {
     hbb_common::socket_client::check_port(host, port)
@@ -914,6 +959,9 @@ pub fn check_software_update() {
 // Because the url is always `https://api.rustdesk.com/version/latest`.
 #[tokio::main(flavor = "current_thread")]
 pub async fn do_check_software_update() -> hbb_common::ResultType<()> {
+    if is_lan_only() {
+        hbb_common::bail!("No update check in LAN-only mode");
+    }
     let (request, url) =
         hbb_common::version_check_request(hbb_common::VER_TYPE_RUSTDESK_CLIENT.to_string());
     let proxy_conf = Config::get_socks();
@@ -1061,6 +1109,11 @@ pub fn get_udp_punch_enabled() -> bool {
     )
 }
 
+#[inline]
+pub fn is_lan_only() -> bool {
+    config::option2bool(OPTION_LAN_ONLY, &Config::get_option(OPTION_LAN_ONLY))
+}
+
 pub fn get_ipv6_punch_enabled() -> bool {
     config::option2bool(
         keys::OPTION_ENABLE_IPV6_PUNCH,
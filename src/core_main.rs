@@ -409,6 +409,15 @@ pub fn core_main() -> Option<Vec<String>> {
         } else if args[0] == "--get-id" {
             println!("{}", crate::ipc::get_id());
             return None;
+        } else if args[0] == "--get-sysinfo" {
+            // With an id, the inventory the peer sent on the last connection.
+            if args.len() == 2 {
+                let options = config::PeerConfig::load(&args[1]).options;
+                println!("{}", options.get("sysinfo").cloned().unwrap_or_default());
+            } else {
+                println!("{}", crate::get_inventory());
+            }
+            return None;
         } else if args[0] == "--set-id" {
             if config::is_disable_settings() {
                 println!("Settings are disabled!");
@@ -468,6 +477,23 @@ pub fn core_main() -> Option<Vec<String>> {
                 println!("Installation and administrative privileges required!");
             }
             return None;
+        } else if args[0] == "--config-apply" {
+            if config::is_disable_settings() {
+                println!("Settings are disabled!");
+                return None;
+            }
+            if args.len() == 2 {
+                if crate::platform::is_installed() && is_root() {
+                    if let Err(err) = apply_config_profile(&args[1]) {
+                        println!("{err}");
+                        std::process::exit(1);
+                    }
+                    println!("Done!");
+                } else {
+                    println!("Installation and administrative privileges required!");
+                }
+            }
+            return None;
         } else if args[0] == "--assign" {
             if config::Config::no_register_device() {
                 println!("Cannot assign an unregistrable device!");
@@ -670,6 +696,70 @@ fn import_config(path: &str) {
     }
 }
 
+/// A deployment profile for `--config-apply`, in JSON or TOML (by the `.toml` extension).
+///
+/// Empty fields are left alone, `options` are the same as `--option` takes. The `password` is
+/// kept in the encrypted permanent password storage, like one set with `--password`. Unknown
+/// fields are refused, so a misspelled one is not silently skipped.
+#[derive(Debug, Default, serde_derive::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigProfile {
+    server: String,
+    key: String,
+    api: String,
+    relay: String,
+    password: String,
+    options: std::collections::HashMap<String, String>,
+}
+
+impl ConfigProfile {
+    fn validate(&self) -> hbb_common::ResultType<()> {
+        if self.password.chars().count() > config::ENCRYPT_MAX_LEN {
+            hbb_common::bail!("No more than {} characters", config::ENCRYPT_MAX_LEN);
+        }
+        if self.options.keys().any(|k| k.is_empty()) {
+            hbb_common::bail!("Empty option name");
+        }
+        Ok(())
+    }
+}
+
+// The whole profile is read and checked before anything is changed, and the options are replaced
+// in one write. Only a failing write to the service midway can leave a part of it applied.
+fn apply_config_profile(path: &str) -> hbb_common::ResultType<()> {
+    let text = std::fs::read_to_string(path)?;
+    let profile: ConfigProfile = if path.ends_with(".toml") {
+        hbb_common::toml::from_str(&text)?
+    } else {
+        serde_json::from_str(&text)?
+    };
+    profile.validate()?;
+    let mut options = crate::ipc::get_options();
+    for (k, v) in [
+        ("custom-rendezvous-server", profile.server),
+        ("key", profile.key),
+        ("api-server", profile.api),
+        ("relay-server", profile.relay),
+    ] {
+        if !v.is_empty() {
+            options.insert(k.to_owned(), v);
+        }
+    }
+    for (k, v) in profile.options {
+        if v.is_empty() {
+            options.remove(&k);
+        } else {
+            options.insert(k, v);
+        }
+    }
+    crate::ipc::set_options(options)?;
+    if !profile.password.is_empty() {
+        crate::ipc::set_permanent_password(profile.password)?;
+    }
+    log::info!("config profile {} applied", path);
+    Ok(())
+}
+
 /// invoke a new connection
 ///
 /// [Note]
@@ -0,0 +1,133 @@
+//! Directories transferred as one archive.
+//!
+//! Thousands of small files cost a round trip each, so the controller can ask for a directory
+//! as `archive://<path>`. The controlled side packs it into a zstd compressed tar, sends that
+//! as a single file, and the controller unpacks it once the job is done.
+
+use hbb_common::{bail, ResultType};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+use tar::{Archive, Builder, EntryType};
+
+const PREFIX: &str = "archive://";
+
+pub fn request_path(dir: &str) -> String {
+    format!("{}{}", PREFIX, dir)
+}
+
+/// The directory asked for by `path`, `None` for a plain transfer.
+pub fn requested_dir(path: &str) -> Option<&str> {
+    path.strip_prefix(PREFIX)
+}
+
+/// Packs `dir` into a temporary file, removing it is up to the caller.
+pub fn create(dir: &str, include_hidden: bool) -> ResultType<PathBuf> {
+    let tmp = std::env::temp_dir().join("rustdesk-archive");
+    std::fs::create_dir_all(&tmp)?;
+    let path = tmp.join(format!("{}.tar.zst", uuid::Uuid::new_v4()));
+    if let Err(err) = write(&path, Path::new(dir), include_hidden) {
+        std::fs::remove_file(&path).ok();
+        return Err(err);
+    }
+    Ok(path)
+}
+
+fn write(path: &Path, dir: &Path, include_hidden: bool) -> ResultType<()> {
+    let mut tar = Builder::new(zstd::Encoder::new(File::create(path)?, 0)?);
+    append_dir(&mut tar, dir, Path::new(""), include_hidden)?;
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+// Directories get entries of their own, so empty ones are kept too. Linked directories are
+// skipped, they could loop.
+fn append_dir<W: std::io::Write>(
+    tar: &mut Builder<W>,
+    dir: &Path,
+    name: &Path,
+    include_hidden: bool,
+) -> ResultType<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !include_hidden && is_hidden(&entry) {
+            continue;
+        }
+        let path = entry.path();
+        let name = name.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            tar.append_dir(&name, &path)?;
+            append_dir(tar, &path, &name, include_hidden)?;
+        } else if file_type.is_file() || path.is_file() {
+            tar.append_path_with_name(&path, &name)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    entry
+        .metadata()
+        .map(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Unpacks `archive` into `to` and removes it.
+pub fn extract(archive: &Path, to: &Path) -> ResultType<()> {
+    let res = extract_(archive, to);
+    std::fs::remove_file(archive).ok();
+    res
+}
+
+fn extract_(archive: &Path, to: &Path) -> ResultType<()> {
+    std::fs::create_dir_all(to)?;
+    let mut tar = Archive::new(zstd::Decoder::new(File::open(archive)?)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        // Links could point anywhere, only plain files and directories are unpacked.
+        if !matches!(
+            entry.header().entry_type(),
+            EntryType::Regular | EntryType::Directory
+        ) {
+            continue;
+        }
+        // `unpack_in` refuses names with `..` or a root, which would write outside of `to`.
+        if !entry.unpack_in(to)? {
+            bail!("Invalid file name in archive: {}", entry.path()?.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip() {
+        let root = std::env::temp_dir().join(format!("archive-test-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("src");
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("a/b/c.txt"), "c").unwrap();
+        std::fs::write(dir.join(".hidden"), "h").unwrap();
+        let archive = create(&dir.to_string_lossy(), false).unwrap();
+        let to = root.join("dst");
+        extract(&archive, &to).unwrap();
+        assert!(!archive.exists());
+        assert_eq!(std::fs::read_to_string(to.join("a/b/c.txt")).unwrap(), "c");
+        assert!(to.join("empty").is_dir());
+        assert!(!to.join(".hidden").exists());
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -0,0 +1,81 @@
+//! Files dropped onto the remote view.
+//!
+//! The controller uploads them with a target of `drop://<name>`, the connection manager of the
+//! controlled side resolves it to the drop folder. This needs no file transfer session and no
+//! knowledge of the remote file system on the controller.
+
+use hbb_common::{config::Config, fs};
+use std::path::{Component, Path, PathBuf};
+
+const PREFIX: &str = "drop://";
+// Folder the dropped files go to, the Desktop of the user by default.
+const OPTION_FILE_DROP_FOLDER: &str = "file-drop-folder";
+
+pub fn target(name: &str) -> String {
+    format!("{}{}", PREFIX, name)
+}
+
+pub fn is_drop_path(path: &str) -> bool {
+    path.starts_with(PREFIX)
+}
+
+/// The local path of a dropped item, `None` if `path` or the names of its files leave the drop folder.
+pub fn resolve(path: &str, files: &[(String, u64)]) -> Option<PathBuf> {
+    let name = path.strip_prefix(PREFIX)?;
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return None;
+    }
+    if !files.iter().all(|(f, _)| is_relative(f)) {
+        return None;
+    }
+    Some(folder().join(name))
+}
+
+fn folder() -> PathBuf {
+    let folder = Config::get_option(OPTION_FILE_DROP_FOLDER);
+    if !folder.is_empty() {
+        return PathBuf::from(folder);
+    }
+    let home = fs::get_home_as_string();
+    let desktop = Path::new(&home).join("Desktop");
+    if desktop.is_dir() {
+        desktop
+    } else {
+        PathBuf::from(home)
+    }
+}
+
+// Names of the files inside a dropped folder, a peer could send `../` to write anywhere.
+fn is_relative(name: &str) -> bool {
+    Path::new(&name.replace('\\', "/"))
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_drop_path() {
+        assert!(is_drop_path(&target("a.txt")));
+        assert!(!is_drop_path("/tmp/a.txt"));
+        let files = vec![("".to_owned(), 0)];
+        assert!(resolve(&target("a.txt"), &files)
+            .unwrap()
+            .ends_with("a.txt"));
+        assert!(resolve("/tmp/a.txt", &files).is_none());
+        assert!(resolve(&target(""), &files).is_none());
+        assert!(resolve(&target(".."), &files).is_none());
+        assert!(resolve(&target("a/b"), &files).is_none());
+        assert!(resolve(&target("/etc"), &files).is_none());
+        let files = vec![("b/c.txt".to_owned(), 0), ("d.txt".to_owned(), 0)];
+        assert!(resolve(&target("a"), &files).is_some());
+        let files = vec![("b\\..\\..\\c.txt".to_owned(), 0)];
+        assert!(resolve(&target("a"), &files).is_none());
+    }
+}
@@ -712,6 +712,15 @@ impl InvokeUiSession for FlutterHandler {
                     &status.codec_format.map_or(NULL, |it| it.to_string()),
                 ),
                 ("chroma", &status.chroma.map_or(NULL, |it| it.to_string())),
+                (
+                    "decode_time",
+                    &status.decode_time.map_or(NULL, |it| it.to_string()),
+                ),
+                (
+                    "render_time",
+                    &status.render_time.map_or(NULL, |it| it.to_string()),
+                ),
+                ("relay", &status.relay.map_or(NULL, |it| it)),
             ],
             &[],
         );
@@ -801,6 +810,14 @@ impl InvokeUiSession for FlutterHandler {
         );
     }
 
+    fn update_folder_sync(&self, id: i32, plan: String) {
+        self.push_event(
+            "folder_sync",
+            &[("id", &id.to_string()), ("plan", &plan)],
+            &[],
+        );
+    }
+
     // unused in flutter
     fn update_transfer_list(&self) {}
 
@@ -1522,6 +1539,10 @@ pub mod connection_manager {
             self.push_event("show_elevation", &[("show", &show.to_string())]);
         }
 
+        fn show_connection(&self, id: i32) {
+            self.push_event("show_connection", &[("id", &id.to_string())]);
+        }
+
         fn update_voice_call_state(&self, client: &crate::ui_cm_interface::Client) {
             let client_json = serde_json::to_string(&client).unwrap_or("".into());
             // send to Android service, active notification no matter UI is shown or not.
@@ -284,6 +284,16 @@ pub fn session_handle_screenshot(
     crate::client::screenshot::handle_screenshot(action)
 }
 
+pub fn session_get_screenshot(
+    #[allow(unused_variables)] session_id: SessionID,
+) -> SyncReturn<Vec<u8>> {
+    SyncReturn(crate::client::screenshot::get_screenshot())
+}
+
+pub fn session_set_screenshot(#[allow(unused_variables)] session_id: SessionID, data: Vec<u8>) {
+    crate::client::screenshot::set_screenshot(data.into());
+}
+
 pub fn session_is_multi_ui_session(session_id: SessionID) -> SyncReturn<bool> {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         SyncReturn(session.is_multi_ui_session())
@@ -298,6 +308,18 @@ pub fn session_record_screen(session_id: SessionID, start: bool) {
     }
 }
 
+pub fn session_pause_record(session_id: SessionID, paused: bool) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.pause_record(paused);
+    }
+}
+
+pub fn session_save_replay(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.save_replay();
+    }
+}
+
 pub fn session_get_is_recording(session_id: SessionID) -> SyncReturn<bool> {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         SyncReturn(session.is_recording())
@@ -728,6 +750,43 @@ pub fn session_send_files(
     }
 }
 
+pub fn session_download_archive(
+    session_id: SessionID,
+    act_id: i32,
+    path: String,
+    to: String,
+    include_hidden: bool,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.download_archive(act_id, path, to, include_hidden);
+    }
+}
+
+pub fn session_relay_files(
+    session_id: SessionID,
+    act_id: i32,
+    path: String,
+    to_session_id: SessionID,
+    to: String,
+    include_hidden: bool,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.relay_files(act_id, path, to_session_id, to, include_hidden);
+    }
+}
+
+pub fn session_sync_folders(
+    session_id: SessionID,
+    act_id: i32,
+    local: String,
+    remote: String,
+    include_hidden: bool,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.sync_folders(act_id, local, remote, include_hidden);
+    }
+}
+
 pub fn session_set_confirm_override_file(
     session_id: SessionID,
     act_id: i32,
@@ -981,6 +1040,7 @@ pub fn main_set_option(key: String, value: String) {
         || key.eq(config::keys::OPTION_ALLOW_WEBSOCKET)
         || key.eq(config::keys::OPTION_DISABLE_UDP)
         || key.eq("api-server")
+        || key.eq(crate::common::OPTION_LAN_ONLY)
     {
         if is_allow_tls_fallback {
             hbb_common::tls::reset_tls_cache();
@@ -1737,6 +1797,7 @@ pub fn session_send_mouse(session_id: SessionID, msg: String) {
                 "up" => MOUSE_TYPE_UP,
                 "wheel" => MOUSE_TYPE_WHEEL,
                 "trackpad" => MOUSE_TYPE_TRACKPAD,
+                "move_relative" => MOUSE_TYPE_MOVE_RELATIVE,
                 _ => 0,
             };
         }
@@ -1771,6 +1832,27 @@ pub fn session_get_audit_server_sync(session_id: SessionID, typ: String) -> Sync
     SyncReturn(res)
 }
 
+pub fn session_drop_files(session_id: SessionID, paths: Vec<String>) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.drop_files(paths);
+    }
+}
+
+pub fn session_get_clipboard_history(session_id: SessionID) -> SyncReturn<String> {
+    let res = if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.get_clipboard_history()
+    } else {
+        "".to_owned()
+    };
+    SyncReturn(res)
+}
+
+pub fn session_paste_clipboard_history(session_id: SessionID, index: usize) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.paste_clipboard_history(index);
+    }
+}
+
 pub fn session_send_note(session_id: SessionID, note: String) {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         session.send_note(note)
@@ -1937,6 +2019,11 @@ pub fn cm_switch_permission(conn_id: i32, name: String, enabled: bool) {
     crate::ui_cm_interface::switch_permission(conn_id, name, enabled)
 }
 
+pub fn cm_force_view_only(conn_id: i32) {
+    #[cfg(not(any(target_os = "ios")))]
+    crate::ui_cm_interface::force_view_only(conn_id)
+}
+
 pub fn cm_can_elevate() -> SyncReturn<bool> {
     SyncReturn(crate::ui_cm_interface::can_elevate())
 }
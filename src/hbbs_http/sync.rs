@@ -100,7 +100,9 @@ async fn start_hbbs_sync_async() {
                     *PRO.lock().unwrap() = false;
                     continue;
                 }
-                if config::option2bool("stop-service", &Config::get_option("stop-service")) {
+                if config::option2bool("stop-service", &Config::get_option("stop-service"))
+                    || crate::is_lan_only()
+                {
                     continue;
                 }
                 let conns = Connection::alive_conns();
@@ -220,6 +220,7 @@ pub enum Data {
     MouseMoveTime(i64),
     Authorize,
     Close,
+    ShowConnection,
     #[cfg(windows)]
     SAS,
     UserSid(Option<u32>),
@@ -366,6 +367,7 @@ pub struct CheckIfRestart {
     disable_udp: String,
     allow_insecure_tls_fallback: String,
     api_server: String,
+    lan_only: String,
 }
 
 impl CheckIfRestart {
@@ -381,6 +383,7 @@ impl CheckIfRestart {
                 config::keys::OPTION_ALLOW_INSECURE_TLS_FALLBACK,
             ),
             api_server: Config::get_option("api-server"),
+            lan_only: Config::get_option(crate::common::OPTION_LAN_ONLY),
         }
     }
 }
@@ -397,6 +400,7 @@ impl Drop for CheckIfRestart {
             || self.ws != Config::get_option(OPTION_ALLOW_WEBSOCKET)
             || self.disable_udp != Config::get_option(config::keys::OPTION_DISABLE_UDP)
             || self.api_server != Config::get_option("api-server")
+            || self.lan_only != Config::get_option(crate::common::OPTION_LAN_ONLY)
         {
             if allow_insecure_tls_fallback_changed {
                 hbb_common::tls::reset_tls_cache();
@@ -0,0 +1,183 @@
+//! Remapping of the keys sent to the peer.
+//!
+//! Rules come from a local option, e.g. `Meta=Control, CapsLock=Escape, RWin=`. The key on the
+//! left is sent as the key on the right, nothing on the right keeps the key from the peer.
+//! They apply on top of the fixed control-command swap.
+
+use hbb_common::{
+    config::LocalConfig,
+    log,
+    message_proto::{key_event, ControlKey, KeyEvent, KeyboardMode},
+    protobuf::EnumOrUnknown,
+};
+use rdev::Key;
+use std::sync::{Arc, Mutex};
+
+pub const OPTION_KEYBOARD_REMAP_RULES: &str = "keyboard-remap-rules";
+
+lazy_static::lazy_static! {
+    // The rules last parsed, with the option value they came from.
+    static ref RULES: Mutex<(String, Arc<RemapRules>)> = Default::default();
+}
+
+/// The rules of the local option, parsed again only when the option changed.
+pub fn rules() -> Arc<RemapRules> {
+    let value = LocalConfig::get_option(OPTION_KEYBOARD_REMAP_RULES);
+    let mut rules = RULES.lock().unwrap();
+    if rules.0 != value {
+        let parsed = Arc::new(RemapRules::parse(&value));
+        *rules = (value, parsed);
+    }
+    rules.1.clone()
+}
+
+// Names used in rules, with the key of the legacy and translate modes and the key of the map mode.
+const KEYS: &[(&str, ControlKey, Key)] = &[
+    ("Control", ControlKey::Control, Key::ControlLeft),
+    ("RControl", ControlKey::RControl, Key::ControlRight),
+    ("Alt", ControlKey::Alt, Key::Alt),
+    ("RAlt", ControlKey::RAlt, Key::AltGr),
+    ("Shift", ControlKey::Shift, Key::ShiftLeft),
+    ("RShift", ControlKey::RShift, Key::ShiftRight),
+    ("Meta", ControlKey::Meta, Key::MetaLeft),
+    ("RWin", ControlKey::RWin, Key::MetaRight),
+    ("CapsLock", ControlKey::CapsLock, Key::CapsLock),
+    ("Escape", ControlKey::Escape, Key::Escape),
+    ("Tab", ControlKey::Tab, Key::Tab),
+    ("Backspace", ControlKey::Backspace, Key::Backspace),
+    ("Return", ControlKey::Return, Key::Return),
+    ("Delete", ControlKey::Delete, Key::Delete),
+    ("Insert", ControlKey::Insert, Key::Insert),
+    ("Home", ControlKey::Home, Key::Home),
+    ("End", ControlKey::End, Key::End),
+    ("PageUp", ControlKey::PageUp, Key::PageUp),
+    ("PageDown", ControlKey::PageDown, Key::PageDown),
+];
+
+/// Indexes into `KEYS`, `None` for a blocked key.
+#[derive(Debug, Default, PartialEq)]
+pub struct RemapRules(Vec<(usize, Option<usize>)>);
+
+impl RemapRules {
+    pub fn parse(value: &str) -> Self {
+        let mut rules = vec![];
+        for rule in value.split(',').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+            let parsed = rule.split_once('=').and_then(|(from, to)| {
+                let to = to.trim();
+                let to = if to.is_empty() {
+                    None
+                } else {
+                    Some(index_of(to)?)
+                };
+                Some((index_of(from.trim())?, to))
+            });
+            match parsed {
+                Some(r) => rules.push(r),
+                None => log::warn!("Invalid {} rule: {}", OPTION_KEYBOARD_REMAP_RULES, rule),
+            }
+        }
+        Self(rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Remaps `msg` for a peer on `peer`, false if it must not be sent.
+    pub fn apply(&self, msg: &mut KeyEvent, peer: &str) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        if let Some(key_event::Union::ControlKey(ck)) = msg.union {
+            match self.map(|(_, c, _)| *c == ck.enum_value_or_default()) {
+                Some(Some(to)) => msg.set_control_key(KEYS[to].1),
+                Some(None) => return false,
+                None => {}
+            }
+        }
+        msg.modifiers = msg
+            .modifiers
+            .iter()
+            .filter_map(|ck| {
+                let ck = ck.enum_value_or_default();
+                match self.map(|(_, c, _)| *c == ck) {
+                    Some(to) => to.map(|to| EnumOrUnknown::new(KEYS[to].1)),
+                    None => Some(EnumOrUnknown::new(ck)),
+                }
+            })
+            .collect();
+        // The other modes send characters in `chr`.
+        if msg.mode.enum_value() == Ok(KeyboardMode::Map) && msg.chr() != 0 {
+            let key = key_from_code(peer, msg.chr());
+            match self.map(|(_, _, k)| *k == key) {
+                Some(Some(to)) => {
+                    if let Some(code) = code_from_key(peer, KEYS[to].2) {
+                        msg.set_chr(code);
+                    }
+                }
+                Some(None) => return false,
+                None => {}
+            }
+        }
+        true
+    }
+
+    fn map(&self, is_key: impl Fn(&(&str, ControlKey, Key)) -> bool) -> Option<Option<usize>> {
+        self.0
+            .iter()
+            .find(|(from, _)| is_key(&KEYS[*from]))
+            .map(|(_, to)| *to)
+    }
+}
+
+fn index_of(name: &str) -> Option<usize> {
+    KEYS.iter()
+        .position(|(n, _, _)| n.eq_ignore_ascii_case(name))
+}
+
+fn key_from_code(peer: &str, code: u32) -> Key {
+    match peer {
+        "windows" => rdev::win_key_from_scancode(code),
+        "macos" => rdev::macos_key_from_code(code as _),
+        _ => rdev::linux_key_from_code(code),
+    }
+}
+
+fn code_from_key(peer: &str, key: Key) -> Option<u32> {
+    match peer {
+        "windows" => rdev::win_scancode_from_key(key),
+        "macos" => rdev::macos_keycode_from_key(key).map(|c| c as _),
+        _ => rdev::linux_keycode_from_key(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap_rules() {
+        let rules = RemapRules::parse("meta=Control, CapsLock = Escape, RWin=, Foo=Tab, Tab");
+        assert_eq!(rules.0.len(), 3);
+
+        let mut msg = KeyEvent::new();
+        msg.set_control_key(ControlKey::Meta);
+        msg.modifiers = vec![
+            EnumOrUnknown::new(ControlKey::Shift),
+            EnumOrUnknown::new(ControlKey::RWin),
+        ];
+        assert!(rules.apply(&mut msg, "windows"));
+        assert_eq!(msg.control_key(), ControlKey::Control);
+        assert_eq!(msg.modifiers, vec![EnumOrUnknown::new(ControlKey::Shift)]);
+
+        let mut msg = KeyEvent::new();
+        msg.set_control_key(ControlKey::RWin);
+        assert!(!rules.apply(&mut msg, "windows"));
+
+        let mut msg = KeyEvent::new();
+        msg.mode = KeyboardMode::Map.into();
+        msg.set_chr(rdev::linux_keycode_from_key(Key::CapsLock).unwrap());
+        assert!(rules.apply(&mut msg, "linux"));
+        assert_eq!(rdev::linux_key_from_code(msg.chr()), Key::Escape);
+    }
+}
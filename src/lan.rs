@@ -21,15 +21,31 @@ use std::{
     time::Instant,
 };
 
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod mdns;
+
 type Message = RendezvousMessage;
 
+// A port-forward to `wol://<mac or id>` asks the controlled side to wake a machine on its LAN.
+// It answers on the raw stream with one of the replies below and closes.
+pub const WOL_SCHEME: &str = "wol://";
+pub const WOL_REPLY_SENT: &str = "wol-sent";
+pub const WOL_REPLY_UNKNOWN: &str = "wol-unknown";
+
 #[cfg(not(target_os = "ios"))]
 pub(super) fn start_listening() -> ResultType<()> {
     let addr = SocketAddr::from(([0, 0, 0, 0], get_broadcast_port()));
     let socket = std::net::UdpSocket::bind(addr)?;
     socket.set_read_timeout(Some(std::time::Duration::from_millis(1000)))?;
     log::info!("lan discovery listener started");
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    let mut mdns_responder = mdns::Responder::default();
     loop {
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        mdns_responder.update(config::option2bool(
+            "enable-lan-discovery",
+            &Config::get_option("enable-lan-discovery"),
+        ));
         let mut buf = [0; 2048];
         if let Ok((len, addr)) = socket.recv_from(&mut buf) {
             if let Ok(msg_in) = Message::parse_from_bytes(&buf[0..len]) {
@@ -76,7 +92,10 @@ pub(super) fn start_listening() -> ResultType<()> {
 #[tokio::main(flavor = "current_thread")]
 pub async fn discover() -> ResultType<()> {
     let sockets = send_query()?;
-    let rx = spawn_wait_responses(sockets);
+    let (tx, rx) = unbounded_channel::<_>();
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    mdns::spawn_browse(tx.clone());
+    spawn_wait_responses(sockets, tx);
     handle_received_peers(rx).await?;
 
     log::info!("discover ping done");
@@ -84,24 +103,40 @@ pub async fn discover() -> ResultType<()> {
 }
 
 pub fn send_wol(id: String) {
-    let interfaces = default_net::get_interfaces();
+    wake_lan_peer(&id);
+}
+
+/// Wakes `target`, a MAC address or the id of a peer found by LAN discovery.
+/// Returns false if no MAC address is known for it.
+pub fn wake_lan_peer(target: &str) -> bool {
+    if send_magic_packets(target) {
+        return true;
+    }
+    let mut sent = false;
     for peer in &config::LanPeers::load().peers {
-        if peer.id == id {
+        if peer.id == target {
             for (_, mac) in peer.ip_mac.iter() {
-                if let Ok(mac_addr) = mac.parse() {
-                    for interface in &interfaces {
-                        for ipv4 in &interface.ipv4 {
-                            // remove below mask check to avoid unexpected bug
-                            // if (u32::from(ipv4.addr) & u32::from(ipv4.netmask)) == (u32::from(peer_ip) & u32::from(ipv4.netmask))
-                            log::info!("Send wol to {mac_addr} of {}", ipv4.addr);
-                            allow_err!(wol::send_wol(mac_addr, None, Some(IpAddr::V4(ipv4.addr))));
-                        }
-                    }
-                }
+                sent |= send_magic_packets(mac);
             }
             break;
         }
     }
+    sent
+}
+
+fn send_magic_packets(mac: &str) -> bool {
+    let Ok(mac_addr) = mac.parse() else {
+        return false;
+    };
+    for interface in &default_net::get_interfaces() {
+        for ipv4 in &interface.ipv4 {
+            // remove below mask check to avoid unexpected bug
+            // if (u32::from(ipv4.addr) & u32::from(ipv4.netmask)) == (u32::from(peer_ip) & u32::from(ipv4.netmask))
+            log::info!("Send wol to {mac_addr} of {}", ipv4.addr);
+            allow_err!(wol::send_wol(mac_addr, None, Some(IpAddr::V4(ipv4.addr))));
+        }
+    }
+    true
 }
 
 #[inline]
@@ -287,8 +322,7 @@ fn wait_response(
     Ok(())
 }
 
-fn spawn_wait_responses(sockets: Vec<UdpSocket>) -> UnboundedReceiver<config::DiscoveryPeer> {
-    let (tx, rx) = unbounded_channel::<_>();
+fn spawn_wait_responses(sockets: Vec<UdpSocket>, tx: UnboundedSender<config::DiscoveryPeer>) {
     for socket in sockets {
         let tx_clone = tx.clone();
         std::thread::spawn(move || {
@@ -299,7 +333,6 @@ fn spawn_wait_responses(sockets: Vec<UdpSocket>) -> UnboundedReceiver<config::Di
             ));
         });
     }
-    rx
 }
 
 async fn handle_received_peers(mut rx: UnboundedReceiver<config::DiscoveryPeer>) -> ResultType<()> {
@@ -0,0 +1,140 @@
+//! DNS-SD over mDNS, next to the broadcast discovery in `lan.rs`.
+//!
+//! Wi-Fi access points and managed switches often filter broadcast but let multicast DNS
+//! through, so peers also announce themselves as `_rustdesk._tcp` services and `discover()`
+//! browses for them. Both feed the same list of lan peers.
+
+use hbb_common::{
+    allow_err,
+    config::{Config, DiscoveryPeer},
+    log,
+    tokio::sync::mpsc::UnboundedSender,
+    whoami, ResultType,
+};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+const SERVICE_TYPE: &str = "_rustdesk._tcp.local.";
+const BROWSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Announces this device while lan discovery is enabled.
+#[derive(Default)]
+pub(super) struct Responder {
+    // The announced id, no daemon if the registration failed. Not retried until the id changes.
+    registered: Option<(String, Option<ServiceDaemon>)>,
+}
+
+impl Responder {
+    pub fn update(&mut self, enabled: bool) {
+        let id = enabled.then(Config::get_id);
+        if self.registered.as_ref().map(|(id, _)| id) == id.as_ref() {
+            return;
+        }
+        if let Some((_, Some(daemon))) = self.registered.take() {
+            allow_err!(daemon.shutdown());
+        }
+        let Some(id) = id else {
+            return;
+        };
+        let daemon = match register(&id) {
+            Ok(daemon) => {
+                log::info!("mdns service registered");
+                Some(daemon)
+            }
+            Err(err) => {
+                log::error!("Failed to register mdns service: {err}");
+                None
+            }
+        };
+        self.registered = Some((id, daemon));
+    }
+}
+
+fn register(id: &str) -> ResultType<ServiceDaemon> {
+    let mut hostname = crate::whoami_hostname();
+    if hostname == "localhost" {
+        hostname = "unknown".to_owned();
+    }
+    let mac = default_net::get_default_interface()
+        .ok()
+        .and_then(|x| x.mac_addr)
+        .map(|x| x.address())
+        .unwrap_or_default();
+    let properties = HashMap::from([
+        ("id".to_owned(), id.to_owned()),
+        ("mac".to_owned(), mac),
+        ("hostname".to_owned(), hostname),
+        (
+            "username".to_owned(),
+            crate::platform::get_active_username(),
+        ),
+        ("platform".to_owned(), whoami::platform().to_string()),
+    ]);
+    let info = ServiceInfo::new(
+        SERVICE_TYPE,
+        id,
+        &format!("rustdesk-{id}.local."),
+        "",
+        crate::rendezvous_mediator::get_direct_port() as _,
+        properties,
+    )?
+    .enable_addr_auto();
+    let daemon = ServiceDaemon::new()?;
+    daemon.register(info)?;
+    Ok(daemon)
+}
+
+/// Browses for a few seconds and sends the resolved peers, except this device.
+pub(super) fn spawn_browse(tx: UnboundedSender<DiscoveryPeer>) {
+    std::thread::spawn(move || {
+        if let Err(err) = browse(tx) {
+            log::error!("mdns discovery failed: {err}");
+        }
+    });
+}
+
+fn browse(tx: UnboundedSender<DiscoveryPeer>) -> ResultType<()> {
+    let mut local_addrs = HashSet::new();
+    let mut local_macs = HashSet::new();
+    for interface in default_net::get_interfaces() {
+        local_addrs.extend(interface.ipv4.iter().map(|x| x.addr.to_string()));
+        local_addrs.extend(interface.ipv6.iter().map(|x| x.addr.to_string()));
+        if let Some(mac) = interface.mac_addr {
+            local_macs.insert(mac.address());
+        }
+    }
+    let daemon = ServiceDaemon::new()?;
+    let receiver = daemon.browse(SERVICE_TYPE)?;
+    let start = Instant::now();
+    while let Some(timeout) = BROWSE_TIMEOUT.checked_sub(start.elapsed()) {
+        let Ok(event) = receiver.recv_timeout(timeout) else {
+            break;
+        };
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        let property = |key: &str| {
+            info.get_property_val_str(key)
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let mac = property("mac");
+        let addrs: Vec<String> = info.get_addresses().iter().map(|x| x.to_string()).collect();
+        if local_macs.contains(&mac) || addrs.iter().any(|x| local_addrs.contains(x)) {
+            continue;
+        }
+        allow_err!(tx.send(DiscoveryPeer {
+            id: property("id"),
+            ip_mac: addrs.into_iter().map(|x| (x, mac.clone())).collect(),
+            username: property("username"),
+            hostname: property("hostname"),
+            platform: property("platform"),
+            online: true,
+        }));
+    }
+    allow_err!(daemon.shutdown());
+    Ok(())
+}
@@ -44,6 +44,9 @@ mod clipboard;
 #[cfg(not(any(target_os = "android", target_os = "ios", feature = "cli")))]
 pub mod core_main;
 mod custom_server;
+mod file_archive;
+mod file_drop;
+mod keyboard_remap;
 mod lang;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 mod port_forward;
@@ -76,3 +79,7 @@ pub mod privacy_mode;
 pub mod virtual_display_manager;
 
 mod kcp_stream;
+
+mod transfer_limit;
+#[cfg(not(target_os = "ios"))]
+mod udp_tunnel;
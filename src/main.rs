@@ -44,6 +44,7 @@ fn main() {
         "-p, --port-forward=[PORT-FORWARD-OPTIONS] 'Format: remote-id:local-port:remote-port[:remote-host]'
         -c, --connect=[REMOTE_ID] 'test only'
         -k, --key=[KEY] ''
+        --password-stdin 'Read the password of the remote peer from stdin instead of asking, RUSTDESK_PASSWORD works too'
        -s, --server=[] 'Start server'",
     );
     let matches = App::new("rustdesk")
@@ -54,25 +55,37 @@ fn main() {
         .get_matches();
     use hbb_common::{config::LocalConfig, env_logger::*};
     init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
+    let password = if matches.is_present("password-stdin") {
+        let mut password = String::new();
+        if let Err(err) = std::io::stdin().read_line(&mut password) {
+            log::error!("Failed to read the password: {}", err);
+            std::process::exit(cli::EXIT_USAGE);
+        }
+        Some(password.trim_end_matches(['\r', '\n']).to_owned())
+    } else {
+        // Not an argument, those are visible to every user in the process list.
+        std::env::var("RUSTDESK_PASSWORD").ok()
+    };
+    let mut ok = true;
     if let Some(p) = matches.value_of("port-forward") {
         let options: Vec<String> = p.split(":").map(|x| x.to_owned()).collect();
         if options.len() < 3 {
             log::error!("Wrong port-forward options");
-            return;
+            std::process::exit(cli::EXIT_USAGE);
         }
         let mut port = 0;
         if let Ok(v) = options[1].parse::<i32>() {
             port = v;
         } else {
             log::error!("Wrong local-port");
-            return;
+            std::process::exit(cli::EXIT_USAGE);
         }
         let mut remote_port = 0;
         if let Ok(v) = options[2].parse::<i32>() {
             remote_port = v;
         } else {
             log::error!("Wrong remote-port");
-            return;
+            std::process::exit(cli::EXIT_USAGE);
         }
         let mut remote_host = "localhost".to_owned();
         if options.len() > 3 {
@@ -82,23 +95,27 @@ fn main() {
         common::test_nat_type();
         let key = matches.value_of("key").unwrap_or("").to_owned();
         let token = LocalConfig::get_option("access_token");
-        cli::start_one_port_forward(
+        ok = cli::start_one_port_forward(
             options[0].clone(),
             port,
             remote_host,
             remote_port,
             key,
             token,
+            password,
         );
     } else if let Some(p) = matches.value_of("connect") {
         common::test_rendezvous_server();
         common::test_nat_type();
         let key = matches.value_of("key").unwrap_or("").to_owned();
         let token = LocalConfig::get_option("access_token");
-        cli::connect_test(p, key, token);
+        ok = cli::connect_test(p, key, token, password);
     } else if let Some(p) = matches.value_of("server") {
         log::info!("id={}", hbb_common::config::Config::get_id());
         crate::start_server(true, false);
     }
     common::global_clean();
+    if !ok {
+        std::process::exit(cli::EXIT_FAILURE);
+    }
 }
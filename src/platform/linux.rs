@@ -20,7 +20,7 @@ use std::{
     string::String,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     time::{Duration, Instant},
 };
@@ -38,6 +38,8 @@ const SHELL_PROCESSES: [&str; 4] = ["bash", "zsh", "fish", "sh"];
 
 lazy_static::lazy_static! {
     pub static ref IS_X11: bool = hbb_common::platform::linux::is_x11_or_headless();
+    // Ids of the devices disabled by `block_input`, `None` if input is not blocked.
+    static ref BLOCKED_INPUT_DEVICES: Mutex<Option<Vec<String>>> = Default::default();
     static ref DATABASE_XTERM_256COLOR: Option<Database> = {
         match Database::from_name("xterm-256color") {
             Ok(database) => Some(database),
@@ -848,10 +850,71 @@ pub fn toggle_blank_screen(_v: bool) {
     // https://unix.stackexchange.com/questions/17170/disable-keyboard-mouse-input-on-unix-under-x
 }
 
-pub fn block_input(_v: bool) -> (bool, String) {
+pub fn block_input(v: bool) -> (bool, String) {
+    if !is_x11() {
+        return (false, "Blocking input is only supported on X11".to_owned());
+    }
+    let mut blocked = BLOCKED_INPUT_DEVICES.lock().unwrap();
+    if !v {
+        if let Some(ids) = blocked.take() {
+            enable_input_devices(&ids);
+            std::fs::remove_file(blocked_input_devices_path()).ok();
+        }
+        return (true, "".to_owned());
+    }
+    // Called again on every idle tick of the input thread.
+    if blocked.is_some() {
+        return (true, "".to_owned());
+    }
+    let list = match Command::new("xinput").args(["list", "--short"]).output() {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => return (false, String::from_utf8_lossy(&output.stderr).into_owned()),
+        Err(e) => return (false, format!("Failed to run xinput: {}", e)),
+    };
+    // The physical devices are disabled, the XTEST devices the input is injected with are not.
+    let ids: Vec<String> = String::from_utf8_lossy(&list)
+        .lines()
+        .filter(|l| l.contains("[slave") && !l.contains("XTEST"))
+        .filter_map(|l| l.split("id=").nth(1)?.split_whitespace().next())
+        .map(|id| id.to_owned())
+        .collect();
+    // Written before disabling, a crash in between still leaves the ids to enable.
+    if let Err(e) = std::fs::write(blocked_input_devices_path(), ids.join("\n")) {
+        return (false, format!("Failed to save the input devices: {}", e));
+    }
+    let mut disabled = vec![];
+    for id in ids {
+        match Command::new("xinput").args(["disable", &id]).status() {
+            Ok(status) if status.success() => disabled.push(id),
+            _ => log::warn!("Failed to disable input device {}", id),
+        }
+    }
+    *blocked = Some(disabled);
     (true, "".to_owned())
 }
 
+fn blocked_input_devices_path() -> PathBuf {
+    Config::path("blocked_input_devices")
+}
+
+fn enable_input_devices(ids: &[String]) {
+    for id in ids {
+        allow_err!(Command::new("xinput").args(["enable", id]).status());
+    }
+}
+
+/// Enables the devices a killed or crashed server left disabled by `block_input`.
+pub fn enable_blocked_input_devices() {
+    let path = blocked_input_devices_path();
+    let Ok(ids) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let ids: Vec<String> = ids.lines().map(|id| id.to_owned()).collect();
+    log::info!("Enable the input devices left blocked: {:?}", ids);
+    enable_input_devices(&ids);
+    std::fs::remove_file(path).ok();
+}
+
 pub fn is_installed() -> bool {
     if let Ok(p) = std::env::current_exe() {
         p.to_str().unwrap_or_default().starts_with("/usr")
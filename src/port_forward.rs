@@ -1,20 +1,34 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-use crate::client::*;
+use crate::{client::*, udp_tunnel};
 use hbb_common::{
     allow_err, bail,
+    bytes::{Bytes, BytesMut},
     config::READ_TIMEOUT,
-    futures::{SinkExt, StreamExt},
+    futures::{self, SinkExt, StreamExt},
     log,
     message_proto::*,
     protobuf::Message as _,
     rendezvous_proto::ConnType,
     tcp, timeout,
-    tokio::{self, net::TcpStream, sync::mpsc},
+    tokio::{
+        self,
+        net::{TcpStream, UdpSocket},
+        sync::mpsc,
+        time::{self, Instant},
+    },
     tokio_util::codec::{BytesCodec, Framed},
     ResultType, Stream,
 };
 
+// UDP has no close, so a forwarded flow ends after this long without traffic either way.
+const UDP_SESSION_TIMEOUT: Duration = Duration::from_secs(180);
+
 fn run_rdp(port: u16) {
     std::process::Command::new("cmdkey")
         .arg("/delete:localhost")
@@ -54,6 +68,25 @@ pub async fn listen(
     remote_host: String,
     remote_port: i32,
 ) -> ResultType<()> {
+    if remote_host.starts_with(crate::lan::WOL_SCHEME) {
+        lc.write().unwrap().port_forward = (remote_host, remote_port);
+        return wake_via_peer(id, password, interface, ui_receiver, key, token).await;
+    }
+    if udp_tunnel::strip_scheme(&remote_host).is_some() {
+        return listen_udp(
+            id,
+            password,
+            port,
+            interface,
+            ui_receiver,
+            key,
+            token,
+            lc,
+            remote_host,
+            remote_port,
+        )
+        .await;
+    }
     let listener = tcp::new_listener(format!("0.0.0.0:{}", port), true).await?;
     let addr = listener.local_addr()?;
     log::info!("listening on port {:?}", addr);
@@ -103,12 +136,170 @@ pub async fn listen(
     Ok(())
 }
 
+// Asks the peer to wake a machine on its LAN, see `crate::lan::WOL_SCHEME`.
+async fn wake_via_peer(
+    id: String,
+    password: String,
+    interface: impl Interface,
+    ui_receiver: mpsc::UnboundedReceiver<Data>,
+    key: &str,
+    token: &str,
+) -> ResultType<()> {
+    let mut ui_receiver = ui_receiver;
+    let mut pending = futures::stream::pending();
+    let Some(mut stream) = connect_and_login(
+        &id,
+        &password,
+        &mut ui_receiver,
+        interface.clone(),
+        &mut pending,
+        key,
+        token,
+        false,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+    match timeout(READ_TIMEOUT, stream.next()).await {
+        Ok(Some(Ok(bytes))) if &bytes[..] == crate::lan::WOL_REPLY_SENT.as_bytes() => {
+            interface.msgbox("info", "Wake-on-LAN", "Magic packet sent", "");
+        }
+        Ok(Some(Ok(bytes))) if &bytes[..] == crate::lan::WOL_REPLY_UNKNOWN.as_bytes() => {
+            interface.msgbox(
+                "error",
+                "Wake-on-LAN",
+                "The peer does not know the MAC address of this device",
+                "",
+            );
+        }
+        _ => {
+            bail!("No response to the Wake-on-LAN request");
+        }
+    }
+    Ok(())
+}
+
+// Each source address is a separate flow with its own connection to the peer, the same way
+// every accepted TCP connection is.
+async fn listen_udp(
+    id: String,
+    password: String,
+    port: i32,
+    interface: impl Interface,
+    ui_receiver: mpsc::UnboundedReceiver<Data>,
+    key: &str,
+    token: &str,
+    lc: Arc<RwLock<LoginConfigHandler>>,
+    remote_host: String,
+    remote_port: i32,
+) -> ResultType<()> {
+    let socket = Arc::new(UdpSocket::bind(format!("0.0.0.0:{}", port)).await?);
+    log::info!("listening on udp port {:?}", socket.local_addr()?);
+    // Logins run in their own task so the flows already forwarded keep going meanwhile,
+    // the datagrams of a new flow are queued until its connection is up.
+    let (login_tx, login_rx) = mpsc::unbounded_channel();
+    tokio::spawn(login_udp_flows(
+        id,
+        password,
+        interface,
+        ui_receiver,
+        key.to_owned(),
+        token.to_owned(),
+        lc,
+        (remote_host, remote_port),
+        socket.clone(),
+        login_rx,
+    ));
+    let mut flows = HashMap::<SocketAddr, mpsc::UnboundedSender<Bytes>>::new();
+    let mut buf = vec![0u8; udp_tunnel::MAX_DATAGRAM_SIZE];
+    loop {
+        tokio::select! {
+            res = socket.recv_from(&mut buf) => {
+                let (n, addr) = match res {
+                    Ok(res) => res,
+                    Err(err) => {
+                        log::debug!("udp recv error: {}", err);
+                        continue;
+                    }
+                };
+                let datagram = Bytes::copy_from_slice(&buf[..n]);
+                let datagram = match flows.get(&addr) {
+                    Some(tx) => match tx.send(datagram) {
+                        Ok(_) => continue,
+                        Err(err) => err.0,
+                    },
+                    None => datagram,
+                };
+                flows.retain(|_, tx| !tx.is_closed());
+                log::info!("new udp flow from {:?}", addr);
+                let (tx, rx) = mpsc::unbounded_channel();
+                tx.send(datagram).ok();
+                flows.insert(addr, tx);
+                if login_tx.send((addr, rx)).is_err() {
+                    break;
+                }
+            }
+            _ = login_tx.closed() => {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Owns the ui receiver, logs in the new flows one by one and stops on `Data::Close`.
+async fn login_udp_flows(
+    id: String,
+    password: String,
+    interface: impl Interface,
+    ui_receiver: mpsc::UnboundedReceiver<Data>,
+    key: String,
+    token: String,
+    lc: Arc<RwLock<LoginConfigHandler>>,
+    port_forward: (String, i32),
+    socket: Arc<UdpSocket>,
+    login_rx: mpsc::UnboundedReceiver<(SocketAddr, mpsc::UnboundedReceiver<Bytes>)>,
+) {
+    let mut ui_receiver = ui_receiver;
+    let mut login_rx = login_rx;
+    loop {
+        tokio::select! {
+            Some((addr, rx)) = login_rx.recv() => {
+                lc.write().unwrap().port_forward = port_forward.clone();
+                let mut pending = futures::stream::pending();
+                match connect_and_login(&id, &password, &mut ui_receiver, interface.clone(), &mut pending, &key, &token, false).await {
+                    Ok(Some(stream)) => {
+                        let socket = socket.clone();
+                        let interface = interface.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = run_udp_forward(socket, addr, rx, stream).await {
+                                interface.msgbox("error", "Error", &err.to_string(), "");
+                            }
+                            log::info!("udp flow from {:?} closed", addr);
+                        });
+                    }
+                    Err(err) => {
+                        interface.on_establish_connection_error(err.to_string());
+                    }
+                    _ => {}
+                }
+            }
+            d = ui_receiver.recv() => {
+                if let Some(Data::Close) | None = d {
+                    break;
+                }
+            }
+        }
+    }
+}
+
 async fn connect_and_login(
     id: &str,
     password: &str,
     ui_receiver: &mut mpsc::UnboundedReceiver<Data>,
     interface: impl Interface,
-    forward: &mut Framed<TcpStream, BytesCodec>,
+    forward: &mut (impl futures::Stream<Item = std::io::Result<BytesMut>> + Unpin),
     key: &str,
     token: &str,
     is_rdp: bool,
@@ -218,3 +409,46 @@ async fn run_forward(forward: Framed<TcpStream, BytesCodec>, stream: Stream) ->
     }
     Ok(())
 }
+
+async fn run_udp_forward(
+    socket: Arc<UdpSocket>,
+    addr: SocketAddr,
+    rx: mpsc::UnboundedReceiver<Bytes>,
+    stream: Stream,
+) -> ResultType<()> {
+    log::info!("new udp forwarding connection started");
+    let mut rx = rx;
+    let mut stream = stream;
+    let mut decoder = udp_tunnel::Decoder::default();
+    let mut last_recv_time = Instant::now();
+    let mut timer = time::interval(Duration::from_secs(5));
+    loop {
+        tokio::select! {
+            res = rx.recv() => {
+                if let Some(datagram) = res {
+                    last_recv_time = Instant::now();
+                    allow_err!(stream.send_bytes(udp_tunnel::encode(&datagram).into()).await);
+                } else {
+                    break;
+                }
+            },
+            res = stream.next() => {
+                if let Some(Ok(bytes)) = res {
+                    last_recv_time = Instant::now();
+                    decoder.push(&bytes);
+                    while let Some(datagram) = decoder.next_datagram() {
+                        allow_err!(socket.send_to(&datagram, addr).await);
+                    }
+                } else {
+                    break;
+                }
+            },
+            _ = timer.tick() => {
+                if last_recv_time.elapsed() >= UDP_SESSION_TIMEOUT {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
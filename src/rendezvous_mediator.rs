@@ -1,5 +1,5 @@
 use std::{
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, RwLock,
@@ -91,7 +91,7 @@ impl RendezvousMediator {
         if crate::is_server() {
             crate::platform::linux_desktop_manager::start_xdesktop();
         }
-        scrap::codec::test_av1();
+        scrap::codec::test_encoders();
         loop {
             let timeout = Arc::new(RwLock::new(CONNECT_TIMEOUT));
             let conn_start_time = Instant::now();
@@ -100,7 +100,11 @@ impl RendezvousMediator {
                 && !crate::platform::installing_service()
             {
                 let mut futs = Vec::new();
-                let servers = Config::get_rendezvous_servers();
+                let servers = if crate::is_lan_only() {
+                    vec![]
+                } else {
+                    Config::get_rendezvous_servers()
+                };
                 SHOULD_EXIT.store(false, Ordering::SeqCst);
                 MANUAL_RESTARTED.store(false, Ordering::SeqCst);
                 for host in servers.clone() {
@@ -566,9 +570,11 @@ impl RendezvousMediator {
             socket_addr_v6 = start_ipv6(peer_addr_v6, peer_addr, server.clone()).await;
         }
         let relay_server = self.get_relay_server(ph.relay_server);
+        // Only this side symmetric, the tcp punch can still meet the peer by a birthday attack.
+        let birthday = crate::use_birthday_punch(Config::get_nat_type(), ph.nat_type.value());
         // for ensure, websocket go relay directly
         if ph.nat_type.enum_value() == Ok(NatType::SYMMETRIC)
-            || Config::get_nat_type() == NatType::SYMMETRIC as i32
+            || (birthday && ph.udp_port > 0)
             || relay
             || (config::is_disable_tcp_listen() && ph.udp_port <= 0)
         {
@@ -610,11 +616,25 @@ impl RendezvousMediator {
             allow_err!(socket_client::connect_tcp_local(peer_addr, Some(local_addr), 30).await);
             socket
         };
+        // Opened after the rendezvous connection, so a NAT that allocates ports in order maps
+        // the first ones to the ports the peer predicts.
+        let birthday_addrs = if birthday {
+            birthday_punch(peer_addr, socket.local_addr().ip()).await
+        } else {
+            vec![]
+        };
         let mut msg_out = Message::new();
         msg_out.set_punch_hole_sent(msg_punch);
         let bytes = msg_out.write_to_bytes()?;
         socket.send_raw(bytes).await?;
-        crate::accept_connection(server.clone(), socket, peer_addr, true).await;
+        if birthday_addrs.is_empty() {
+            crate::accept_connection(server.clone(), socket, peer_addr, true).await;
+        } else {
+            let mut local_addrs = vec![socket.local_addr()];
+            local_addrs.extend(birthday_addrs);
+            drop(socket);
+            crate::accept_first_connection(server.clone(), local_addrs, peer_addr, true).await;
+        }
         Ok(())
     }
 
@@ -714,7 +734,33 @@ impl RendezvousMediator {
     }
 }
 
-fn get_direct_port() -> i32 {
+// Punches from `BIRTHDAY_PORTS` new local ports, returns them to listen on.
+async fn birthday_punch(peer_addr: SocketAddr, ip: IpAddr) -> Vec<SocketAddr> {
+    // Free ports, released right away to punch and then listen from them.
+    let local_addrs: Vec<SocketAddr> = (0..crate::BIRTHDAY_PORTS)
+        .filter_map(|_| {
+            std::net::TcpListener::bind((ip, 0))
+                .and_then(|l| l.local_addr())
+                .ok()
+        })
+        .collect();
+    log::debug!(
+        "Punch tcp hole to {:?} from {} ports",
+        peer_addr,
+        local_addrs.len()
+    );
+    for batch in local_addrs.chunks(crate::BIRTHDAY_BATCH) {
+        join_all(
+            batch
+                .iter()
+                .map(|addr| socket_client::connect_tcp_local(peer_addr, Some(*addr), 30)),
+        )
+        .await;
+    }
+    local_addrs
+}
+
+pub(crate) fn get_direct_port() -> i32 {
     let mut port = Config::get_option("direct-access-port")
         .parse::<i32>()
         .unwrap_or(0);
@@ -728,10 +774,12 @@ async fn direct_server(server: ServerPtr) {
     let mut listener = None;
     let mut port = 0;
     loop {
-        let disabled = !option2bool(
+        // Direct connections are the only way in while LAN-only.
+        let disabled = !(option2bool(
             OPTION_DIRECT_SERVER,
             &Config::get_option(OPTION_DIRECT_SERVER),
-        ) || option2bool("stop-service", &Config::get_option("stop-service"));
+        ) || crate::is_lan_only())
+            || option2bool("stop-service", &Config::get_option("stop-service"));
         if !disabled && listener.is_none() {
             port = get_direct_port();
             match hbb_common::tcp::listen_any(port as _).await {
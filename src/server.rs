@@ -33,6 +33,7 @@ use video_service::VideoSource;
 use crate::ipc::Data;
 
 pub mod audio_service;
+mod audit;
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 pub mod terminal_service;
 cfg_if::cfg_if! {
@@ -65,9 +66,16 @@ pub mod input_service {
 }
 
 mod connection;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod control;
 pub mod display_service;
+mod input_arbitration;
+mod login_backoff;
+mod metrics;
+mod permission_profile;
 #[cfg(windows)]
 pub mod portable_service;
+mod privacy_mask;
 mod service;
 mod video_qos;
 pub mod video_service;
@@ -168,6 +176,30 @@ async fn accept_connection_(server: ServerPtr, socket: Stream, secure: bool) ->
     Ok(())
 }
 
+// The birthday punch listens on many ports, the first connection on any of them is taken.
+async fn accept_first_connection_(
+    server: ServerPtr,
+    local_addrs: Vec<SocketAddr>,
+    secure: bool,
+) -> ResultType<()> {
+    use hbb_common::futures::{future::select_ok, FutureExt};
+    let mut accepts = Vec::new();
+    for local_addr in local_addrs {
+        match new_listener(local_addr, true).await {
+            Ok(listener) => accepts.push(async move { listener.accept().await }.boxed()),
+            Err(err) => log::debug!("Failed to listen on {}: {}", local_addr, err),
+        }
+    }
+    if accepts.is_empty() {
+        bail!("No port to listen on");
+    }
+    log::info!("Server listening on {} punched ports", accepts.len());
+    let ((stream, addr), _) = timeout(CONNECT_TIMEOUT, select_ok(accepts)).await??;
+    stream.set_nodelay(true).ok();
+    let stream_addr = stream.local_addr()?;
+    create_tcp_connection(server, Stream::from(stream, stream_addr), addr, secure).await
+}
+
 pub async fn create_tcp_connection(
     server: ServerPtr,
     stream: Stream,
@@ -255,6 +287,17 @@ pub async fn accept_connection(
     }
 }
 
+pub async fn accept_first_connection(
+    server: ServerPtr,
+    local_addrs: Vec<SocketAddr>,
+    peer_addr: SocketAddr,
+    secure: bool,
+) {
+    if let Err(err) = accept_first_connection_(server, local_addrs, secure).await {
+        log::warn!("Failed to accept connection from {}: {}", peer_addr, err);
+    }
+}
+
 pub async fn create_relay_connection(
     server: ServerPtr,
     relay_server: String,
@@ -523,6 +566,7 @@ pub fn check_zombie() {
 #[cfg(any(target_os = "android", target_os = "ios"))]
 #[tokio::main]
 pub async fn start_server(_is_server: bool) {
+    metrics::start();
     crate::RendezvousMediator::start_all().await;
 }
 
@@ -563,6 +607,8 @@ pub async fn start_server(is_server: bool, no_server: bool) {
         });
         input_service::fix_key_down_timeout_loop();
         #[cfg(target_os = "linux")]
+        crate::platform::linux::enable_blocked_input_devices();
+        #[cfg(target_os = "linux")]
         if input_service::wayland_use_uinput() {
             allow_err!(input_service::setup_uinput(0, 1920, 0, 1080).await);
         }
@@ -572,6 +618,8 @@ pub async fn start_server(is_server: bool, no_server: bool) {
         crate::platform::try_kill_broker();
         #[cfg(feature = "hwcodec")]
         scrap::hwcodec::start_check_process();
+        metrics::start();
+        control::start();
         crate::RendezvousMediator::start_all().await;
     } else {
         match crate::ipc::connect(1000, "").await {
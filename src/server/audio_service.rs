@@ -15,15 +15,17 @@
 use super::*;
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
 use hbb_common::anyhow::anyhow;
-use magnum_opus::{Application::*, Channels::*, Encoder};
+use magnum_opus::{Application::*, Bitrate, Channels::*, Encoder};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 pub const NAME: &'static str = "audio";
 pub const AUDIO_DATA_SIZE_U8: usize = 960 * 4; // 10ms in 48000 stereo
 static RESTARTING: AtomicBool = AtomicBool::new(false);
+const AUDIO_QOS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 lazy_static::lazy_static! {
     static ref VOICE_CALL_INPUT_DEVICE: Arc::<Mutex::<Option<String>>> = Default::default();
+    static ref AUDIO_QOS: Mutex<AudioQos> = Default::default();
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
@@ -100,6 +102,7 @@ mod pa_impl {
         unsafe {
             AUDIO_ZERO_COUNT = 0;
         }
+        *AUDIO_QOS.lock().unwrap() = Default::default();
         let mut encoder = Encoder::new(crate::platform::PA_SAMPLE_RATE, Stereo, LowDelay)?;
         #[cfg(target_os = "linux")]
         allow_err!(
@@ -403,6 +406,7 @@ mod cpal_impl {
         unsafe {
             AUDIO_ZERO_COUNT = 0;
         }
+        *AUDIO_QOS.lock().unwrap() = Default::default();
         let device_channel = config.channels();
         let mut encoder = Encoder::new(sample_rate, encode_channel, LowDelay)?;
         // https://www.opus-codec.org/docs/html_api/group__opusencoder.html#gace941e4ef26ed844879fde342ffbe546
@@ -465,6 +469,56 @@ fn create_format_msg(sample_rate: u32, channels: u16) -> Message {
 const MAX_AUDIO_ZERO_COUNT: u16 = 800;
 static mut AUDIO_ZERO_COUNT: u16 = 0;
 
+// Bitrate and packet size of the encoder, following the network health of the video.
+struct AudioQos {
+    checked: Option<std::time::Instant>,
+    // 0 until set, the encoder starts with its own default
+    bitrate: i32,
+    frames: usize,
+    pending: Vec<f32>,
+}
+
+impl Default for AudioQos {
+    fn default() -> Self {
+        Self {
+            checked: None,
+            bitrate: 0,
+            frames: 1,
+            pending: vec![],
+        }
+    }
+}
+
+impl AudioQos {
+    fn update(&mut self, encoder: &mut Encoder) {
+        // A packet size change would leave an invalid Opus frame behind
+        if !self.pending.is_empty()
+            || self
+                .checked
+                .map_or(false, |t| t.elapsed() < AUDIO_QOS_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.checked = Some(std::time::Instant::now());
+        let health = video_service::VIDEO_QOS.lock().unwrap().network_health();
+        let (bitrate, frames) = health.audio_encoding();
+        if bitrate != self.bitrate {
+            if let Err(e) = encoder.set_bitrate(Bitrate::Bits(bitrate)) {
+                log::error!("Failed to set audio bitrate: {:?}", e);
+                return;
+            }
+            log::debug!(
+                "Audio bitrate {} bps, {} ms packets, network {:?}",
+                bitrate,
+                frames * 10,
+                health
+            );
+            self.bitrate = bitrate;
+        }
+        self.frames = frames;
+    }
+}
+
 fn send_f32(data: &[f32], encoder: &mut Encoder, sp: &GenericService) {
     if data.iter().filter(|x| **x != 0.).next().is_some() {
         unsafe {
@@ -488,6 +542,7 @@ fn send_f32(data: &[f32], encoder: &mut Encoder, sp: &GenericService) {
         // if data size is bigger than BATCH_SIZE, AND is an integer multiple of BATCH_SIZE
         // then upload in batches
         const BATCH_SIZE: usize = 960;
+        AUDIO_QOS.lock().unwrap().update(encoder);
         let input_size = data.len();
         if input_size > BATCH_SIZE && input_size % BATCH_SIZE == 0 {
             let n = input_size / BATCH_SIZE;
@@ -512,6 +567,20 @@ fn send_f32(data: &[f32], encoder: &mut Encoder, sp: &GenericService) {
         }
     }
 
+    #[cfg(not(target_os = "android"))]
+    let pending;
+    #[cfg(not(target_os = "android"))]
+    let data = {
+        // Consecutive 10ms frames are joined into one packet under congestion
+        let mut qos = AUDIO_QOS.lock().unwrap();
+        qos.update(encoder);
+        qos.pending.extend_from_slice(data);
+        if qos.pending.len() < data.len() * qos.frames {
+            return;
+        }
+        pending = std::mem::take(&mut qos.pending);
+        &pending[..]
+    };
     #[cfg(not(target_os = "android"))]
     match encoder.encode_vec_float(data, data.len() * 6) {
         Ok(data) => {
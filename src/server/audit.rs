@@ -0,0 +1,206 @@
+//! Local audit log of remote access.
+//!
+//! Events are appended as JSON lines to `audit.log` in the log directory when the `audit-log`
+//! option is "Y", and posted to `audit-log-url` when it is set. This works without an API
+//! server, unlike the connection and file audits posted by `Connection`.
+//!
+//! With `audit-log-secret` set, posts carry the hex HMAC-SHA256 of the body in
+//! `X-RustDesk-Signature`, so a webhook receiver can tell them from forged ones.
+
+use hbb_common::{allow_err, bail, config::Config, get_time, log, ResultType};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+const OPTION_AUDIT_LOG: &str = "audit-log";
+const OPTION_AUDIT_LOG_URL: &str = "audit-log-url";
+const OPTION_AUDIT_LOG_SECRET: &str = "audit-log-secret";
+const FILE_NAME: &str = "audit.log";
+const POST_ATTEMPTS: u32 = 3;
+
+lazy_static::lazy_static! {
+    static ref SENDER: Mutex<mpsc::Sender<Record>> = Mutex::new(start_writer());
+}
+
+struct Record {
+    value: Value,
+    to_file: bool,
+    url: String,
+    secret: String,
+}
+
+/// Records `event` with `fields`, which must be a JSON object.
+pub fn emit(event: &str, fields: Value) {
+    let to_file = Config::get_option(OPTION_AUDIT_LOG) == "Y";
+    let url = Config::get_option(OPTION_AUDIT_LOG_URL);
+    if !to_file && url.is_empty() {
+        return;
+    }
+    let mut value = json!({
+        "time": get_time(),
+        "event": event,
+        "device_id": Config::get_id(),
+    });
+    if let (Some(value), Value::Object(fields)) = (value.as_object_mut(), fields) {
+        value.extend(fields);
+    }
+    allow_err!(SENDER.lock().unwrap().send(Record {
+        value,
+        to_file,
+        url,
+        secret: Config::get_option(OPTION_AUDIT_LOG_SECRET),
+    }));
+}
+
+struct Post {
+    url: String,
+    body: String,
+    header: String,
+    attempt: u32,
+    retry_at: Instant,
+}
+
+// Writing and posting happen off the connection loops, a slow endpoint must not stall a session.
+// Failed posts wait in a queue, so they do not hold up the events behind them either.
+fn start_writer() -> mpsc::Sender<Record> {
+    let (tx, rx) = mpsc::channel::<Record>();
+    std::thread::spawn(move || {
+        let mut retries: Vec<Post> = vec![];
+        loop {
+            let next_retry = retries.iter().map(|p| p.retry_at).min();
+            let record = match next_retry {
+                Some(at) => match rx.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                    Ok(record) => Some(record),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match rx.recv() {
+                    Ok(record) => Some(record),
+                    Err(_) => break,
+                },
+            };
+            if let Some(record) = record {
+                let line = record.value.to_string();
+                if record.to_file {
+                    if let Err(err) = append(&line) {
+                        log::error!("Failed to write audit log: {}", err);
+                    }
+                }
+                if !record.url.is_empty() {
+                    let header = header(&line, &record.secret);
+                    retries.extend(try_post(Post {
+                        url: record.url,
+                        body: line,
+                        header,
+                        attempt: 0,
+                        retry_at: Instant::now(),
+                    }));
+                }
+            }
+            let now = Instant::now();
+            let (due, waiting): (Vec<_>, Vec<_>) =
+                retries.into_iter().partition(|p| p.retry_at <= now);
+            retries = waiting;
+            for post in due {
+                retries.extend(try_post(post));
+            }
+        }
+    });
+    tx
+}
+
+// Returns the post to try again if it failed with attempts left.
+fn try_post(mut post: Post) -> Option<Post> {
+    post.attempt += 1;
+    let err = match send(&post) {
+        Ok(()) => return None,
+        Err(err) => err,
+    };
+    log::error!(
+        "Failed to post audit event, attempt {}: {}",
+        post.attempt,
+        err
+    );
+    if post.attempt >= POST_ATTEMPTS {
+        return None;
+    }
+    post.retry_at = Instant::now() + Duration::from_secs(1 << post.attempt);
+    Some(post)
+}
+
+fn header(body: &str, secret: &str) -> String {
+    let mut header = json!({ "Content-Type": "application/json" });
+    if !secret.is_empty() {
+        header["X-RustDesk-Signature"] = json!(format!(
+            "sha256={}",
+            hex::encode(hmac_sha256(secret.as_bytes(), body.as_bytes()))
+        ));
+    }
+    header.to_string()
+}
+
+// An error status is a failure too, the endpoint did not take the event.
+fn send(post: &Post) -> ResultType<()> {
+    let res = crate::http_request_sync(
+        post.url.clone(),
+        "post".to_owned(),
+        Some(post.body.clone()),
+        post.header.clone(),
+    )?;
+    let status = serde_json::from_str::<Value>(&res)?["status_code"]
+        .as_u64()
+        .unwrap_or_default();
+    if !(200..300).contains(&status) {
+        bail!("HTTP status {}", status);
+    }
+    Ok(())
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC takes keys of any length")
+        .chain_update(data)
+        .finalize()
+        .into_bytes()
+        .into()
+}
+
+fn append(line: &str) -> std::io::Result<()> {
+    let dir = Config::log_path();
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(FILE_NAME))?;
+    writeln!(file, "{}", line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test cases 2 and 6.
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex::encode(hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+}
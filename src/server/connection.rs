@@ -17,7 +17,7 @@ use crate::{
     client::{
         new_voice_call_request, new_voice_call_response, start_audio_thread, MediaData, MediaSender,
     },
-    display_service, ipc, privacy_mode, video_service, VERSION,
+    display_service, ipc, privacy_mode, transfer_limit, video_service, VERSION,
 };
 #[cfg(any(target_os = "android", target_os = "ios"))]
 use crate::{common::DEVICE_NAME, flutter::connection_manager::start_channel};
@@ -27,6 +27,7 @@ use hbb_common::platform::linux::run_cmds;
 #[cfg(target_os = "android")]
 use hbb_common::protobuf::EnumOrUnknown;
 use hbb_common::{
+    bytes::BytesMut,
     config::{self, keys, Config, TrustedDevice},
     fs::{self, can_enable_overwrite_detection, JobType},
     futures::{SinkExt, StreamExt},
@@ -69,8 +70,10 @@ pub type Sender = mpsc::UnboundedSender<(Instant, Arc<Message>)>;
 
 lazy_static::lazy_static! {
     static ref LOGIN_FAILURES: [Arc::<Mutex<HashMap<String, (i32, i32, i32)>>>; 2] = Default::default();
+    static ref LOGIN_BACKOFF: Mutex<login_backoff::LoginBackoff> = Default::default();
+    static ref INPUT_ARBITER: Mutex<input_arbitration::InputArbiter> = Default::default();
     static ref SESSIONS: Arc::<Mutex<HashMap<SessionKey, Session>>> = Default::default();
-    static ref ALIVE_CONNS: Arc::<Mutex<Vec<i32>>> = Default::default();
+    static ref ALIVE_CONNS: Arc::<Mutex<Vec<(i32, mpsc::UnboundedSender<Data>)>>> = Default::default();
     pub static ref AUTHED_CONNS: Arc::<Mutex<Vec<AuthedConn>>> = Default::default();
     static ref SWITCH_SIDES_UUID: Arc::<Mutex<HashMap<String, (Instant, uuid::Uuid)>>> = Default::default();
     static ref WAKELOCK_SENDER: Arc::<Mutex<std::sync::mpsc::Sender<(usize, usize)>>> = Arc::new(Mutex::new(start_wakelock_thread()));
@@ -203,6 +206,32 @@ impl TerminalUserToken {
         }
     }
 }
+// The target of a port-forward connection, UDP when the controlling side asked for it.
+// `Wol` is not forwarded, it only wakes the given LAN peer once authorized.
+enum PortForwardSocket {
+    Tcp(Framed<TcpStream, BytesCodec>),
+    Udp(crate::udp_tunnel::UdpForward),
+    Wol(String),
+}
+
+impl PortForwardSocket {
+    async fn next(&mut self) -> Option<std::io::Result<BytesMut>> {
+        match self {
+            Self::Tcp(forward) => forward.next().await,
+            Self::Udp(forward) => forward.next().await,
+            Self::Wol(_) => None,
+        }
+    }
+
+    async fn send(&mut self, bytes: BytesMut) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(forward) => forward.send(bytes).await,
+            Self::Udp(forward) => forward.send(bytes).await,
+            Self::Wol(_) => Ok(()),
+        }
+    }
+}
+
 pub struct Connection {
     inner: ConnInner,
     display_idx: usize,
@@ -215,18 +244,26 @@ pub struct Connection {
     file_transfer: Option<(String, bool)>,
     view_camera: bool,
     terminal: bool,
-    port_forward_socket: Option<Framed<TcpStream, BytesCodec>>,
+    port_forward_socket: Option<PortForwardSocket>,
     port_forward_address: String,
     tx_to_cm: mpsc::UnboundedSender<ipc::Data>,
     authorized: bool,
     require_2fa: Option<totp_rs::TOTP>,
     keyboard: bool,
+    // by cm, lasts until the connection is closed
+    view_only_forced: bool,
+    permission_profile: permission_profile::PermissionProfile,
     clipboard: bool,
     audio: bool,
+    // Voice calls play the microphone of the controller here, separate from sending our audio
+    voice_call: bool,
     file: bool,
     restart: bool,
     recording: bool,
     block_input: bool,
+    // Asked to block the local input, which has to be lifted when the connection is dropped.
+    #[cfg(target_os = "linux")]
+    input_blocked: bool,
     last_test_delay: Option<Instant>,
     network_delay: u32,
     lock_after_session_end: bool,
@@ -266,12 +303,19 @@ pub struct Connection {
     options_in_login: Option<OptionMessage>,
     #[cfg(not(any(target_os = "ios")))]
     pressed_modifiers: HashSet<rdev::Key>,
+    // Keys by their mode and value.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    pressed_input: input_arbitration::PressedInput<(i32, Option<key_event::Union>)>,
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    input_control: input_arbitration::InputControl,
     #[cfg(target_os = "linux")]
     linux_headless_handle: LinuxHeadlessHandle,
     closed: bool,
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     start_cm_ipc_para: Option<StartCmIpcPara>,
     auto_disconnect_timer: Option<(Instant, u64)>,
+    session_deadline: Option<Instant>,
+    disconnect_warned: bool,
     authed_conn_id: Option<self::raii::AuthedConnID>,
     file_remove_log_control: FileRemoveLogControl,
     last_supported_encoding: Option<SupportedEncoding>,
@@ -284,6 +328,11 @@ pub struct Connection {
     multi_ui_session: bool,
     tx_from_authed: mpsc::UnboundedSender<ipc::Data>,
     printer_data: Vec<(Instant, String, Vec<u8>)>,
+    // Ids of the jobs of files dropped onto the remote view.
+    file_drop_jobs: Vec<i32>,
+    file_transfer_limiter: transfer_limit::TransferLimiter,
+    // Temporary archives of directories being sent.
+    archives: Vec<PathBuf>,
     // For post requests that need to be sent sequentially.
     // eg. post_conn_audit
     tx_post_seq: mpsc::UnboundedSender<(String, Value)>,
@@ -338,6 +387,26 @@ const MILLI1: Duration = Duration::from_millis(1);
 const SEND_TIMEOUT_VIDEO: u64 = 12_000;
 const SEND_TIMEOUT_OTHER: u64 = SEND_TIMEOUT_VIDEO * 10;
 const SESSION_TIMEOUT: Duration = Duration::from_secs(30);
+// Comma separated IPs or CIDR ranges that may not connect, checked before the whitelist.
+const OPTION_BLACKLIST: &str = "blacklist";
+// Comma separated IPs or CIDR ranges that may connect in LAN-only mode, the private ranges if empty.
+const OPTION_LAN_ONLY_SUBNETS: &str = "lan-only-subnets";
+const PRIVATE_SUBNETS: &[&str] = &[
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "169.254.0.0/16",
+    "127.0.0.0/8",
+    "fc00::/7",
+    "fe80::/10",
+    "::1/128",
+];
+// "Y" to slow file transfer down while the network struggles to carry the video.
+const OPTION_FILE_TRANSFER_YIELD_TO_VIDEO: &str = "file-transfer-yield-to-video";
+// Minutes a session may last at most, empty or "0" means no limit.
+const OPTION_MAX_SESSION_DURATION: &str = "max-session-duration";
+// The controller is warned this long before the session is closed by a timeout.
+const DISCONNECT_WARNING: Duration = Duration::from_secs(60);
 
 impl Connection {
     pub async fn start(
@@ -346,13 +415,13 @@ impl Connection {
         id: i32,
         server: super::ServerPtrWeak,
     ) {
-        let _raii_id = raii::ConnectionID::new(id);
         let hash = Hash {
             salt: Config::get_salt(),
             challenge: Config::get_auto_password(6),
             ..Default::default()
         };
         let (tx_from_cm_holder, mut rx_from_cm) = mpsc::unbounded_channel::<ipc::Data>();
+        let _raii_id = raii::ConnectionID::new(id, tx_from_cm_holder.clone());
         // holding tx_from_cm_holder to avoid cpu burning of rx_from_cm.recv when all sender closed
         let tx_from_cm = tx_from_cm_holder.clone();
         let (tx_to_cm, rx_to_cm) = mpsc::unbounded_channel::<ipc::Data>();
@@ -398,13 +467,18 @@ impl Connection {
             tx_to_cm,
             authorized: false,
             keyboard: Connection::permission("enable-keyboard"),
+            view_only_forced: false,
+            permission_profile: Default::default(),
             clipboard: Connection::permission("enable-clipboard"),
             audio: Connection::permission("enable-audio"),
+            voice_call: Connection::permission("enable-voice-call"),
             // to-do: make sure is the option correct here
             file: Connection::permission(keys::OPTION_ENABLE_FILE_TRANSFER),
             restart: Connection::permission("enable-remote-restart"),
             recording: Connection::permission("enable-record-session"),
             block_input: Connection::permission("enable-block-input"),
+            #[cfg(target_os = "linux")]
+            input_blocked: false,
             last_test_delay: None,
             network_delay: 0,
             lock_after_session_end: false,
@@ -438,6 +512,10 @@ impl Connection {
             options_in_login: None,
             #[cfg(not(any(target_os = "ios")))]
             pressed_modifiers: Default::default(),
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            pressed_input: Default::default(),
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            input_control: Default::default(),
             #[cfg(target_os = "linux")]
             linux_headless_handle,
             closed: false,
@@ -449,6 +527,8 @@ impl Connection {
                 tx_cm_stream_ready,
             }),
             auto_disconnect_timer: None,
+            session_deadline: None,
+            disconnect_warned: false,
             authed_conn_id: None,
             file_remove_log_control: FileRemoveLogControl::new(id),
             last_supported_encoding: None,
@@ -458,6 +538,9 @@ impl Connection {
             retina: Retina::default(),
             tx_from_authed,
             printer_data: Vec::new(),
+            file_drop_jobs: Vec::new(),
+            file_transfer_limiter: Default::default(),
+            archives: Vec::new(),
             tx_post_seq,
             terminal_service_id: "".to_owned(),
             terminal_persistent: false,
@@ -575,8 +658,25 @@ impl Connection {
                             conn.send(msg_out).await;
                             conn.chat_unanswered = false;
                         }
-                        ipc::Data::SwitchPermission{name, enabled} => {
+                        ipc::Data::SwitchPermission{mut name, mut enabled} => {
                             log::info!("Change permission {} -> {}", name, enabled);
+                            conn.audit("permission", json!({ "name": name, "enabled": enabled }));
+                            if &name == "view_only" {
+                                if !enabled {
+                                    log::warn!("Forced view-only can't be reverted");
+                                    continue;
+                                }
+                                conn.view_only_forced = true;
+                                name = "keyboard".to_owned();
+                                enabled = false;
+                            } else if &name == "keyboard" && enabled && conn.view_only_forced {
+                                log::warn!("Ignore enabling keyboard, view-only is forced");
+                                continue;
+                            }
+                            if enabled && !conn.permission_profile.allows(&name) {
+                                log::warn!("Ignore enabling {}, denied by the permission profile", name);
+                                continue;
+                            }
                             if &name == "keyboard" {
                                 conn.keyboard = enabled;
                                 conn.send_permission(Permission::Keyboard, enabled).await;
@@ -620,6 +720,12 @@ impl Connection {
                                         }
                                     }
                                 }
+                            } else if &name == "voice_call" {
+                                conn.voice_call = enabled;
+                                if !enabled && conn.voice_calling {
+                                    conn.close_voice_call().await;
+                                    conn.send(new_voice_call_request(false)).await;
+                                }
                             } else if &name == "file" {
                                 conn.file = enabled;
                                 conn.send_permission(Permission::File, enabled).await;
@@ -751,6 +857,9 @@ impl Connection {
                 },
                 _ = conn.file_timer.tick() => {
                     if !conn.read_jobs.is_empty() {
+                        if !conn.file_transfer_ready() {
+                            continue;
+                        }
                         conn.send_to_cm(ipc::Data::FileTransferLog(("transfer".to_string(), fs::serialize_transfer_jobs(&conn.read_jobs))));
                         match fs::handle_read_jobs(&mut conn.read_jobs, &mut conn.stream).await {
                             Ok(log) => {
@@ -764,6 +873,7 @@ impl Connection {
                             }
                         }
                     } else {
+                        conn.remove_archives();
                         conn.file_timer = crate::rustdesk_interval(time::interval_at(Instant::now() + SEC30, SEC30));
                     }
                 }
@@ -780,6 +890,7 @@ impl Connection {
                             video_service::notify_video_frame_fetched(vf.display as usize, id, Some(instant.into()));
                         }
                     }
+                    metrics::add_sent_bytes(value.compute_size() as _);
                     if let Err(err) = conn.stream.send(&value as &Message).await {
                         conn.on_close(&err.to_string(), false).await;
                         break;
@@ -841,6 +952,7 @@ impl Connection {
                     }
 
                     let msg: &Message = &msg;
+                    metrics::add_sent_bytes(msg.compute_size() as _);
                     if let Err(err) = conn.stream.send(msg).await {
                         conn.on_close(&err.to_string(), false).await;
                         break;
@@ -856,19 +968,30 @@ impl Connection {
                                 conn.send_remote_printing_disallowed().await;
                             }
                         }
+                        ipc::Data::Close => {
+                            conn.send_close_reason_no_retry("").await;
+                            conn.on_close("local control", true).await;
+                            break;
+                        }
+                        ipc::Data::ShowConnection => {
+                            conn.send_to_cm(ipc::Data::ShowConnection);
+                        }
                         _ => {}
                     }
                 }
                 _ = second_timer.tick() => {
                     #[cfg(windows)]
                     conn.portable_check();
-                    if let Some((instant, minute)) = conn.auto_disconnect_timer.as_ref() {
-                        if instant.elapsed().as_secs() > minute * 60 {
-                            conn.send_close_reason_no_retry("Connection failed due to inactivity").await;
+                    if let Some((left, reason)) = conn.time_to_disconnect() {
+                        if left.is_zero() {
+                            conn.send_close_reason_no_retry(reason).await;
                             conn.on_close("auto disconnect", true).await;
                             break;
                         }
+                        conn.warn_disconnect(left).await;
                     }
+                    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+                    conn.notify_input_control().await;
                     conn.file_remove_log_control.on_timer().drain(..).map(|x| conn.send_to_cm(x)).count();
                     #[cfg(feature = "hwcodec")]
                     conn.update_supported_encoding();
@@ -1049,7 +1172,13 @@ impl Connection {
             }
         }
         #[cfg(target_os = "linux")]
-        clear_remapped_keycode();
+        {
+            // Disabled devices stay disabled after the session otherwise.
+            if block_input_mode {
+                let _ = crate::platform::block_input(false);
+            }
+            clear_remapped_keycode();
+        }
         log::debug!("Input thread exited");
     }
 
@@ -1068,6 +1197,16 @@ impl Connection {
         if let Some(mut forward) = self.port_forward_socket.take() {
             log::info!("Running port forwarding loop");
             self.stream.set_raw();
+            if let PortForwardSocket::Wol(target) = &forward {
+                let reply = if crate::lan::wake_lan_peer(target) {
+                    crate::lan::WOL_REPLY_SENT
+                } else {
+                    crate::lan::WOL_REPLY_UNKNOWN
+                };
+                log::info!("Wake-on-LAN request for {}: {}", target, reply);
+                self.stream.send_bytes(Bytes::from(reply)).await?;
+                return Ok(());
+            }
             let mut hbbs_rx = crate::hbbs_http::sync::signal_receiver();
             loop {
                 tokio::select! {
@@ -1138,29 +1277,46 @@ impl Connection {
         }
     }
 
-    async fn check_whitelist(&mut self, addr: &SocketAddr) -> bool {
-        let whitelist: Vec<String> = Config::get_option("whitelist")
+    fn ip_list_option(name: &str) -> Vec<String> {
+        Config::get_option(name)
             .split(",")
+            .map(|x| x.trim())
             .filter(|x| !x.is_empty())
             .map(|x| x.to_owned())
-            .collect();
-        if !whitelist.is_empty()
-            && whitelist
-                .iter()
-                .filter(|x| x == &"0.0.0.0")
-                .next()
-                .is_none()
-            && whitelist
-                .iter()
-                .filter(|x| IpCidr::from_str(x).map_or(false, |y| y.contains(addr.ip())))
-                .next()
-                .is_none()
-        {
+            .collect()
+    }
+
+    // `addr` is the original address reported by the rendezvous server for relayed connections.
+    // An entry of the blacklist wins over the whitelist, so holes can be punched into an allowed range.
+    async fn check_whitelist(&mut self, addr: &SocketAddr) -> bool {
+        // A dual-stack listener reports IPv4 peers as mapped IPv6 addresses.
+        let ip = match addr.ip() {
+            std::net::IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(std::net::IpAddr::V4)
+                .unwrap_or(std::net::IpAddr::V6(v6)),
+            ip => ip,
+        };
+        let contains = |x: &String| IpCidr::from_str(x).map_or(false, |y| y.contains(ip));
+        let whitelist = Self::ip_list_option("whitelist");
+        let blacklist = Self::ip_list_option(OPTION_BLACKLIST);
+        let mut blocked = blacklist.iter().any(contains)
+            || (!whitelist.is_empty()
+                && !whitelist.iter().any(|x| x == "0.0.0.0")
+                && !whitelist.iter().any(contains));
+        if crate::is_lan_only() {
+            let mut subnets = Self::ip_list_option(OPTION_LAN_ONLY_SUBNETS);
+            if subnets.is_empty() {
+                subnets = PRIVATE_SUBNETS.iter().map(|x| x.to_string()).collect();
+            }
+            blocked |= !subnets.iter().any(contains);
+        }
+        if blocked {
             self.send_login_error("Your ip is blocked by the peer")
                 .await;
             Self::post_alarm_audit(
                 AlarmAuditType::IpWhitelist, //"ip whitelist",
-                json!({ "ip":addr.ip() }),
+                json!({ "ip":ip }),
             );
             return false;
         }
@@ -1204,7 +1360,17 @@ impl Connection {
         );
     }
 
+    fn audit(&self, event: &str, fields: Value) {
+        let mut fields = fields;
+        fields["conn_id"] = json!(self.inner.id);
+        fields["ip"] = json!(self.ip);
+        fields["peer_id"] = json!(self.lr.my_id);
+        fields["peer_name"] = json!(self.lr.my_name);
+        audit::emit(event, fields);
+    }
+
     fn post_conn_audit(&self, v: Value) {
+        self.audit("connection", v.clone());
         if self.server_audit_conn.is_empty() {
             return;
         }
@@ -1240,6 +1406,18 @@ impl Connection {
         files: Vec<(String, i64)>,
         info: Value,
     ) {
+        self.audit(
+            "file",
+            json!({
+                "direction": match r#type {
+                    FileAuditType::RemoteSend => "send",
+                    FileAuditType::RemoteReceive => "receive",
+                },
+                "path": path,
+                "files": files,
+                "info": info,
+            }),
+        );
         if self.server_audit_file.is_empty() {
             return;
         }
@@ -1373,14 +1551,12 @@ impl Connection {
             pi.hostname = DEVICE_NAME.lock().unwrap().clone();
             pi.platform = "Android".into();
         }
-        #[cfg(all(target_os = "macos", not(feature = "unix-file-copy-paste")))]
-        let platform_additions = serde_json::Map::new();
-        #[cfg(any(
-            target_os = "windows",
-            target_os = "linux",
-            all(target_os = "macos", feature = "unix-file-copy-paste")
-        ))]
+        #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
         let mut platform_additions = serde_json::Map::new();
+        #[cfg(any(target_os = "windows", target_os = "linux", target_os = "macos"))]
+        if Config::get_option(crate::OPTION_SHARE_SYSINFO) == "Y" {
+            platform_additions.insert("sysinfo".into(), crate::get_inventory());
+        }
         #[cfg(target_os = "linux")]
         {
             if crate::platform::current_is_wayland() {
@@ -1666,6 +1842,11 @@ impl Connection {
                 #[cfg(not(any(target_os = "android", target_os = "ios")))]
                 let _h = try_start_record_cursor_pos();
                 self.auto_disconnect_timer = Self::get_auto_disconenct_timer();
+                self.session_deadline = Config::get_option(OPTION_MAX_SESSION_DURATION)
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|minute| *minute > 0)
+                    .map(|minute| Instant::now() + Duration::from_secs(minute * 60));
                 s.try_add_primay_video_service();
                 s.add_connection(self.inner.clone(), &noperms);
             }
@@ -1720,10 +1901,66 @@ impl Connection {
         }
     }
 
+    // Messages that control the controlled side, rejected when view-only is forced.
+    fn is_input_message(msg: &Message) -> bool {
+        matches!(
+            msg.union,
+            Some(message::Union::MouseEvent(_))
+                | Some(message::Union::PointerDeviceEvent(_))
+                | Some(message::Union::KeyEvent(_))
+                | Some(message::Union::Clipboard(_))
+                | Some(message::Union::MultiClipboards(_))
+        )
+    }
+
     fn peer_keyboard_enabled(&self) -> bool {
         self.keyboard && !self.disable_keyboard
     }
 
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    fn holds_input(&self) -> bool {
+        Config::get_option(input_arbitration::OPTION_INPUT_ARBITRATION) != "Y"
+            || INPUT_ARBITER
+                .lock()
+                .unwrap()
+                .try_hold(self.inner.id(), Instant::now())
+    }
+
+    // Releases of what this connection pressed get through without holding the input.
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    fn may_simulate_mouse(&mut self, me: &MouseEvent) -> bool {
+        let buttons = me.mask >> 3;
+        match me.mask & 0x7 {
+            crate::input::MOUSE_TYPE_DOWN => {
+                let hold = self.holds_input();
+                if hold {
+                    self.pressed_input.press_button(buttons);
+                }
+                hold
+            }
+            crate::input::MOUSE_TYPE_UP => {
+                self.pressed_input.release_button(buttons) || self.holds_input()
+            }
+            _ => self.holds_input(),
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    fn may_simulate_key(&mut self, me: &KeyEvent) -> bool {
+        let key = (me.mode.value(), me.union.clone());
+        if me.press {
+            self.holds_input()
+        } else if me.down {
+            let hold = self.holds_input();
+            if hold {
+                self.pressed_input.press_key(key);
+            }
+            hold
+        } else {
+            self.pressed_input.release_key(&key) || self.holds_input()
+        }
+    }
+
     fn clipboard_enabled(&self) -> bool {
         self.clipboard && !self.disable_clipboard
     }
@@ -1744,6 +1981,58 @@ impl Connection {
         self.file && self.enable_file_transfer
     }
 
+    // Paces the read jobs, slowing them down further while the video of this session struggles.
+    fn file_transfer_ready(&mut self) -> bool {
+        let transferred = self.read_jobs.iter().map(|j| j.transferred()).sum();
+        let mut limit = transfer_limit::parse_limit(&Config::get_option(
+            transfer_limit::OPTION_FILE_TRANSFER_SPEED_LIMIT,
+        ));
+        if self.is_remote() && Config::get_option(OPTION_FILE_TRANSFER_YIELD_TO_VIDEO) == "Y" {
+            let qos = video_service::VIDEO_QOS.lock().unwrap();
+            let share = qos.network_health().file_transfer_share();
+            if share < 1.0 {
+                if limit == 0 {
+                    // Without a limit of its own, the transfer is measured against the video bitrate (kbps).
+                    limit = qos.bitrate() as u64 * 1000 / 8;
+                }
+                limit = (limit as f32 * share) as u64;
+            }
+        }
+        self.file_transfer_limiter.ready(transferred, limit)
+    }
+
+    // Packing stalls this connection, archives are only asked for by file transfer sessions.
+    async fn create_archive(&mut self, dir: &str, include_hidden: bool) -> ResultType<PathBuf> {
+        let dir = dir.to_owned();
+        let archive =
+            tokio::task::spawn_blocking(move || crate::file_archive::create(&dir, include_hidden))
+                .await??;
+        self.archives.push(archive.clone());
+        Ok(archive)
+    }
+
+    fn remove_archives(&mut self) {
+        for archive in self.archives.drain(..) {
+            std::fs::remove_file(&archive).ok();
+        }
+    }
+
+    // Files dropped onto the remote view are received without a file transfer session.
+    fn is_file_drop_action(&mut self, fa: &FileAction) -> bool {
+        if !(self.file && self.enable_file_transfer) {
+            return false;
+        }
+        match &fa.union {
+            Some(file_action::Union::Receive(r)) if crate::file_drop::is_drop_path(&r.path) => {
+                self.file_drop_jobs.push(r.id);
+                true
+            }
+            Some(file_action::Union::SendConfirm(r)) => self.file_drop_jobs.contains(&r.id),
+            Some(file_action::Union::Cancel(c)) => self.file_drop_jobs.contains(&c.id),
+            _ => false,
+        }
+    }
+
     #[cfg(feature = "unix-file-copy-paste")]
     fn can_sub_file_clipboard_service(&self) -> bool {
         self.clipboard_enabled()
@@ -1947,7 +2236,10 @@ impl Connection {
 
     async fn handle_login_request_without_validation(&mut self, lr: &LoginRequest) {
         self.lr = lr.clone();
-        self.peer_argb = crate::str2color(&format!("{}{}", &lr.my_id, &lr.my_platform), 0xff);
+        self.peer_argb = INPUT_ARBITER.lock().unwrap().cursor_color(
+            self.inner.id(),
+            crate::str2color(&format!("{}{}", &lr.my_id, &lr.my_platform), 0xff),
+        );
         if let Some(o) = lr.option.as_ref() {
             self.options_in_login = Some(o.clone());
         }
@@ -1965,6 +2257,20 @@ impl Connection {
             }
         }
         self.video_ack_required = lr.video_ack_required;
+        self.apply_permission_profile();
+    }
+
+    fn apply_permission_profile(&mut self) {
+        let profile = permission_profile::PermissionProfile::load(&self.lr.my_id);
+        if profile != Default::default() {
+            log::info!("Permission profile of {}: {:?}", self.lr.my_id, profile);
+        }
+        self.keyboard &= profile.keyboard;
+        self.clipboard &= profile.clipboard;
+        self.file &= profile.file;
+        self.audio &= profile.audio;
+        self.restart &= profile.restart;
+        self.permission_profile = profile;
     }
 
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -2019,7 +2325,9 @@ impl Connection {
             }
             match lr.union {
                 Some(login_request::Union::FileTransfer(ft)) => {
-                    if !Connection::permission(keys::OPTION_ENABLE_FILE_TRANSFER) {
+                    if !Connection::permission(keys::OPTION_ENABLE_FILE_TRANSFER)
+                        || !self.permission_profile.file
+                    {
                         self.send_login_error("No permission of file transfer")
                             .await;
                         sleep(1.).await;
@@ -2037,7 +2345,9 @@ impl Connection {
                     self.view_camera = true;
                 }
                 Some(login_request::Union::Terminal(terminal)) => {
-                    if !Connection::permission(keys::OPTION_ENABLE_TERMINAL) {
+                    if !Connection::permission(keys::OPTION_ENABLE_TERMINAL)
+                        || !self.permission_profile.terminal
+                    {
                         self.send_login_error("No permission of terminal").await;
                         sleep(1.).await;
                         return false;
@@ -2096,16 +2406,40 @@ impl Connection {
                         pf.port = 3389;
                         is_rdp = true;
                     }
+                    let mut udp = false;
+                    if let Some(host) = crate::udp_tunnel::strip_scheme(&pf.host) {
+                        pf.host = host.to_owned();
+                        udp = true;
+                    }
                     if pf.host.is_empty() {
                         pf.host = "localhost".to_owned();
                     }
                     let mut addr = format!("{}:{}", pf.host, pf.port);
-                    self.port_forward_address = addr.clone();
-                    match timeout(3000, TcpStream::connect(&addr)).await {
-                        Ok(Ok(sock)) => {
-                            self.port_forward_socket = Some(Framed::new(sock, BytesCodec::new()));
+                    let socket = if let Some(target) = pf.host.strip_prefix(crate::lan::WOL_SCHEME)
+                    {
+                        self.port_forward_address = pf.host.clone();
+                        Some(PortForwardSocket::Wol(target.to_owned()))
+                    } else if udp {
+                        self.port_forward_address =
+                            format!("{}{}", crate::udp_tunnel::UDP_SCHEME, addr);
+                        match timeout(3000, crate::udp_tunnel::UdpForward::connect(&addr)).await {
+                            Ok(Ok(forward)) => Some(PortForwardSocket::Udp(forward)),
+                            _ => None,
+                        }
+                    } else {
+                        self.port_forward_address = addr.clone();
+                        match timeout(3000, TcpStream::connect(&addr)).await {
+                            Ok(Ok(sock)) => {
+                                Some(PortForwardSocket::Tcp(Framed::new(sock, BytesCodec::new())))
+                            }
+                            _ => None,
                         }
-                        _ => {
+                    };
+                    match socket {
+                        Some(socket) => {
+                            self.port_forward_socket = Some(socket);
+                        }
+                        None => {
                             if is_rdp {
                                 addr = "RDP".to_owned();
                             }
@@ -2236,31 +2570,29 @@ impl Connection {
                 return true;
             }
             if let Some(totp) = self.require_2fa.as_ref() {
-                if let Ok(res) = totp.check_current(&tfa.code) {
-                    if res {
-                        self.update_failure(failure, true, 1);
-                        self.require_2fa.take();
-                        raii::AuthedConnID::set_session_2fa(self.session_key());
-                        self.send_logon_response().await;
-                        self.try_start_cm(
-                            self.lr.my_id.to_owned(),
-                            self.lr.my_name.to_owned(),
-                            self.authorized,
-                        );
-                        if !tfa.hwid.is_empty() && Self::enable_trusted_devices() {
-                            Config::add_trusted_device(TrustedDevice {
-                                hwid: tfa.hwid,
-                                time: hbb_common::get_time(),
-                                id: self.lr.my_id.clone(),
-                                name: self.lr.my_name.clone(),
-                                platform: self.lr.my_platform.clone(),
-                            });
-                        }
-                    } else {
-                        self.update_failure(failure, false, 1);
-                        self.send_login_error(crate::client::LOGIN_MSG_2FA_WRONG)
-                            .await;
+                if crate::auth_2fa::check_login_code(totp, &tfa.code) {
+                    self.update_failure(failure, true, 1);
+                    self.require_2fa.take();
+                    raii::AuthedConnID::set_session_2fa(self.session_key());
+                    self.send_logon_response().await;
+                    self.try_start_cm(
+                        self.lr.my_id.to_owned(),
+                        self.lr.my_name.to_owned(),
+                        self.authorized,
+                    );
+                    if !tfa.hwid.is_empty() && Self::enable_trusted_devices() {
+                        Config::add_trusted_device(TrustedDevice {
+                            hwid: tfa.hwid,
+                            time: hbb_common::get_time(),
+                            id: self.lr.my_id.clone(),
+                            name: self.lr.my_name.clone(),
+                            platform: self.lr.my_platform.clone(),
+                        });
                     }
+                } else {
+                    self.update_failure(failure, false, 1);
+                    self.send_login_error(crate::client::LOGIN_MSG_2FA_WRONG)
+                        .await;
                 }
             }
         } else if let Some(message::Union::TestDelay(t)) = msg.union {
@@ -2308,6 +2640,18 @@ impl Connection {
             if self.port_forward_socket.is_some() {
                 return true;
             }
+            if self.view_only_forced && Self::is_input_message(&msg) {
+                return true;
+            }
+            if !self.permission_profile.mouse
+                && matches!(
+                    msg.union,
+                    Some(message::Union::MouseEvent(_))
+                        | Some(message::Union::PointerDeviceEvent(_))
+                )
+            {
+                return true;
+            }
             match msg.union {
                 #[allow(unused_mut)]
                 Some(message::Union::MouseEvent(mut me)) => {
@@ -2318,8 +2662,9 @@ impl Connection {
                     if let Err(e) = call_main_service_pointer_input("mouse", me.mask, me.x, me.y) {
                         log::debug!("call_main_service_pointer_input fail:{}", e);
                     }
+                    // Without control the mouse only moves the cursor shown to the others.
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    if self.peer_keyboard_enabled() {
+                    if self.peer_keyboard_enabled() && self.may_simulate_mouse(&me) {
                         if is_left_up(&me) {
                             CLICK_TIME.store(get_time(), Ordering::SeqCst);
                         } else {
@@ -2382,7 +2727,7 @@ impl Connection {
                         log::debug!("call_main_service_pointer_input fail:{}", e);
                     }
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
-                    if self.peer_keyboard_enabled() {
+                    if self.peer_keyboard_enabled() && self.holds_input() {
                         MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
                         self.input_pointer(pde, self.inner.id());
                     }
@@ -2450,7 +2795,7 @@ impl Connection {
                     if self.is_authed_view_camera_conn() {
                         return true;
                     }
-                    if self.peer_keyboard_enabled() {
+                    if self.peer_keyboard_enabled() && self.may_simulate_key(&me) {
                         if is_enter(&me) {
                             CLICK_TIME.store(get_time(), Ordering::SeqCst);
                         }
@@ -2508,6 +2853,7 @@ impl Connection {
                 }
                 Some(message::Union::Clipboard(cb)) => {
                     if self.clipboard {
+                        self.audit("clipboard", json!({ "format": cb.format.value() }));
                         #[cfg(not(any(target_os = "android", target_os = "ios")))]
                         update_clipboard(vec![cb], ClipboardSide::Host);
                         // ios as the controlled side is actually not supported for now.
@@ -2535,6 +2881,9 @@ impl Connection {
                     }
                 }
                 Some(message::Union::MultiClipboards(_mcb)) => {
+                    if self.clipboard {
+                        self.audit("clipboard", json!({ "count": _mcb.clipboards.len() }));
+                    }
                     #[cfg(not(any(target_os = "android", target_os = "ios")))]
                     if self.clipboard {
                         update_clipboard(_mcb.clipboards, ClipboardSide::Host);
@@ -2625,6 +2974,10 @@ impl Connection {
                             }
                         }
                     }
+                    if !handle_fa {
+                        handle_fa = self.is_file_drop_action(&fa);
+                    }
+                    handle_fa &= self.permission_profile.file;
                     if handle_fa {
                         if self.delayed_read_dir.is_some() {
                             if let Some(file_action::Union::ReadDir(rd)) = fa.union {
@@ -2686,8 +3039,22 @@ impl Connection {
                                 let data_source;
                                 match r#type {
                                     JobType::Generic => {
-                                        data_source =
-                                            fs::DataSource::FilePath(PathBuf::from(&path));
+                                        let path = match crate::file_archive::requested_dir(&path) {
+                                            Some(dir) => {
+                                                match self
+                                                    .create_archive(dir, s.include_hidden)
+                                                    .await
+                                                {
+                                                    Ok(archive) => archive,
+                                                    Err(err) => {
+                                                        self.send(fs::new_error(id, err, 0)).await;
+                                                        return true;
+                                                    }
+                                                }
+                                            }
+                                            None => PathBuf::from(&path),
+                                        };
+                                        data_source = fs::DataSource::FilePath(path);
                                     }
                                     JobType::Printer => {
                                         if let Some((_, _, data)) = self
@@ -3043,7 +3410,11 @@ impl Connection {
                     }
                 }
                 Some(message::Union::VoiceCallRequest(request)) => {
-                    if request.is_connect {
+                    if request.is_connect && !self.voice_call {
+                        log::info!("Voice call rejected, not permitted");
+                        self.send(new_voice_call_response(request.req_timestamp, false))
+                            .await;
+                    } else if request.is_connect {
                         self.voice_call_request_timestamp = Some(
                             NonZeroI64::new(request.req_timestamp)
                                 .unwrap_or(NonZeroI64::new(get_time()).unwrap()),
@@ -3215,6 +3586,28 @@ impl Connection {
             }
             cur
         }
+        self.audit(
+            "login",
+            json!({
+                "factor": if i == 0 { "password" } else { "2fa" },
+                "success": remove,
+            }),
+        );
+        if remove {
+            LOGIN_BACKOFF.lock().unwrap().on_success(&self.ip);
+        } else {
+            metrics::on_auth_failure();
+            let now = std::time::Instant::now();
+            let delay = LOGIN_BACKOFF.lock().unwrap().on_failure(&self.ip, now);
+            if !delay.is_zero() {
+                log::warn!(
+                    "Login of {} from {} failed again, locked out for {:?}",
+                    self.lr.my_id,
+                    self.ip,
+                    delay
+                );
+            }
+        }
         let map_mutex = &LOGIN_FAILURES[i];
         if remove {
             if failure.0 != 0 {
@@ -3327,12 +3720,26 @@ impl Connection {
                 }),
             );
             false
+        } else if let Some(remaining) = self.login_backoff_remaining() {
+            self.send_login_error(format!(
+                "Please try {} seconds later",
+                remaining.as_secs().max(1)
+            ))
+            .await;
+            false
         } else {
             true
         };
         ((failure, time), res)
     }
 
+    fn login_backoff_remaining(&self) -> Option<Duration> {
+        let now = std::time::Instant::now();
+        // Keyed by the source ip only, the peer id is self-reported and anybody could lock
+        // out somebody else's id with it.
+        LOGIN_BACKOFF.lock().unwrap().remaining(&self.ip, now)
+    }
+
     fn refresh_video_display(&self, display: Option<usize>) {
         video_service::refresh();
         self.server.upgrade().map(|s| {
@@ -3780,9 +4187,17 @@ impl Connection {
                 match q {
                     BoolOption::Yes => {
                         self.tx_input.send(MessageInput::BlockOn).ok();
+                        #[cfg(target_os = "linux")]
+                        {
+                            self.input_blocked = true;
+                        }
                     }
                     BoolOption::No => {
                         self.tx_input.send(MessageInput::BlockOff).ok();
+                        #[cfg(target_os = "linux")]
+                        {
+                            self.input_blocked = false;
+                        }
                     }
                     _ => {}
                 }
@@ -3982,6 +4397,7 @@ impl Connection {
         // We can add a (Vec<conn_id>, input device) to avoid this.
         // But it's not necessary now and we have to consider two audio services(client, server).
         crate::audio_service::set_voice_call_input_device(None, true);
+        INPUT_ARBITER.lock().unwrap().release(self.inner.id());
         log::info!("#{} Connection closed: {}", self.inner.id(), reason);
         if lock && self.lock_after_session_end && self.keyboard {
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -4035,7 +4451,7 @@ impl Connection {
     }
 
     pub fn alive_conns() -> Vec<i32> {
-        ALIVE_CONNS.lock().unwrap().clone()
+        ALIVE_CONNS.lock().unwrap().iter().map(|c| c.0).collect()
     }
 
     #[cfg(windows)]
@@ -4114,6 +4530,80 @@ impl Connection {
             .map(|t| t.0 = Instant::now());
     }
 
+    // The time left before the session is closed by a timeout, and the reason.
+    fn time_to_disconnect(&self) -> Option<(Duration, &'static str)> {
+        let idle = self.auto_disconnect_timer.map(|(instant, minute)| {
+            (
+                Duration::from_secs(minute * 60).saturating_sub(instant.elapsed()),
+                "Connection failed due to inactivity",
+            )
+        });
+        let max = self.session_deadline.map(|deadline| {
+            (
+                deadline.saturating_duration_since(Instant::now()),
+                "Maximum session duration reached",
+            )
+        });
+        idle.into_iter().chain(max).min_by_key(|(left, _)| *left)
+    }
+
+    async fn warn_disconnect(&mut self, left: Duration) {
+        // Input resets the idle timeout, warn again the next time.
+        let warn = left <= DISCONNECT_WARNING;
+        if warn && !self.disconnect_warned {
+            let mut msg_out = Message::new();
+            msg_out.set_message_box(MessageBox {
+                msgtype: "nook-nocancel-hasclose".to_owned(),
+                title: "Session timeout".to_owned(),
+                text: format!("The session will be closed in {} seconds", left.as_secs()),
+                link: "".to_owned(),
+                ..Default::default()
+            });
+            self.send(msg_out).await;
+        }
+        self.disconnect_warned = warn;
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    async fn notify_input_control(&mut self) {
+        use input_arbitration::InputControl;
+
+        if !self.authorized
+            || Config::get_option(input_arbitration::OPTION_INPUT_ARBITRATION) != "Y"
+        {
+            return;
+        }
+        let state = INPUT_ARBITER
+            .lock()
+            .unwrap()
+            .state(self.inner.id(), Instant::now());
+        let text = match (self.input_control, state) {
+            (last, state) if last == state => None,
+            (_, InputControl::Requested) => {
+                Some("Another controller is using the input, you get control once it is idle")
+            }
+            (InputControl::Requested, InputControl::Held { .. }) => {
+                Some("You have control of the input now")
+            }
+            (_, InputControl::Held { requested: true }) => Some(
+                "Another controller requested control of the input, it gets control once you are idle for 3 seconds",
+            ),
+            _ => None,
+        };
+        self.input_control = state;
+        if let Some(text) = text {
+            let mut msg_out = Message::new();
+            msg_out.set_message_box(MessageBox {
+                msgtype: "nook-nocancel-hasclose".to_owned(),
+                title: "Input control".to_owned(),
+                text: text.to_owned(),
+                link: "".to_owned(),
+                ..Default::default()
+            });
+            self.send(msg_out).await;
+        }
+    }
+
     #[cfg(feature = "hwcodec")]
     fn update_supported_encoding(&mut self) {
         let Some(last) = &self.last_supported_encoding else {
@@ -4665,6 +5155,39 @@ pub fn on_printer_data(data: Vec<u8>) {
         });
 }
 
+/// The id, peer id and type of the authorized connections.
+pub fn authed_conns() -> Vec<(i32, String, AuthConnType)> {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|c| (c.conn_id, c.session_key.peer_id.clone(), c.conn_type))
+        .collect()
+}
+
+/// Shows the connection `conn_id` in the connection manager, authorized or still waiting for
+/// approval, false if there is none.
+pub fn show_conn(conn_id: i32) -> bool {
+    ALIVE_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|c| c.0 == conn_id)
+        .map(|c| c.1.send(Data::ShowConnection).is_ok())
+        .unwrap_or(false)
+}
+
+/// Closes the authorized connection `conn_id`, false if there is none.
+pub fn close_authed_conn(conn_id: i32) -> bool {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|c| c.conn_id == conn_id)
+        .map(|c| c.sender.send(Data::Close).is_ok())
+        .unwrap_or(false)
+}
+
 #[cfg(windows)]
 pub struct PortableState {
     pub last_uac: bool,
@@ -4690,10 +5213,20 @@ impl Drop for Connection {
         #[cfg(not(any(target_os = "android", target_os = "ios")))]
         self.release_pressed_modifiers();
 
+        // The read jobs keep the archives open.
+        self.read_jobs.clear();
+        self.remove_archives();
+
         if let Some(s) = self.terminal_generic_service.as_ref() {
             s.join();
         }
 
+        // The input thread lifts it too, but only when it gets to exit.
+        #[cfg(target_os = "linux")]
+        if self.input_blocked {
+            let _ = crate::platform::block_input(false);
+        }
+
         #[cfg(target_os = "windows")]
         if let Some(TerminalUserToken::CurrentLogonUser(token)) = self.terminal_user_token.take() {
             if token != 0 {
@@ -4778,6 +5311,10 @@ impl Retina {
             // x and y are always 0, +1 or -1
             return;
         }
+        if evt_type == crate::input::MOUSE_TYPE_MOVE_RELATIVE {
+            // x and y are a movement, not a position on the display
+            return;
+        }
         let Some(d) = self.displays.get(current) else {
             return;
         };
@@ -4827,8 +5364,8 @@ mod raii {
     pub struct ConnectionID(i32);
 
     impl ConnectionID {
-        pub fn new(id: i32) -> Self {
-            ALIVE_CONNS.lock().unwrap().push(id);
+        pub fn new(id: i32, sender: mpsc::UnboundedSender<Data>) -> Self {
+            ALIVE_CONNS.lock().unwrap().push((id, sender));
             Self(id)
         }
     }
@@ -4836,7 +5373,7 @@ mod raii {
     impl Drop for ConnectionID {
         fn drop(&mut self) {
             let mut active_conns_lock = ALIVE_CONNS.lock().unwrap();
-            active_conns_lock.retain(|&c| c != self.0);
+            active_conns_lock.retain(|c| c.0 != self.0);
         }
     }
 
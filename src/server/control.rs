@@ -0,0 +1,183 @@
+//! Local control api for other software on this machine.
+//!
+//! Enabled by the `enable-control-api` option, read once at startup. It listens on the ipc
+//! path with the `_control` postfix, a unix socket or a named pipe. Requests and responses
+//! are JSON-RPC 2.0 objects, one per line:
+//!
+//! - `sessions`: the authorized connections.
+//! - `disconnect` with `{"conn_id": 1}`: closes a connection.
+//! - `show-dialog` with `{"conn_id": 1}`: brings up the connection in the connection manager,
+//!   the incoming connection dialog if it waits for approval. Connections that have not logged
+//!   in yet are not shown.
+//! - `get-option` with `{"key": "enable-keyboard"}` and `set-option` with
+//!   `{"key": "enable-keyboard", "value": "N"}`: reads or toggles a permission, only the
+//!   options in `PERMISSIONS` are reachable.
+//!
+//! Any local user can open the socket, like the other ipc paths.
+
+use super::{authed_conns, close_authed_conn, show_conn};
+use hbb_common::{
+    config::Config,
+    futures::StreamExt as _,
+    log,
+    tokio::{
+        self,
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    },
+};
+use serde_json::{json, Value};
+
+const OPTION_CONTROL_API: &str = "enable-control-api";
+const POSTFIX: &str = "_control";
+// The permissions of "Settings -> Security", other options stay out of reach.
+const PERMISSIONS: &[&str] = &[
+    "enable-keyboard",
+    "enable-remote-printer",
+    "enable-clipboard",
+    "enable-file-transfer",
+    "enable-audio",
+    "enable-voice-call",
+    "enable-camera",
+    "enable-terminal",
+    "enable-tunnel",
+    "enable-remote-restart",
+    "enable-record-session",
+    "enable-block-input",
+];
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+pub fn start() {
+    if Config::get_option(OPTION_CONTROL_API) != "Y" {
+        return;
+    }
+    std::thread::spawn(serve);
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn serve() {
+    let mut incoming = match crate::ipc::new_listener(POSTFIX).await {
+        Ok(incoming) => incoming,
+        Err(err) => {
+            log::error!("Failed to start control api: {}", err);
+            return;
+        }
+    };
+    while let Some(result) = incoming.next().await {
+        match result {
+            Ok(stream) => {
+                tokio::spawn(async move {
+                    let (reader, mut writer) = tokio::io::split(stream);
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        // Setting an option goes through the blocking ipc client.
+                        let Ok(response) = tokio::task::spawn_blocking(move || handle(&line)).await
+                        else {
+                            break;
+                        };
+                        let response = response.to_string() + "\n";
+                        if writer.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            Err(err) => {
+                log::error!("Couldn't get control api client: {:?}", err);
+            }
+        }
+    }
+}
+
+fn handle(line: &str) -> Value {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(err) => return error(Value::Null, PARSE_ERROR, &err.to_string()),
+    };
+    let id = request["id"].clone();
+    let params = &request["params"];
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "sessions" => Ok(authed_conns()
+            .into_iter()
+            .map(|(conn_id, peer_id, conn_type)| {
+                json!({
+                    "conn_id": conn_id,
+                    "peer_id": peer_id,
+                    "type": format!("{:?}", conn_type),
+                })
+            })
+            .collect()),
+        "disconnect" => match params["conn_id"].as_i64() {
+            Some(conn_id) => Ok(json!(close_authed_conn(conn_id as _))),
+            None => Err((INVALID_PARAMS, "conn_id is required")),
+        },
+        "show-dialog" => match params["conn_id"].as_i64() {
+            Some(conn_id) => Ok(json!(show_conn(conn_id as _))),
+            None => Err((INVALID_PARAMS, "conn_id is required")),
+        },
+        "get-option" => permission_key(params).map(|key| json!(Config::get_option(key))),
+        "set-option" => permission_key(params).and_then(|key| {
+            let value = params["value"]
+                .as_str()
+                .ok_or((INVALID_PARAMS, "value is required"))?;
+            log::info!("Control api sets {} to {}", key, value);
+            crate::ipc::set_option(key, value);
+            Ok(Value::Null)
+        }),
+        _ => Err((METHOD_NOT_FOUND, "method not found")),
+    };
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => error(id, code, message),
+    }
+}
+
+fn permission_key(params: &Value) -> Result<&str, (i32, &'static str)> {
+    match params["key"].as_str() {
+        Some(key) if PERMISSIONS.contains(&key) => Ok(key),
+        Some(_) => Err((INVALID_PARAMS, "only permissions are reachable")),
+        None => Err((INVALID_PARAMS, "key is required")),
+    }
+}
+
+fn error(id: Value, code: i32, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_errors() {
+        let code = |line: &str| handle(line)["error"]["code"].as_i64();
+        assert_eq!(code("{"), Some(PARSE_ERROR as _));
+        assert_eq!(
+            code(r#"{"id": 1, "method": "reboot"}"#),
+            Some(METHOD_NOT_FOUND as _)
+        );
+        assert_eq!(
+            code(r#"{"id": 1, "method": "disconnect"}"#),
+            Some(INVALID_PARAMS as _)
+        );
+        assert_eq!(
+            code(r#"{"id": 1, "method": "show-dialog"}"#),
+            Some(INVALID_PARAMS as _)
+        );
+        let res = handle(r#"{"id": 2, "method": "set-option", "params": {"key": "password"}}"#);
+        assert_eq!(res["id"], 2);
+        assert_eq!(res["error"]["code"], INVALID_PARAMS);
+        assert_eq!(
+            code(
+                r#"{"method": "set-option", "params": {"key": "enable-control-api", "value": ""}}"#
+            ),
+            Some(INVALID_PARAMS as _)
+        );
+        assert_eq!(
+            code(r#"{"method": "get-option", "params": {"key": "enable-lan-discovery"}}"#),
+            Some(INVALID_PARAMS as _)
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub const OPTION_INPUT_ARBITRATION: &str = "input-arbitration";
+// The holder keeps control while it is using it, the others get it once it is idle this long.
+const IDLE_RELEASE: Duration = Duration::from_secs(3);
+// ARGB, handed out in order so the cursors of the controllers are told apart at a glance.
+const CURSOR_COLORS: [u32; 8] = [
+    0xffe53935, 0xff1e88e5, 0xff43a047, 0xfffb8c00, 0xff8e24aa, 0xff00acc1, 0xfff06292, 0xff6d4c41,
+];
+
+/// What a controller is told about the input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InputControl {
+    #[default]
+    Free,
+    Held {
+        requested: bool,
+    },
+    Requested,
+}
+
+/// Which controller's input is simulated when several are connected.
+///
+/// Input from the others while the holder is using it requests control. Once the holder is idle,
+/// control is granted to the requests in order. The others still show their cursors, so they can
+/// point at things while they wait.
+#[derive(Default)]
+pub struct InputArbiter {
+    holder: Option<(i32, Instant)>,
+    requests: Vec<i32>,
+    cursor_colors: HashMap<i32, u32>,
+}
+
+impl InputArbiter {
+    /// Whether the input of `conn` may be simulated, taking control if it is free.
+    pub fn try_hold(&mut self, conn: i32, now: Instant) -> bool {
+        self.grant_request(now);
+        match self.holder {
+            Some((id, last))
+                if id != conn && now.saturating_duration_since(last) < IDLE_RELEASE =>
+            {
+                if !self.requests.contains(&conn) {
+                    self.requests.push(conn);
+                }
+                false
+            }
+            _ => {
+                self.holder = Some((conn, now));
+                true
+            }
+        }
+    }
+
+    /// The state of `conn`, granting control to the first request if the holder is idle.
+    pub fn state(&mut self, conn: i32, now: Instant) -> InputControl {
+        self.grant_request(now);
+        match self.holder {
+            Some((id, _)) if id == conn => InputControl::Held {
+                requested: !self.requests.is_empty(),
+            },
+            _ if self.requests.contains(&conn) => InputControl::Requested,
+            _ => InputControl::Free,
+        }
+    }
+
+    fn grant_request(&mut self, now: Instant) {
+        let idle = match self.holder {
+            Some((_, last)) => now.saturating_duration_since(last) >= IDLE_RELEASE,
+            None => true,
+        };
+        if idle && !self.requests.is_empty() {
+            self.holder = Some((self.requests.remove(0), now));
+        }
+    }
+
+    /// The cursor color of `conn`, the first one of `CURSOR_COLORS` the others do not use.
+    ///
+    /// `color` is kept once they are all taken.
+    pub fn cursor_color(&mut self, conn: i32, color: u32) -> u32 {
+        let taken = |c: &u32| {
+            self.cursor_colors
+                .iter()
+                .any(|(id, used)| *id != conn && used == c)
+        };
+        let color = CURSOR_COLORS
+            .iter()
+            .copied()
+            .find(|c| !taken(c))
+            .unwrap_or(color);
+        self.cursor_colors.insert(conn, color);
+        color
+    }
+
+    pub fn release(&mut self, conn: i32) {
+        if matches!(self.holder, Some((id, _)) if id == conn) {
+            self.holder = None;
+        }
+        self.requests.retain(|id| *id != conn);
+        self.cursor_colors.remove(&conn);
+    }
+}
+
+/// The keys and buttons a controller pressed.
+///
+/// Their releases are simulated even once another controller holds the input, or they would stay
+/// down on the controlled side.
+pub struct PressedInput<K> {
+    buttons: i32,
+    keys: Vec<K>,
+}
+
+impl<K> Default for PressedInput<K> {
+    fn default() -> Self {
+        Self {
+            buttons: 0,
+            keys: vec![],
+        }
+    }
+}
+
+impl<K: PartialEq> PressedInput<K> {
+    pub fn press_button(&mut self, buttons: i32) {
+        self.buttons |= buttons;
+    }
+
+    /// Forgets `buttons`, returns whether any of them was pressed.
+    pub fn release_button(&mut self, buttons: i32) -> bool {
+        let pressed = self.buttons & buttons != 0;
+        self.buttons &= !buttons;
+        pressed
+    }
+
+    pub fn press_key(&mut self, key: K) {
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+    }
+
+    /// Forgets `key`, returns whether it was pressed.
+    pub fn release_key(&mut self, key: &K) -> bool {
+        let len = self.keys.len();
+        self.keys.retain(|k| k != key);
+        self.keys.len() != len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_arbiter() {
+        let mut arbiter = InputArbiter::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert!(arbiter.try_hold(1, at(0)));
+        assert!(!arbiter.try_hold(2, at(1000)));
+        assert!(arbiter.try_hold(1, at(2000)));
+        assert!(!arbiter.try_hold(2, at(4000)));
+        assert!(arbiter.try_hold(2, at(5000)));
+        assert!(!arbiter.try_hold(1, at(5000)));
+        arbiter.release(1);
+        assert!(!arbiter.try_hold(1, at(5000)));
+        arbiter.release(2);
+        assert!(arbiter.try_hold(1, at(5000)));
+    }
+
+    #[test]
+    fn test_input_requests_granted_in_order() {
+        let mut arbiter = InputArbiter::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert!(arbiter.try_hold(1, at(0)));
+        assert!(!arbiter.try_hold(2, at(100)));
+        assert!(!arbiter.try_hold(3, at(200)));
+        assert_eq!(
+            arbiter.state(1, at(200)),
+            InputControl::Held { requested: true }
+        );
+        assert_eq!(arbiter.state(2, at(200)), InputControl::Requested);
+        // The first request gets control, not the first input after the holder is idle.
+        assert!(!arbiter.try_hold(3, at(3000)));
+        assert_eq!(
+            arbiter.state(2, at(3000)),
+            InputControl::Held { requested: true }
+        );
+        assert_eq!(arbiter.state(1, at(3000)), InputControl::Free);
+        arbiter.release(2);
+        assert_eq!(
+            arbiter.state(3, at(3000)),
+            InputControl::Held { requested: false }
+        );
+    }
+
+    #[test]
+    fn test_cursor_colors() {
+        let mut arbiter = InputArbiter::default();
+        let first = arbiter.cursor_color(1, 0);
+        let second = arbiter.cursor_color(2, 0);
+        assert_ne!(first, second);
+        assert_eq!(arbiter.cursor_color(1, 0), first);
+        arbiter.release(1);
+        assert_eq!(arbiter.cursor_color(3, 0), first);
+        for conn in 4..4 + CURSOR_COLORS.len() as i32 {
+            arbiter.cursor_color(conn, 0);
+        }
+        assert_eq!(arbiter.cursor_color(100, 0x7f123456), 0x7f123456);
+    }
+
+    #[test]
+    fn test_pressed_input() {
+        let mut pressed = PressedInput::default();
+        pressed.press_button(0x01);
+        assert!(!pressed.release_button(0x02));
+        assert!(pressed.release_button(0x01));
+        assert!(!pressed.release_button(0x01));
+        pressed.press_key('a');
+        pressed.press_key('a');
+        assert!(!pressed.release_key(&'b'));
+        assert!(pressed.release_key(&'a'));
+        assert!(!pressed.release_key(&'a'));
+    }
+}
@@ -1011,7 +1011,8 @@ pub fn handle_mouse_(
     }
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
     if _show_cursor {
-        handle_mouse_show_cursor_(evt, conn, _username, _argb);
+        // Dragging draws marks when it does not control the mouse.
+        handle_mouse_show_cursor_(evt, conn, _username, _argb, !simulate);
     }
 }
 
@@ -1087,6 +1088,12 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
             }
         }
         
+        MOUSE_TYPE_MOVE_RELATIVE => {
+            // Not recorded as a peer input position, so the cursor position is sent back to the
+            // peer, which has no position of its own in this mode.
+            en.mouse_move_relative(evt.x, evt.y);
+        }
+
         MOUSE_TYPE_DOWN => {
             // 快速鼠标按钮按下处理
             match buttons {
@@ -1198,10 +1205,42 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
     }
 }
 
+// Shift drags an arrow and control a circle, the same as on the controlling side.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn ink_shape(evt: &MouseEvent) -> whiteboard::InkShape {
+    let has = |key: ControlKey| evt.modifiers.iter().any(|m| m.enum_value() == Ok(key));
+    if has(ControlKey::Shift) || has(ControlKey::RShift) {
+        whiteboard::InkShape::Arrow
+    } else if has(ControlKey::Control) || has(ControlKey::RControl) {
+        whiteboard::InkShape::Circle
+    } else {
+        whiteboard::InkShape::Free
+    }
+}
+
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
-pub fn handle_mouse_show_cursor_(evt: &MouseEvent, conn: i32, username: String, argb: u32) {
+pub fn handle_mouse_show_cursor_(
+    evt: &MouseEvent,
+    conn: i32,
+    username: String,
+    argb: u32,
+    ink: bool,
+) {
     let buttons = evt.mask >> 3;
     let evt_type = evt.mask & 0x7;
+    let drawing = evt_type == MOUSE_TYPE_DOWN || evt_type == MOUSE_TYPE_MOVE;
+    if ink && drawing && buttons & MOUSE_BUTTON_LEFT != 0 {
+        whiteboard::update_whiteboard(
+            whiteboard::get_key_cursor(conn),
+            whiteboard::CustomEvent::Ink(whiteboard::Ink {
+                x: evt.x as _,
+                y: evt.y as _,
+                argb,
+                start: evt_type == MOUSE_TYPE_DOWN,
+                shape: ink_shape(evt),
+            }),
+        );
+    }
     match evt_type {
         MOUSE_TYPE_MOVE => {
             whiteboard::update_whiteboard(
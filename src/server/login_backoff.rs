@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// Failures allowed before any delay, a typo or two should not lock anybody out.
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY: Duration = Duration::from_secs(2);
+const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+// A key without failures for this long starts over.
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
+
+struct Entry {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Instant,
+}
+
+/// Escalating lockouts after repeated login failures.
+///
+/// Keyed by source ip, each failure doubles the lockout of its ip once the free attempts are
+/// used up.
+#[derive(Default)]
+pub struct LoginBackoff {
+    entries: HashMap<String, Entry>,
+}
+
+impl LoginBackoff {
+    /// Time left before `key` may try again.
+    pub fn remaining(&self, key: &str, now: Instant) -> Option<Duration> {
+        let entry = self.entries.get(key)?;
+        if now.duration_since(entry.last_failure) >= FORGET_AFTER {
+            return None;
+        }
+        entry
+            .locked_until
+            .checked_duration_since(now)
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Records a failure of `key` and returns the lockout it causes.
+    pub fn on_failure(&mut self, key: &str, now: Instant) -> Duration {
+        self.entries
+            .retain(|_, e| now.duration_since(e.last_failure) < FORGET_AFTER);
+        let entry = self.entries.entry(key.to_owned()).or_insert(Entry {
+            failures: 0,
+            last_failure: now,
+            locked_until: now,
+        });
+        entry.failures += 1;
+        entry.last_failure = now;
+        let delay = delay(entry.failures);
+        entry.locked_until = now + delay;
+        delay
+    }
+
+    pub fn on_success(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+}
+
+fn delay(failures: u32) -> Duration {
+    if failures <= FREE_ATTEMPTS {
+        return Duration::ZERO;
+    }
+    let exp = (failures - FREE_ATTEMPTS - 1).min(16);
+    BASE_DELAY.saturating_mul(1 << exp).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_escalates_and_resets() {
+        let mut backoff = LoginBackoff::default();
+        let now = Instant::now();
+        for _ in 0..FREE_ATTEMPTS {
+            assert_eq!(backoff.on_failure("1.2.3.4", now), Duration::ZERO);
+        }
+        assert!(backoff.remaining("1.2.3.4", now).is_none());
+        assert_eq!(backoff.on_failure("1.2.3.4", now), BASE_DELAY);
+        assert_eq!(backoff.on_failure("1.2.3.4", now), BASE_DELAY * 2);
+        assert_eq!(backoff.on_failure("1.2.3.4", now), BASE_DELAY * 4);
+        assert_eq!(backoff.remaining("1.2.3.4", now), Some(BASE_DELAY * 4));
+        assert!(backoff.remaining("5.6.7.8", now).is_none());
+        for _ in 0..32 {
+            backoff.on_failure("1.2.3.4", now);
+        }
+        assert_eq!(backoff.remaining("1.2.3.4", now), Some(MAX_DELAY));
+        assert!(backoff.remaining("1.2.3.4", now + MAX_DELAY).is_none());
+
+        let later = now + FORGET_AFTER;
+        assert!(backoff.remaining("1.2.3.4", later).is_none());
+        assert_eq!(backoff.on_failure("1.2.3.4", later), Duration::ZERO);
+
+        backoff.on_success("1.2.3.4");
+        assert!(backoff.remaining("1.2.3.4", later).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+//! Prometheus metrics of the controlled side.
+//!
+//! Served as plain text on `http://127.0.0.1:<metrics-port>/metrics` by the server process
+//! when the `metrics-port` option is set, it is read once at startup. Only localhost is bound,
+//! a scraper on another machine has to go through an exporter or a tunnel.
+
+use super::{connection::AUTHED_CONNS, video_service::VIDEO_QOS};
+use hbb_common::{config::Config, log};
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+const OPTION_METRICS_PORT: &str = "metrics-port";
+
+static SENT_BYTES: AtomicU64 = AtomicU64::new(0);
+static AUTH_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+pub fn add_sent_bytes(n: usize) {
+    SENT_BYTES.fetch_add(n as _, Ordering::Relaxed);
+}
+
+pub fn on_auth_failure() {
+    AUTH_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn start() {
+    let port = Config::get_option(OPTION_METRICS_PORT);
+    if port.is_empty() {
+        return;
+    }
+    let Ok(port) = port.parse::<u16>() else {
+        log::error!("Invalid {}: {}", OPTION_METRICS_PORT, port);
+        return;
+    };
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            log::error!("Failed to listen for metrics on port {}: {}", port, err);
+            return;
+        }
+    };
+    log::info!("Serving metrics on 127.0.0.1:{}", port);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(err) = respond(stream) {
+                log::debug!("Failed to serve metrics: {}", err);
+            }
+        }
+    });
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(3)))?;
+    // Every path gets the metrics, the request only has to be read far enough to answer it.
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf)?;
+    let body = Snapshot::take().render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[derive(Debug, Default)]
+struct Snapshot {
+    sessions: usize,
+    sent_bytes: u64,
+    fps: f32,
+    bitrate: u32,
+    qos_ratio: f32,
+    auth_failures: u64,
+}
+
+impl Snapshot {
+    fn take() -> Self {
+        let mut qos = VIDEO_QOS.lock().unwrap();
+        Self {
+            sessions: AUTHED_CONNS.lock().unwrap().len(),
+            sent_bytes: SENT_BYTES.load(Ordering::Relaxed),
+            fps: qos.measured_fps(),
+            bitrate: qos.bitrate(),
+            qos_ratio: qos.ratio(),
+            auth_failures: AUTH_FAILURES.load(Ordering::Relaxed),
+        }
+    }
+
+    fn render(&self) -> String {
+        let metrics: [(&str, &str, &str, String); 6] = [
+            (
+                "rustdesk_sessions_active",
+                "gauge",
+                "Authorized connections.",
+                self.sessions.to_string(),
+            ),
+            (
+                "rustdesk_sent_bytes_total",
+                "counter",
+                "Bytes of messages sent to controllers.",
+                self.sent_bytes.to_string(),
+            ),
+            (
+                "rustdesk_video_fps",
+                "gauge",
+                "Frames encoded per second over all displays.",
+                self.fps.to_string(),
+            ),
+            (
+                "rustdesk_video_bitrate_kbps",
+                "gauge",
+                "Target bitrate of the video.",
+                self.bitrate.to_string(),
+            ),
+            (
+                "rustdesk_video_qos_ratio",
+                "gauge",
+                "Bitrate ratio chosen by the video quality control.",
+                self.qos_ratio.to_string(),
+            ),
+            (
+                "rustdesk_auth_failures_total",
+                "counter",
+                "Failed password and 2FA attempts.",
+                self.auth_failures.to_string(),
+            ),
+        ];
+        metrics
+            .iter()
+            .map(|(name, kind, help, value)| {
+                format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        let text = Snapshot {
+            sessions: 2,
+            sent_bytes: 1024,
+            fps: 29.5,
+            qos_ratio: 0.5,
+            ..Default::default()
+        }
+        .render();
+        assert!(text.starts_with(
+            "# HELP rustdesk_sessions_active Authorized connections.\n\
+             # TYPE rustdesk_sessions_active gauge\n\
+             rustdesk_sessions_active 2\n"
+        ));
+        assert!(text.contains("\nrustdesk_sent_bytes_total 1024\n"));
+        assert!(text.contains("\nrustdesk_video_fps 29.5\n"));
+        assert!(text.contains("\nrustdesk_video_qos_ratio 0.5\n"));
+        assert_eq!(text.lines().count(), 18);
+    }
+}
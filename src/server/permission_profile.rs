@@ -0,0 +1,146 @@
+use hbb_common::{config::Config, log};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// JSON object keyed by peer id, e.g. `{"*": {"terminal": false}, "123456789": {"file": false}}`.
+const OPTION_PEER_PERMISSION_PROFILES: &str = "peer-permission-profiles";
+// The profile of every peer, the ones of peer ids can only take more away.
+const DEFAULT_PROFILE: &str = "*";
+
+/// Permissions of one peer on top of the global toggles.
+///
+/// A profile can only take permissions away, the global toggles and the `*` profile stay the
+/// upper bound. The peer id is reported by the peer itself and not verified, a peer claiming
+/// another id gets at most the `*` profile, so that is where a restriction for all goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionProfile {
+    pub keyboard: bool,
+    pub mouse: bool,
+    pub clipboard: bool,
+    pub file: bool,
+    pub audio: bool,
+    pub restart: bool,
+    pub terminal: bool,
+}
+
+impl Default for PermissionProfile {
+    fn default() -> Self {
+        Self {
+            keyboard: true,
+            mouse: true,
+            clipboard: true,
+            file: true,
+            audio: true,
+            restart: true,
+            terminal: true,
+        }
+    }
+}
+
+impl PermissionProfile {
+    pub fn load(peer_id: &str) -> Self {
+        Self::from_option(
+            &Config::get_option(OPTION_PEER_PERMISSION_PROFILES),
+            peer_id,
+        )
+    }
+
+    fn from_option(value: &str, peer_id: &str) -> Self {
+        if value.is_empty() {
+            return Self::default();
+        }
+        match serde_json::from_str::<HashMap<String, PermissionProfile>>(value) {
+            Ok(mut profiles) => {
+                let default = profiles.remove(DEFAULT_PROFILE).unwrap_or_default();
+                match profiles.remove(peer_id) {
+                    Some(profile) => profile.intersect(&default),
+                    None => default,
+                }
+            }
+            Err(err) => {
+                // Fail closed, a broken profile list must not grant what it was meant to deny.
+                log::error!("Invalid {}: {}", OPTION_PEER_PERMISSION_PROFILES, err);
+                Self::locked_down()
+            }
+        }
+    }
+
+    /// Whether the permission the connection manager switches as `name` may be enabled.
+    pub fn allows(&self, name: &str) -> bool {
+        match name {
+            "keyboard" => self.keyboard,
+            "clipboard" => self.clipboard,
+            "file" => self.file,
+            "audio" => self.audio,
+            "restart" => self.restart,
+            _ => true,
+        }
+    }
+
+    fn intersect(&self, other: &Self) -> Self {
+        Self {
+            keyboard: self.keyboard && other.keyboard,
+            mouse: self.mouse && other.mouse,
+            clipboard: self.clipboard && other.clipboard,
+            file: self.file && other.file,
+            audio: self.audio && other.audio,
+            restart: self.restart && other.restart,
+            terminal: self.terminal && other.terminal,
+        }
+    }
+
+    fn locked_down() -> Self {
+        Self {
+            keyboard: false,
+            mouse: false,
+            clipboard: false,
+            file: false,
+            audio: false,
+            restart: false,
+            terminal: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_from_option() {
+        assert_eq!(
+            PermissionProfile::from_option("", "1"),
+            PermissionProfile::default()
+        );
+        let value = r#"{"1": {"keyboard": false, "file": false}, "2": {}}"#;
+        let profile = PermissionProfile::from_option(value, "1");
+        assert!(!profile.keyboard && !profile.file);
+        assert!(profile.mouse && profile.clipboard && profile.terminal);
+        assert!(!profile.allows("file") && profile.allows("audio"));
+        assert!(profile.allows("recording"));
+        assert_eq!(
+            PermissionProfile::from_option(value, "2"),
+            PermissionProfile::default()
+        );
+        assert_eq!(
+            PermissionProfile::from_option(value, "3"),
+            PermissionProfile::default()
+        );
+        assert_eq!(
+            PermissionProfile::from_option("{", "1"),
+            PermissionProfile::locked_down()
+        );
+    }
+
+    #[test]
+    fn test_profile_bounded_by_default() {
+        let value = r#"{"*": {"terminal": false}, "1": {"file": false}, "2": {"terminal": true}}"#;
+        let profile = PermissionProfile::from_option(value, "1");
+        assert!(!profile.file && !profile.terminal && profile.keyboard);
+        // A profile can not grant what the default takes away, nor can an unknown id.
+        assert!(!PermissionProfile::from_option(value, "2").terminal);
+        let profile = PermissionProfile::from_option(value, "3");
+        assert!(!profile.terminal && profile.file);
+    }
+}
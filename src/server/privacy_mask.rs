@@ -0,0 +1,159 @@
+use hbb_common::{config::Config, log};
+use scrap::{EncodeYuvFormat, Pixfmt};
+use serde_derive::Deserialize;
+
+// JSON list of regions in pixels of the captured display,
+// e.g. `[{"display": 0, "x": 0, "y": 0, "w": 640, "h": 480}]`.
+pub const OPTION_PRIVACY_MASKS: &str = "privacy-masks";
+// Black in the limited range the frames are converted to.
+const BLACK_Y: u8 = 16;
+const BLACK_UV: u8 = 128;
+
+/// A region of a display that is blacked out before encoding, so it never leaves this machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct MaskRect {
+    pub display: usize,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// The masks of `display`.
+pub fn load(display: usize) -> Vec<MaskRect> {
+    from_option(&Config::get_option(OPTION_PRIVACY_MASKS), display)
+}
+
+fn from_option(value: &str, display: usize) -> Vec<MaskRect> {
+    if value.is_empty() {
+        return vec![];
+    }
+    match serde_json::from_str::<Vec<MaskRect>>(value) {
+        Ok(masks) => masks.into_iter().filter(|m| m.display == display).collect(),
+        Err(err) => {
+            // Fail closed, a broken list must not show what it was meant to hide.
+            log::error!("Invalid {}: {}", OPTION_PRIVACY_MASKS, err);
+            vec![MaskRect {
+                display,
+                x: 0,
+                y: 0,
+                w: i32::MAX,
+                h: i32::MAX,
+            }]
+        }
+    }
+}
+
+/// Blacks out `masks` in a frame converted to `fmt`.
+pub fn mask_yuv(yuv: &mut [u8], fmt: &EncodeYuvFormat, masks: &[MaskRect]) {
+    for m in masks {
+        let (x0, x1) = clip(m.x, m.w, fmt.w);
+        let (y0, y1) = clip(m.y, m.h, fmt.h);
+        if x0 >= x1 || y0 >= y1 {
+            continue;
+        }
+        fill(yuv, 0, fmt.stride[0], (x0, x1), (y0, y1), BLACK_Y);
+        // Chroma is subsampled, round outwards so no colour of the region is left.
+        let (cx, cy) = ((x0 / 2, x1.div_ceil(2)), (y0 / 2, y1.div_ceil(2)));
+        match fmt.pixfmt {
+            Pixfmt::I420 => {
+                fill(yuv, fmt.u, fmt.stride[1], cx, cy, BLACK_UV);
+                fill(yuv, fmt.v, fmt.stride[2], cx, cy, BLACK_UV);
+            }
+            Pixfmt::NV12 => {
+                // U and V are interleaved.
+                let uv = (cx.0 * 2, cx.1 * 2);
+                fill(yuv, fmt.u, fmt.stride[1], uv, cy, BLACK_UV);
+            }
+            Pixfmt::I444 => {
+                fill(yuv, fmt.u, fmt.stride[1], (x0, x1), (y0, y1), BLACK_UV);
+                fill(yuv, fmt.v, fmt.stride[2], (x0, x1), (y0, y1), BLACK_UV);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Blacks out `masks` in a tightly packed RGBA image, for screenshots.
+pub fn mask_rgba(rgba: &mut [u8], w: usize, h: usize, masks: &[MaskRect]) {
+    for m in masks {
+        let (x0, x1) = clip(m.x, m.w, w);
+        let (y0, y1) = clip(m.y, m.h, h);
+        for y in y0..y1 {
+            if let Some(line) = rgba.get_mut((y * w + x0) * 4..(y * w + x1) * 4) {
+                line.chunks_exact_mut(4)
+                    .for_each(|p| p.copy_from_slice(&[0, 0, 0, 255]));
+            }
+        }
+    }
+}
+
+fn clip(start: i32, len: i32, max: usize) -> (usize, usize) {
+    let end = (start as i64 + len as i64).clamp(0, max as i64) as usize;
+    (start.clamp(0, max as i32) as usize, end)
+}
+
+fn fill(
+    data: &mut [u8],
+    offset: usize,
+    stride: usize,
+    (x0, x1): (usize, usize),
+    (y0, y1): (usize, usize),
+    value: u8,
+) {
+    for y in y0..y1 {
+        let row = offset + y * stride;
+        if let Some(line) = data.get_mut(row + x0..row + x1) {
+            line.fill(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_frames() {
+        let masks = from_option(
+            r#"[{"display": 0, "x": -1, "y": 1, "w": 3, "h": 2},
+                {"display": 1, "x": 0, "y": 0, "w": 4, "h": 4}]"#,
+            0,
+        );
+        assert_eq!(masks.len(), 1);
+        let fmt = EncodeYuvFormat {
+            pixfmt: Pixfmt::I420,
+            w: 4,
+            h: 4,
+            stride: vec![4, 2, 2],
+            u: 16,
+            v: 20,
+        };
+        let mut yuv = vec![255; 24];
+        mask_yuv(&mut yuv, &fmt, &masks);
+        #[rustfmt::skip]
+        assert_eq!(
+            yuv,
+            vec![
+                255, 255, 255, 255,
+                16, 16, 255, 255,
+                16, 16, 255, 255,
+                255, 255, 255, 255,
+                128, 255,
+                128, 255,
+                128, 255,
+                128, 255,
+            ]
+        );
+        // A broken list hides the whole display.
+        let mut yuv = vec![255; 24];
+        mask_yuv(&mut yuv, &fmt, &from_option("[", 0));
+        assert!(yuv[..16].iter().all(|&b| b == BLACK_Y));
+        assert!(yuv[16..].iter().all(|&b| b == BLACK_UV));
+        assert!(from_option("", 0).is_empty());
+        let mut rgba = vec![255; 2 * 2 * 4];
+        mask_rgba(&mut rgba, 2, 2, &masks);
+        assert!(rgba[..8].iter().all(|&b| b == 255));
+        assert_eq!(&rgba[8..], &[0, 0, 0, 255, 0, 0, 0, 255]);
+    }
+}
@@ -242,6 +242,19 @@ impl<T: Subscriber + From<ConnInner>> ServiceTmpl<T> {
         conn_ids
     }
 
+    pub fn send_video_frame_without(&self, msg: Message, excluded: &HashSet<i32>) -> HashSet<i32> {
+        let mut conn_ids = HashSet::new();
+        let msg = Arc::new(msg);
+        let mut lock = self.0.write().unwrap();
+        for s in lock.subscribes.values_mut() {
+            if !excluded.contains(&s.id()) {
+                s.send(msg.clone());
+                conn_ids.insert(s.id());
+            }
+        }
+        conn_ids
+    }
+
     pub fn send_without(&self, msg: Message, sub: i32) {
         let mut lock = self.0.write().unwrap();
         let msg = Arc::new(msg);
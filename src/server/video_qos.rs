@@ -1,7 +1,7 @@
 use super::*;
 use scrap::codec::{Quality, BR_BALANCED, BR_BEST, BR_SPEED};
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     time::{Duration, Instant},
 };
 
@@ -25,6 +25,11 @@ adjust between FPS and ratio:
 
 delay:
     use delay minus RTT as the actual network delay
+
+network health:
+    classify the actual network delay plus jitter (variation between consecutive delays),
+    so that a stable link ranks better than an oscillating one with the same average;
+    the FPS adjustment uses the same jittered delay
 */
 
 // Constants - 极致优化支持240+ FPS
@@ -45,6 +50,155 @@ const ADJUST_RATIO_INTERVAL: usize = 2; // 优化质量调整间隔到2秒，更
 const DYNAMIC_SCREEN_THRESHOLD: usize = 3; // 提高动态屏幕阈值，支持更高FPS的场景
 const DELAY_THRESHOLD_150MS: u32 = 80; // 降低延迟阈值到80ms，支持更敏感的响应
 const HIGH_PERF_DELAY_THRESHOLD: u32 = 30; // 新增高性能延迟阈值，支持240+FPS极致体验
+const JITTER_SMOOTHING: f32 = 0.25; // Weight of the newest sample in the smoothed jitter
+
+// Advanced options overriding the QoS defaults, see QosTuning
+const OPTION_QOS_DELAY_THRESHOLDS: &str = "qos-delay-thresholds";
+const OPTION_QOS_RATIO_MULTIPLIERS: &str = "qos-ratio-multipliers";
+const OPTION_QOS_JITTER_WEIGHT: &str = "qos-jitter-weight";
+
+fn jittered_delay(delay: u32, jitter: u32, jitter_weight: f32) -> u32 {
+    delay + (jitter as f32 * jitter_weight) as u32
+}
+
+// Network condition classes, from best to worst
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NetworkHealth {
+    Excellent,
+    #[default]
+    Good,
+    Fair,
+    Poor,
+    Bad,
+    Critical,
+}
+
+impl NetworkHealth {
+    const ALL: [NetworkHealth; 6] = [
+        NetworkHealth::Excellent,
+        NetworkHealth::Good,
+        NetworkHealth::Fair,
+        NetworkHealth::Poor,
+        NetworkHealth::Bad,
+        NetworkHealth::Critical,
+    ];
+
+    // Classify by the actual network delay, penalized by jitter
+    fn new(delay: u32, jitter: u32, tuning: &QosTuning) -> Self {
+        let effective = jittered_delay(delay, jitter, tuning.jitter_weight);
+        tuning
+            .delay_thresholds
+            .iter()
+            .position(|threshold| effective < *threshold)
+            .map_or(NetworkHealth::Critical, |i| Self::ALL[i])
+    }
+
+    // Share of the file transfer rate left when it competes with the video for the link
+    pub fn file_transfer_share(&self) -> f32 {
+        match self {
+            NetworkHealth::Excellent | NetworkHealth::Good => 1.0,
+            NetworkHealth::Fair => 0.75,
+            NetworkHealth::Poor => 0.5,
+            NetworkHealth::Bad => 0.25,
+            NetworkHealth::Critical => 0.1,
+        }
+    }
+
+    // Opus bitrate (bps) and number of 10ms frames per audio packet, larger packets cost less overhead
+    pub fn audio_encoding(&self) -> (i32, usize) {
+        match self {
+            NetworkHealth::Excellent | NetworkHealth::Good => (128_000, 1),
+            NetworkHealth::Fair => (64_000, 2),
+            NetworkHealth::Poor => (32_000, 2),
+            NetworkHealth::Bad => (24_000, 4),
+            NetworkHealth::Critical => (16_000, 4),
+        }
+    }
+
+    // Multiplier applied to the bitrate ratio, increases only apply to dynamic screens
+    fn ratio_multiplier(&self, dynamic_screen: bool, tuning: &QosTuning) -> f32 {
+        let multiplier = tuning.ratio_multipliers[*self as usize];
+        if multiplier > 1.0 && !dynamic_screen {
+            1.0
+        } else {
+            multiplier
+        }
+    }
+}
+
+// QoS parameters that can be tuned for unusual links (satellite, intercontinental),
+// e.g. qos-delay-thresholds=150,300,450,900,1800 for a geostationary link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QosTuning {
+    delay_thresholds: [u32; 5], // Upper bounds (ms) of Excellent, Good, Fair, Poor and Bad
+    ratio_multipliers: [f32; 6], // Ratio multiplier for each NetworkHealth class
+    jitter_weight: f32,         // How much jitter counts towards the effective delay
+}
+
+impl Default for QosTuning {
+    fn default() -> Self {
+        QosTuning {
+            delay_thresholds: [50, 100, 200, 300, 500],
+            ratio_multipliers: [1.15, 1.1, 0.95, 0.9, 0.85, 0.8],
+            jitter_weight: 1.0,
+        }
+    }
+}
+
+impl QosTuning {
+    pub fn load() -> Self {
+        Self::from_options(
+            &Config::get_option(OPTION_QOS_DELAY_THRESHOLDS),
+            &Config::get_option(OPTION_QOS_RATIO_MULTIPLIERS),
+            &Config::get_option(OPTION_QOS_JITTER_WEIGHT),
+        )
+    }
+
+    // Invalid or empty values fall back to the defaults individually
+    fn from_options(delay_thresholds: &str, ratio_multipliers: &str, jitter_weight: &str) -> Self {
+        let mut tuning = Self::default();
+        if let Some(v) = Self::parse_list::<u32, 5>(delay_thresholds) {
+            if v[0] > 0 && v.windows(2).all(|w| w[0] < w[1]) {
+                tuning.delay_thresholds = v;
+            } else {
+                log::warn!("ignore invalid {OPTION_QOS_DELAY_THRESHOLDS}: {delay_thresholds}");
+            }
+        }
+        if let Some(v) = Self::parse_list::<f32, 6>(ratio_multipliers) {
+            if v.iter().all(|m| (0.5..=2.0).contains(m)) {
+                tuning.ratio_multipliers = v;
+            } else {
+                log::warn!("ignore invalid {OPTION_QOS_RATIO_MULTIPLIERS}: {ratio_multipliers}");
+            }
+        }
+        if let Ok(v) = jitter_weight.trim().parse::<f32>() {
+            if (0.0..=4.0).contains(&v) {
+                tuning.jitter_weight = v;
+            } else {
+                log::warn!("ignore invalid {OPTION_QOS_JITTER_WEIGHT}: {jitter_weight}");
+            }
+        }
+        tuning
+    }
+
+    fn parse_list<T: std::str::FromStr + Copy + Default, const N: usize>(
+        s: &str,
+    ) -> Option<[T; N]> {
+        if s.trim().is_empty() {
+            return None;
+        }
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<T>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if values.len() != N {
+            return None;
+        }
+        let mut arr = [T::default(); N];
+        arr.copy_from_slice(&values);
+        Some(arr)
+    }
+}
 
 #[derive(Default, Debug, Clone)]
 struct UserDelay {
@@ -54,6 +208,8 @@ struct UserDelay {
     rtt_calculator: RttCalculator,
     quick_increase_fps_count: usize,
     increase_fps_count: usize,
+    jitter: f32, // Smoothed absolute difference between consecutive delays
+    last_measured: Option<u32>,
 }
 
 impl UserDelay {
@@ -85,6 +241,27 @@ impl UserDelay {
             DELAY_THRESHOLD_150MS
         }
     }
+
+    // Lost probes are left out, they mark a stall rather than a varying delay
+    fn update_jitter(&mut self, delay: u32) {
+        if let Some(last) = self.last_measured.replace(delay) {
+            let diff = (delay as f32 - last as f32).abs();
+            self.jitter += (diff - self.jitter) * JITTER_SMOOTHING;
+        }
+    }
+
+    fn jitter(&self) -> u32 {
+        self.jitter.round() as u32
+    }
+
+    // An oscillating link is treated like a slower stable one
+    fn effective_delay(&self, jitter_weight: f32) -> u32 {
+        jittered_delay(self.avg_delay(), self.jitter(), jitter_weight)
+    }
+
+    fn network_health(&self, tuning: &QosTuning) -> NetworkHealth {
+        NetworkHealth::new(self.avg_delay(), self.jitter(), tuning)
+    }
 }
 
 // User session data structure
@@ -100,7 +277,11 @@ struct UserData {
 #[derive(Default, Debug, Clone)]
 struct DisplayData {
     send_counter: usize, // Number of times encode during period
+    measured_fps: f32,   // Frames encoded per second during the last period
     support_changing_quality: bool,
+    encoder_fps: Option<u32>, // Highest fps the encoder keeps up with, from the startup benchmark
+    temporal_layers: u32,     // Temporal layers of the encoder, 1 or 0 without SVC
+    user_temporal_layers: HashMap<i32, u32>, // Highest temporal layer each user receives now
 }
 
 // Main QoS controller structure
@@ -113,6 +294,7 @@ pub struct VideoQoS {
     adjust_ratio_instant: Instant,
     abr_config: bool,
     new_user_instant: Instant,
+    tuning: QosTuning,
 }
 
 impl Default for VideoQoS {
@@ -126,6 +308,7 @@ impl Default for VideoQoS {
             adjust_ratio_instant: Instant::now(),
             abr_config: true,
             new_user_instant: Instant::now(),
+            tuning: Default::default(),
         }
     }
 }
@@ -176,10 +359,84 @@ impl VideoQoS {
         }
     }
 
+    pub fn set_encoder_fps(&mut self, video_service_name: &str, fps: Option<u32>) {
+        if let Some(display) = self.displays.get_mut(video_service_name) {
+            display.encoder_fps = fps;
+        }
+    }
+
     // Check if variable bitrate encoding is supported and enabled
     pub fn in_vbr_state(&self) -> bool {
         self.abr_config && self.displays.iter().all(|e| e.1.support_changing_quality)
     }
+
+    pub fn set_temporal_layers(&mut self, video_service_name: &str, layers: u32) {
+        if let Some(display) = self.displays.get_mut(video_service_name) {
+            display.temporal_layers = layers;
+        }
+    }
+
+    // Temporal layers every display is encoded with, 1 if any display has no SVC
+    fn temporal_layers(&self) -> u32 {
+        self.displays
+            .values()
+            .map(|d| d.temporal_layers.max(1))
+            .min()
+            .unwrap_or(1)
+    }
+
+    // Highest temporal layer the user receives, because of their network condition
+    fn max_temporal_layer(&self, user: &UserData) -> u32 {
+        if user.delay.response_delayed {
+            0
+        } else {
+            match user.delay.network_health(&self.tuning) {
+                NetworkHealth::Bad | NetworkHealth::Critical => 0,
+                NetworkHealth::Poor => 1,
+                _ => u32::MAX,
+            }
+        }
+    }
+
+    // Users that skip a frame of `layer` of the display. What a user receives only changes on
+    // layer 0 where the pattern starts over, because a higher layer joined in the middle would
+    // reference a frame the user skipped. Until then a new user only receives layer 0.
+    pub fn users_skipping_temporal_layer(
+        &mut self,
+        video_service_name: &str,
+        layer: u32,
+    ) -> HashSet<i32> {
+        let Some(display) = self.displays.get(video_service_name) else {
+            return HashSet::new();
+        };
+        let mut user_layers = display.user_temporal_layers.clone();
+        if layer == 0 {
+            user_layers = self
+                .users
+                .iter()
+                .map(|(id, u)| (*id, self.max_temporal_layer(u)))
+                .collect();
+        }
+        let skipping = self
+            .users
+            .keys()
+            .filter(|id| layer > user_layers.get(id).copied().unwrap_or(0))
+            .copied()
+            .collect();
+        if let Some(display) = self.displays.get_mut(video_service_name) {
+            display.user_temporal_layers = user_layers;
+        }
+        skipping
+    }
+
+    // Network condition of the worst connected user
+    pub fn network_health(&self) -> NetworkHealth {
+        self.users
+            .values()
+            .map(|u| u.delay.network_health(&self.tuning))
+            .max()
+            .unwrap_or_default()
+    }
 }
 
 // User session management
@@ -188,12 +445,16 @@ impl VideoQoS {
     pub fn on_connection_open(&mut self, id: i32) {
         self.users.insert(id, UserData::default());
         self.abr_config = Config::get_option("enable-abr") != "N";
+        self.tuning = QosTuning::load();
         self.new_user_instant = Instant::now();
     }
 
     // Clean up user session
     pub fn on_connection_close(&mut self, id: i32) {
         self.users.remove(&id);
+        for display in self.displays.values_mut() {
+            display.user_temporal_layers.remove(&id);
+        }
         if self.users.is_empty() {
             *self = Default::default();
         }
@@ -261,12 +522,14 @@ impl VideoQoS {
         // Calculate minimum acceptable delay-fps product
         let dividend_ms = DELAY_THRESHOLD_150MS * min_fps;
 
+        let jitter_weight = self.tuning.jitter_weight;
         let mut adjust_ratio = false;
         if let Some(user) = self.users.get_mut(&id) {
             let delay = delay.max(10);
-            let old_avg_delay = user.delay.avg_delay();
+            let old_avg_delay = user.delay.effective_delay(jitter_weight);
             user.delay.add_delay(delay);
-            let mut avg_delay = user.delay.avg_delay();
+            user.delay.update_jitter(delay);
+            let mut avg_delay = user.delay.effective_delay(jitter_weight);
             avg_delay = avg_delay.max(10);
             let mut fps = self.fps;
 
@@ -357,6 +620,17 @@ impl VideoQoS {
         self.displays.remove(video_service_name);
     }
 
+    pub fn set_measured_fps(&mut self, video_service_name: &str, fps: f32) {
+        if let Some(display) = self.displays.get_mut(video_service_name) {
+            display.measured_fps = fps;
+        }
+    }
+
+    // Frames encoded per second over all displays, unlike `fps` which is the target.
+    pub fn measured_fps(&self) -> f32 {
+        self.displays.values().map(|d| d.measured_fps).sum()
+    }
+
     pub fn update_display_data(&mut self, video_service_name: &str, send_counter: usize) {
         if let Some(display) = self.displays.get_mut(video_service_name) {
             display.send_counter += send_counter;
@@ -398,6 +672,12 @@ impl VideoQoS {
             .filter(|u| *u >= MIN_FPS)
             .min()
             .unwrap_or(FPS);
+        // Frames the encoder can't keep up with would only queue up
+        let fps = self
+            .displays
+            .values()
+            .filter_map(|d| d.encoder_fps)
+            .fold(fps, u32::min);
 
         fps.clamp(MIN_FPS, MAX_FPS)
     }
@@ -419,11 +699,10 @@ impl VideoQoS {
         if !self.in_vbr_state() {
             return;
         }
-        // Get maximum delay from all users
-        let max_delay = self.users.iter().map(|u| u.1.delay.avg_delay()).max();
-        let Some(max_delay) = max_delay else {
+        if self.users.is_empty() {
             return;
-        };
+        }
+        let health = self.network_health();
 
         let target_quality = self.latest_quality();
         let target_ratio = self.latest_quality().ratio();
@@ -470,30 +749,8 @@ impl VideoQoS {
         };
         let max = target_ratio * MAX_BR_MULTIPLE;
 
-        let mut v = current_ratio;
-
-        // Adjust ratio based on network delay thresholds
-        if max_delay < 50 {
-            if dynamic_screen {
-                v = current_ratio * 1.15;
-            }
-        } else if max_delay < 100 {
-            if dynamic_screen {
-                v = current_ratio * 1.1;
-            }
-        } else if max_delay < DELAY_THRESHOLD_150MS {
-            if dynamic_screen {
-                v = current_ratio * 1.05;
-            }
-        } else if max_delay < 200 {
-            v = current_ratio * 0.95;
-        } else if max_delay < 300 {
-            v = current_ratio * 0.9;
-        } else if max_delay < 500 {
-            v = current_ratio * 0.85;
-        } else {
-            v = current_ratio * 0.8;
-        }
+        // Adjust ratio based on network health
+        let mut v = current_ratio * health.ratio_multiplier(dynamic_screen, &self.tuning);
 
         // Limit quality increase rate for better stability
         if let Some(ratio_add_150kbps) = ratio_add_150kbps {
@@ -512,20 +769,23 @@ impl VideoQoS {
     // Adjust fps based on network delay and user response time
     fn adjust_fps(&mut self) {
         let highest_fps = self.highest_fps();
-        // Get minimum fps from all users
+        let layers = self.temporal_layers();
+        // Get minimum fps from all users. A user that drops the upper temporal layers only
+        // receives every 2nd or 4th frame, so the others may be sent more.
         let mut fps = self
             .users
-            .iter()
-            .map(|u| u.1.delay.fps.unwrap_or(INIT_FPS))
+            .values()
+            .map(|u| {
+                let mut fps = u.delay.fps.unwrap_or(INIT_FPS);
+                if u.delay.response_delayed {
+                    fps = fps.min(MIN_FPS + 1);
+                }
+                let dropped_layers = (layers - 1).saturating_sub(self.max_temporal_layer(u));
+                fps << dropped_layers
+            })
             .min()
             .unwrap_or(INIT_FPS);
 
-        if self.users.iter().any(|u| u.1.delay.response_delayed) {
-            if fps > MIN_FPS + 1 {
-                fps = MIN_FPS + 1;
-            }
-        }
-
         // For new connections (within 1 second), cap fps to INIT_FPS to ensure stability
         if self.new_user_instant.elapsed().as_secs() < 1 {
             if fps > INIT_FPS {
@@ -595,3 +855,313 @@ impl RttCalculator {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONN_ID: i32 = 1;
+    const DISPLAY: &str = "display0";
+    // Encoder bitrate (kbps) at ratio 1.0, roughly a 1080p screen.
+    const BASE_BITRATE: f32 = 1500.0;
+    // A probe that never got answered, as seen by the TestDelay timer.
+    const LOST_PROBE_ELAPSED: u128 = 3000;
+
+    // One entry per TestDelay round, `None` means the probe was lost.
+    type Trace = Vec<Option<u32>>;
+
+    fn constant(delay: u32, len: usize) -> Trace {
+        vec![Some(delay); len]
+    }
+
+    fn step(before: u32, after: u32, at: usize, len: usize) -> Trace {
+        (0..len)
+            .map(|i| Some(if i < at { before } else { after }))
+            .collect()
+    }
+
+    fn square_wave(low: u32, high: u32, half_period: usize, len: usize) -> Trace {
+        (0..len)
+            .map(|i| {
+                Some(if (i / half_period) % 2 == 0 {
+                    low
+                } else {
+                    high
+                })
+            })
+            .collect()
+    }
+
+    fn with_loss(mut trace: Trace, every: usize) -> Trace {
+        trace
+            .iter_mut()
+            .skip(every - 1)
+            .step_by(every)
+            .for_each(|s| *s = None);
+        trace
+    }
+
+    // Drives a single-user, single-display VideoQoS the way the connection
+    // and video service loops do, one TestDelay round per trace entry.
+    struct Simulation {
+        qos: VideoQoS,
+        fps: Vec<u32>,
+        ratio: Vec<f32>,
+    }
+
+    impl Simulation {
+        fn new(quality: ImageQuality) -> Self {
+            let mut qos = VideoQoS::default();
+            qos.on_connection_open(CONN_ID);
+            qos.abr_config = true;
+            qos.new_display(DISPLAY.to_owned());
+            qos.set_support_changing_quality(DISPLAY, true);
+            qos.user_image_quality(CONN_ID, quality.value());
+            // Skip the INIT_FPS cap applied right after a connection opens.
+            qos.new_user_instant = Instant::now() - Duration::from_secs(10);
+            let ratio = qos.ratio();
+            qos.store_bitrate((BASE_BITRATE * ratio) as u32);
+            Simulation {
+                qos,
+                fps: vec![],
+                ratio: vec![],
+            }
+        }
+
+        fn run(&mut self, trace: &[Option<u32>]) {
+            for sample in trace {
+                match sample {
+                    Some(delay) => {
+                        self.qos.user_delay_response_elapsed(CONN_ID, 0);
+                        self.qos.user_network_delay(CONN_ID, *delay);
+                    }
+                    None => self
+                        .qos
+                        .user_delay_response_elapsed(CONN_ID, LOST_PROBE_ELAPSED),
+                }
+                // Every round covers a full ratio adjustment window of a busy screen.
+                self.qos.adjust_ratio_instant =
+                    Instant::now() - Duration::from_secs(ADJUST_RATIO_INTERVAL as u64);
+                let send_counter = self.qos.fps() as usize * ADJUST_RATIO_INTERVAL;
+                self.qos.update_display_data(DISPLAY, send_counter);
+                let ratio = self.qos.ratio();
+                self.qos.store_bitrate((BASE_BITRATE * ratio) as u32);
+                self.fps.push(self.qos.fps());
+                self.ratio.push(ratio);
+            }
+        }
+
+        fn fps_from(&self, from: usize) -> &[u32] {
+            &self.fps[from.min(self.fps.len())..]
+        }
+    }
+
+    // Number of times the series switches between rising and falling.
+    fn direction_changes<T: PartialOrd + Copy>(values: &[T]) -> usize {
+        let mut last = None;
+        let mut changes = 0;
+        for w in values.windows(2) {
+            let rising = if w[1] > w[0] {
+                true
+            } else if w[1] < w[0] {
+                false
+            } else {
+                continue;
+            };
+            if last.is_some_and(|l| l != rising) {
+                changes += 1;
+            }
+            last = Some(rising);
+        }
+        changes
+    }
+
+    // Index of the first sample after which `pred` holds for the rest of the series.
+    fn settled_at<T: Copy>(values: &[T], pred: impl Fn(T) -> bool) -> Option<usize> {
+        let idx = values.iter().rposition(|v| !pred(*v)).map_or(0, |i| i + 1);
+        if idx < values.len() {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_stable_link_keeps_highest_fps() {
+        let mut sim = Simulation::new(ImageQuality::Balanced);
+        sim.run(&constant(20, 60));
+        assert!(sim.fps.iter().all(|fps| *fps == FPS), "{:?}", sim.fps);
+        assert_eq!(direction_changes(&sim.ratio), 0, "{:?}", sim.ratio);
+        assert_eq!(*sim.ratio.last().unwrap(), BR_BALANCED);
+    }
+
+    #[test]
+    fn test_congestion_backs_off_quickly() {
+        let at = 20;
+        let mut sim = Simulation::new(ImageQuality::Balanced);
+        sim.run(&step(20, 400, at, 60));
+        let settled = settled_at(sim.fps_from(at), |fps| fps <= MIN_FPS + 1);
+        assert!(settled.is_some_and(|i| i <= 5), "{:?}", sim.fps);
+        // The ratio only goes down, and ends up at the Balanced floor.
+        assert_eq!(direction_changes(&sim.ratio[at..]), 0, "{:?}", sim.ratio);
+        assert!(*sim.ratio.last().unwrap() <= BR_BALANCED / 2.0 + 0.01);
+    }
+
+    #[test]
+    fn test_recovery_time_is_bounded() {
+        let at = 20;
+        let mut sim = Simulation::new(ImageQuality::Balanced);
+        sim.run(&step(400, 20, at, 100));
+        // One more low sample is allowed while the history still holds the old delays.
+        assert_eq!(direction_changes(sim.fps_from(at + 1)), 0, "{:?}", sim.fps);
+        let reached = settled_at(sim.fps_from(at), |fps| fps >= INIT_FPS);
+        assert!(reached.is_some_and(|i| i <= 30), "{:?}", sim.fps);
+        assert_eq!(*sim.fps.last().unwrap(), FPS);
+        let restored = settled_at(&sim.ratio[at..], |r| r >= BR_BALANCED);
+        assert!(restored.is_some_and(|i| i <= 10), "{:?}", sim.ratio);
+    }
+
+    #[test]
+    fn test_oscillating_link_converges() {
+        let mut sim = Simulation::new(ImageQuality::Balanced);
+        sim.run(&square_wave(20, 200, 2, 80));
+        let warmup = 20;
+        assert_eq!(direction_changes(sim.fps_from(warmup)), 0, "{:?}", sim.fps);
+        assert_eq!(
+            direction_changes(&sim.ratio[warmup..]),
+            0,
+            "{:?}",
+            sim.ratio
+        );
+        assert!(sim.fps_from(warmup).iter().all(|fps| *fps >= 10));
+    }
+
+    #[test]
+    fn test_lost_probe_recovers() {
+        let every = 10;
+        let mut sim = Simulation::new(ImageQuality::Balanced);
+        sim.run(&with_loss(constant(30, 60), every));
+        for lost in (every - 1..sim.fps.len()).step_by(every) {
+            assert!(sim.fps[lost] <= MIN_FPS + 1, "{:?}", sim.fps);
+            let next = &sim.fps[lost + 1..(lost + every).min(sim.fps.len())];
+            if next.len() == every - 1 {
+                assert!(*next.last().unwrap() > 10, "{:?}", sim.fps);
+            }
+        }
+    }
+
+    #[test]
+    fn test_jitter_degrades_network_health() {
+        let mut stable = Simulation::new(ImageQuality::Balanced);
+        stable.run(&constant(120, 30));
+        let mut oscillating = Simulation::new(ImageQuality::Balanced);
+        oscillating.run(&square_wave(20, 200, 1, 30));
+        assert_eq!(stable.qos.users[&CONN_ID].delay.jitter(), 0);
+        assert!(oscillating.qos.users[&CONN_ID].delay.jitter() > 50);
+        assert!(stable.qos.network_health() < oscillating.qos.network_health());
+    }
+
+    #[test]
+    fn test_temporal_layers_spare_fast_users() {
+        let mut qos = VideoQoS::default();
+        qos.new_display(DISPLAY.to_owned());
+        qos.set_temporal_layers(DISPLAY, 3);
+        qos.on_connection_open(1);
+        qos.on_connection_open(2);
+        qos.new_user_instant = Instant::now() - Duration::from_secs(2);
+        qos.users.get_mut(&1).unwrap().delay.fps = Some(60);
+        let slow = qos.users.get_mut(&2).unwrap();
+        slow.delay.add_delay(700);
+        slow.delay.fps = Some(10);
+        qos.adjust_fps();
+        assert_eq!(qos.fps(), 40);
+        qos.set_temporal_layers(DISPLAY, 1);
+        qos.adjust_fps();
+        assert_eq!(qos.fps(), 10);
+    }
+
+    #[test]
+    fn test_temporal_layer_switch_on_pattern_start() {
+        let mut qos = VideoQoS::default();
+        qos.new_display(DISPLAY.to_owned());
+        qos.set_temporal_layers(DISPLAY, 3);
+        qos.on_connection_open(1);
+        qos.users.get_mut(&1).unwrap().delay.response_delayed = true;
+        let skipped = |qos: &mut VideoQoS, layer| {
+            qos.users_skipping_temporal_layer(DISPLAY, layer)
+                .contains(&1)
+        };
+        // L1T3 is 0 2 1 2, the user recovers after the first frame of the pattern.
+        assert!(!skipped(&mut qos, 0));
+        assert!(skipped(&mut qos, 2));
+        qos.users.get_mut(&1).unwrap().delay.response_delayed = false;
+        assert!(skipped(&mut qos, 1));
+        assert!(skipped(&mut qos, 2));
+        assert!(!skipped(&mut qos, 0));
+        assert!(!skipped(&mut qos, 2));
+        // Going down waits for the pattern too.
+        qos.users.get_mut(&1).unwrap().delay.response_delayed = true;
+        assert!(!skipped(&mut qos, 1));
+        assert!(!skipped(&mut qos, 0));
+        assert!(skipped(&mut qos, 2));
+    }
+
+    #[test]
+    fn test_default_tuning_keeps_delay_steps() {
+        let tuning = QosTuning::default();
+        let multiplier =
+            |delay| NetworkHealth::new(delay, 0, &tuning).ratio_multiplier(true, &tuning);
+        assert_eq!(multiplier(40), 1.15);
+        assert_eq!(multiplier(90), 1.1);
+        assert_eq!(multiplier(120), 0.95);
+        assert_eq!(multiplier(250), 0.9);
+        assert_eq!(multiplier(450), 0.85);
+        assert_eq!(multiplier(550), 0.8);
+        assert_eq!(
+            NetworkHealth::new(40, 0, &tuning).ratio_multiplier(false, &tuning),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_encoder_fps_caps_fps() {
+        let mut sim = Simulation::new(ImageQuality::Balanced);
+        sim.qos.set_encoder_fps(DISPLAY, Some(20));
+        sim.run(&constant(20, 10));
+        assert!(sim.fps.iter().all(|fps| *fps <= 20), "{:?}", sim.fps);
+        sim.qos.set_encoder_fps(DISPLAY, None);
+        sim.run(&constant(20, 10));
+        assert!(sim.fps.last().is_some_and(|fps| *fps > 20), "{:?}", sim.fps);
+    }
+
+    #[test]
+    fn test_audio_follows_network_health() {
+        for pair in NetworkHealth::ALL.windows(2) {
+            let (better, worse) = (pair[0].audio_encoding(), pair[1].audio_encoding());
+            assert!(better.0 >= worse.0 && better.1 <= worse.1);
+        }
+        // Opus packets are 10, 20, 40 or 60ms
+        assert!(NetworkHealth::ALL
+            .iter()
+            .all(|h| [1, 2, 4, 6].contains(&h.audio_encoding().1)));
+    }
+
+    #[test]
+    fn test_qos_tuning_from_options() {
+        assert_eq!(QosTuning::from_options("", "", ""), QosTuning::default());
+        let tuning =
+            QosTuning::from_options("150, 300, 450, 900, 1800", "1.2,1.1,1,0.95,0.9,0.85", "0.5");
+        assert_eq!(tuning.delay_thresholds, [150, 300, 450, 900, 1800]);
+        assert_eq!(tuning.ratio_multipliers, [1.2, 1.1, 1.0, 0.95, 0.9, 0.85]);
+        assert_eq!(tuning.jitter_weight, 0.5);
+        assert_eq!(NetworkHealth::new(400, 0, &tuning), NetworkHealth::Fair);
+        // Not increasing, wrong length, out of range
+        let tuning = QosTuning::from_options("50,40,150,300,600", "1.2,1.1", "10");
+        assert_eq!(tuning, QosTuning::default());
+        assert_eq!(NetworkHealth::new(400, 0, &tuning), NetworkHealth::Bad);
+        assert_eq!(
+            NetworkHealth::new(40, 700, &tuning),
+            NetworkHealth::Critical
+        );
+    }
+}
@@ -19,7 +19,13 @@
 // to-do:
 // https://slhck.info/video/2017/03/01/rate-control.html
 
-use super::{display_service::check_display_changed, service::ServiceTmpl, video_qos::VideoQoS, *};
+use super::{
+    display_service::check_display_changed,
+    privacy_mask,
+    service::ServiceTmpl,
+    video_qos::{NetworkHealth, VideoQoS},
+    *,
+};
 #[cfg(target_os = "linux")]
 use crate::common::SimpleCallOnReturn;
 #[cfg(target_os = "linux")]
@@ -48,7 +54,7 @@ use scrap::Capturer;
 use scrap::{
     aom::AomEncoderConfig,
     codec::{Encoder, EncoderCfg},
-    record::{Recorder, RecorderContext},
+    record::{RecordLimits, Recorder, RecorderContext},
     vpxcodec::{VpxEncoderConfig, VpxVideoCodecId},
     CodecFormat, Display, EncodeInput, TraitCapturer, TraitPixelBuffer,
 };
@@ -68,6 +74,11 @@ const MIN_LATENCY_FPS: u32 = 60;          // 最小延迟帧率
 const MAX_FRAME_DROP_COUNT: usize = 5;    // 最大连续丢帧数
 
 pub const OPTION_REFRESH: &'static str = "refresh";
+const OPTION_AV1_TEMPORAL_LAYERS: &'static str = "av1-temporal-layers";
+// Encode the area around the cursor at a higher quality than the rest of the screen
+const OPTION_ROI_AROUND_CURSOR: &'static str = "roi-around-cursor";
+// Part of the display width and height taken by the area around the cursor
+const ROI_SHARE: usize = 4;
 
 type FrameFetchedNotifierSender = UnboundedSender<(i32, Option<Instant>)>;
 type FrameFetchedNotifierReceiver = Arc<TokioMutex<UnboundedReceiver<(i32, Option<Instant>)>>>;
@@ -85,6 +96,8 @@ lazy_static::lazy_static! {
     pub static ref IS_UAC_RUNNING: Arc<Mutex<bool>> = Default::default();
     pub static ref IS_FOREGROUND_WINDOW_ELEVATED: Arc<Mutex<bool>> = Default::default();
     static ref SCREENSHOTS: Mutex<HashMap<usize, Screenshot>> = Default::default();
+    // display_idx -> last time the video service was refreshed.
+    static ref LAST_REFRESH: Mutex<HashMap<usize, Instant>> = Default::default();
 }
 
 struct Screenshot {
@@ -630,7 +643,26 @@ fn run(vs: VideoService) -> ResultType<()> {
         .lock()
         .unwrap()
         .set_support_changing_quality(&sp.name(), encoder.support_changing_quality());
-    log::info!("initial quality: {quality:?}");
+    let encoder_fps = if encoder.is_hardware() {
+        None
+    } else {
+        scrap::codec::benchmark_fps(codec_format, c.width, c.height)
+    };
+    VIDEO_QOS
+        .lock()
+        .unwrap()
+        .set_encoder_fps(&sp.name(), encoder_fps);
+    let temporal_layers = match &encoder_cfg {
+        EncoderCfg::AOM(cfg) => cfg
+            .temporal_layers
+            .clamp(1, scrap::aom::MAX_TEMPORAL_LAYERS),
+        _ => 1,
+    };
+    VIDEO_QOS
+        .lock()
+        .unwrap()
+        .set_temporal_layers(&sp.name(), temporal_layers);
+    log::info!("initial quality: {quality:?}, encoder fps: {encoder_fps:?}");
 
     if sp.is_option_true(OPTION_REFRESH) {
         sp.set_option_bool(OPTION_REFRESH, false);
@@ -656,6 +688,13 @@ fn run(vs: VideoService) -> ResultType<()> {
     let repeat_encode_max = 10;
     let mut encode_fail_counter = 0;
     let mut first_frame = true;
+    let mut roi_around_cursor =
+        vs.source.is_monitor() && Config::get_option(OPTION_ROI_AROUND_CURSOR) == "Y";
+    let mut privacy_masks = if vs.source.is_monitor() {
+        privacy_mask::load(display_idx)
+    } else {
+        vec![]
+    };
     let capture_width = c.width;
     let capture_height = c.height;
     let (mut second_instant, mut send_counter) = (Instant::now(), 0);
@@ -672,7 +711,7 @@ fn run(vs: VideoService) -> ResultType<()> {
             &mut second_instant,
             &sp.name(),
         )?;
-        if sp.is_option_true(OPTION_REFRESH) {
+        if sp.is_option_true(OPTION_REFRESH) && refresh_allowed(display_idx) {
             if vs.source.is_monitor() {
                 let _ = try_broadcast_display_changed(&sp, display_idx, &c, true);
             }
@@ -716,6 +755,7 @@ fn run(vs: VideoService) -> ResultType<()> {
         let now = time::Instant::now();
         if vs.source.is_monitor() && last_check_displays.elapsed().as_millis() > 1000 {
             last_check_displays = now;
+            privacy_masks = privacy_mask::load(display_idx);
             // This check may be redundant, but it is better to be safe.
             // The previous check in `sp.is_option_true(OPTION_REFRESH)` block may be enough.
             try_broadcast_display_changed(&sp, display_idx, &c, false)?;
@@ -734,7 +774,15 @@ fn run(vs: VideoService) -> ResultType<()> {
                         let restore_vram = screenshot.restore_vram;
                         let (msg, w, h, data) = match &frame {
                             scrap::Frame::PixelBuffer(f) => match get_rgba_from_pixelbuf(f) {
-                                Ok(rgba) => ("".to_owned(), f.width(), f.height(), rgba),
+                                Ok(mut rgba) => {
+                                    privacy_mask::mask_rgba(
+                                        &mut rgba,
+                                        f.width(),
+                                        f.height(),
+                                        &privacy_masks,
+                                    );
+                                    ("".to_owned(), f.width(), f.height(), rgba)
+                                }
                                 Err(e) => {
                                     let serr = e.to_string();
                                     log::error!(
@@ -772,7 +820,25 @@ fn run(vs: VideoService) -> ResultType<()> {
                         }
                     }
 
+                    if roi_around_cursor {
+                        if let Err(e) = encoder.set_roi(cursor_roi(&c)) {
+                            log::error!("Failed to set the region of interest: {e:?}");
+                            roi_around_cursor = false;
+                        }
+                    }
                     let frame = frame.to(encoder.yuvfmt(), &mut yuv, &mut mid_data)?;
+                    let frame = if privacy_masks.is_empty() {
+                        frame
+                    } else {
+                        if !matches!(frame, EncodeInput::YUV(_)) {
+                            // Textures go to the encoder without a copy that could be masked.
+                            #[cfg(all(windows, feature = "vram"))]
+                            VRamEncoder::set_not_use(sp.name(), true);
+                            bail!("SWITCH");
+                        }
+                        privacy_mask::mask_yuv(&mut yuv, &encoder.yuvfmt(), &privacy_masks);
+                        EncodeInput::YUV(&yuv)
+                    };
                     let send_conn_ids = handle_one_frame(
                         display_idx,
                         &sp,
@@ -962,6 +1028,38 @@ fn setup_encoder(
     Ok((encoder, encoder_cfg, codec_format, use_i444, recorder))
 }
 
+// With temporal layers, users on a slow link only receive the lower layers,
+// instead of lowering the fps of everyone.
+fn av1_temporal_layers() -> u32 {
+    Config::get_option(OPTION_AV1_TEMPORAL_LAYERS)
+        .parse()
+        .unwrap_or(1)
+        .clamp(1, scrap::aom::MAX_TEMPORAL_LAYERS)
+}
+
+// Area around the cursor in the coordinates of the display, `None` if the cursor is elsewhere.
+fn cursor_roi(c: &CapturerInfo) -> Option<(usize, usize, usize, usize)> {
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    {
+        let (x, y) = crate::get_cursor_pos()?;
+        let x = usize::try_from(x - c.origin.0)
+            .ok()
+            .filter(|x| *x < c.width)?;
+        let y = usize::try_from(y - c.origin.1)
+            .ok()
+            .filter(|y| *y < c.height)?;
+        let (w, h) = (c.width / ROI_SHARE, c.height / ROI_SHARE);
+        let left = x.saturating_sub(w / 2).min(c.width - w);
+        let top = y.saturating_sub(h / 2).min(c.height - h);
+        Some((left, top, w, h))
+    }
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    {
+        let _ = c;
+        None
+    }
+}
+
 fn get_encoder_config(
     c: &CapturerInfo,
     _name: String,
@@ -1028,6 +1126,7 @@ fn get_encoder_config(
             height: c.height as _,
             quality,
             keyframe_interval,
+            temporal_layers: av1_temporal_layers(),
         }),
         _ => EncoderCfg::VPX(VpxEncoderConfig {
             width: c.width as _,
@@ -1065,6 +1164,7 @@ fn get_recorder(
             display_idx,
             camera,
             tx,
+            limits: RecordLimits::from_options(Config::get_option),
         })
         .map_or(Default::default(), |r| Arc::new(Mutex::new(Some(r))))
     } else {
@@ -1164,12 +1264,33 @@ fn handle_one_frame(
             vf.display = display as _;
             let mut msg = Message::new();
             msg.set_video_frame(vf);
-            recorder
+            let disk_full = recorder.lock().unwrap().as_mut().map_or(false, |r| {
+                let was_full = r.disk_full();
+                r.write_message(&msg, width, height);
+                !was_full && r.disk_full()
+            });
+            if disk_full {
+                // The controllers are not told otherwise, the recording just stops.
+                let mut msg_out = Message::new();
+                msg_out.set_message_box(MessageBox {
+                    msgtype: "custom-nook-nocancel-hasclose".to_owned(),
+                    title: "Session recording".to_owned(),
+                    text: "The remote side stopped recording the session, its disk is almost full."
+                        .to_owned(),
+                    link: "".to_owned(),
+                    ..Default::default()
+                });
+                sp.send(msg_out);
+            }
+            let skipping = VIDEO_QOS
                 .lock()
                 .unwrap()
-                .as_mut()
-                .map(|r| r.write_message(&msg, width, height));
-            send_conn_ids = sp.send_video_frame(msg);
+                .users_skipping_temporal_layer(&sp.name(), encoder.temporal_layer_id());
+            send_conn_ids = if skipping.is_empty() {
+                sp.send_video_frame(msg)
+            } else {
+                sp.send_video_frame_without(msg, &skipping)
+            };
         }
         Err(e) => {
             *encode_fail_counter += 1;
@@ -1205,6 +1326,30 @@ fn handle_one_frame(
     Ok(send_conn_ids)
 }
 
+// A refresh recreates the encoder and starts with a keyframe. When clients keep requesting
+// refreshes on a saturated link, the keyframes only add to the congestion, so refreshes
+// within the interval are deferred (the option stays set) until the interval has passed.
+fn refresh_min_interval(health: NetworkHealth) -> Duration {
+    match health {
+        NetworkHealth::Excellent | NetworkHealth::Good | NetworkHealth::Fair => Duration::ZERO,
+        NetworkHealth::Poor => Duration::from_secs(1),
+        NetworkHealth::Bad => Duration::from_secs(2),
+        NetworkHealth::Critical => Duration::from_secs(4),
+    }
+}
+
+fn refresh_allowed(display_idx: usize) -> bool {
+    let health = VIDEO_QOS.lock().unwrap().network_health();
+    let mut last_refresh = LAST_REFRESH.lock().unwrap();
+    if let Some(last) = last_refresh.get(&display_idx) {
+        if last.elapsed() < refresh_min_interval(health) {
+            return false;
+        }
+    }
+    last_refresh.insert(display_idx, Instant::now());
+    true
+}
+
 #[inline]
 pub fn refresh() {
     #[cfg(target_os = "android")]
@@ -1371,11 +1516,13 @@ fn check_qos(
             // 在高FPS模式下，更频繁地更新显示数据
             if elapsed > Duration::from_millis(500) {
                 *second_instant = Instant::now();
+                video_qos.set_measured_fps(&name, *send_counter as f32 / elapsed.as_secs_f32());
                 video_qos.update_display_data(&name, *send_counter);
                 *send_counter = 0;
             }
         } else if elapsed > Duration::from_secs(1) {
             *second_instant = Instant::now();
+            video_qos.set_measured_fps(&name, *send_counter as f32 / elapsed.as_secs_f32());
             video_qos.update_display_data(&name, *send_counter);
             *send_counter = 0;
         }
@@ -0,0 +1,80 @@
+//! Rate limit of file transfer streams, independent of the video.
+
+use std::time::{Duration, Instant};
+
+// KB/s, empty or "0" means no limit.
+pub const OPTION_FILE_TRANSFER_SPEED_LIMIT: &str = "file-transfer-speed-limit";
+// Unused budget carried over after an idle period.
+const MAX_BURST: Duration = Duration::from_secs(1);
+
+/// Bytes per second of the option value, 0 means no limit.
+pub fn parse_limit(value: &str) -> u64 {
+    value
+        .trim()
+        .parse::<u64>()
+        .unwrap_or(0)
+        .saturating_mul(1024)
+}
+
+/// Token bucket in front of `fs::handle_read_jobs`, which reads one block per job each time.
+pub struct TransferLimiter {
+    tokens: f64,
+    last: Instant,
+    last_transferred: u64,
+}
+
+impl Default for TransferLimiter {
+    fn default() -> Self {
+        Self {
+            tokens: 0.,
+            last: Instant::now(),
+            last_transferred: 0,
+        }
+    }
+}
+
+impl TransferLimiter {
+    /// Whether the next blocks may be read, `transferred` is the total of the running jobs.
+    pub fn ready(&mut self, transferred: u64, limit: u64) -> bool {
+        self.ready_at(transferred, limit, Instant::now())
+    }
+
+    fn ready_at(&mut self, transferred: u64, limit: u64, now: Instant) -> bool {
+        // The total drops when a job finishes.
+        let consumed = transferred.saturating_sub(self.last_transferred);
+        self.last_transferred = transferred;
+        let elapsed = now.saturating_duration_since(self.last);
+        self.last = now;
+        if limit == 0 {
+            self.tokens = 0.;
+            return true;
+        }
+        let rate = limit as f64;
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate - consumed as f64)
+            .min(rate * MAX_BURST.as_secs_f64());
+        self.tokens > 0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_limiter() {
+        assert_eq!(parse_limit(""), 0);
+        assert_eq!(parse_limit("100"), 100 * 1024);
+        let mut limiter = TransferLimiter::default();
+        let start = limiter.last;
+        let at = |ms| start + Duration::from_millis(ms);
+        assert!(limiter.ready_at(0, 1000, at(100)));
+        // 100 bytes of budget, a 500 byte block takes 400ms to pay off.
+        assert!(!limiter.ready_at(500, 1000, at(100)));
+        assert!(!limiter.ready_at(500, 1000, at(400)));
+        assert!(limiter.ready_at(500, 1000, at(600)));
+        // Idle time only builds up one second of budget.
+        assert!(limiter.ready_at(500, 1000, at(60_000)));
+        assert!(!limiter.ready_at(1600, 1000, at(60_000)));
+        assert!(limiter.ready_at(0, 0, at(60_000)));
+    }
+}
@@ -0,0 +1,137 @@
+//! UDP port forwarding.
+//!
+//! A UDP flow rides on the raw stream of a port-forward connection just like a TCP tunnel.
+//! The raw stream has no message boundaries, so every datagram is prefixed with its length
+//! as a big-endian u16.
+//!
+//! The controlling side asks for UDP by prefixing the remote host with `udp://`, which keeps
+//! the login request unchanged.
+
+use hbb_common::{
+    bytes::{Buf, BufMut, BytesMut},
+    tokio::net::{self, UdpSocket},
+    ResultType,
+};
+use std::io;
+
+pub const UDP_SCHEME: &str = "udp://";
+pub const MAX_DATAGRAM_SIZE: usize = u16::MAX as usize;
+const LEN_SIZE: usize = 2;
+
+pub fn strip_scheme(host: &str) -> Option<&str> {
+    host.strip_prefix(UDP_SCHEME)
+}
+
+pub fn encode(datagram: &[u8]) -> BytesMut {
+    let mut buf = BytesMut::with_capacity(LEN_SIZE + datagram.len());
+    buf.put_u16(datagram.len() as u16);
+    buf.put_slice(datagram);
+    buf
+}
+
+#[derive(Default)]
+pub struct Decoder {
+    buf: BytesMut,
+}
+
+impl Decoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn next_datagram(&mut self) -> Option<BytesMut> {
+        if self.buf.len() < LEN_SIZE {
+            return None;
+        }
+        let len = u16::from_be_bytes([self.buf[0], self.buf[1]]) as usize;
+        if self.buf.len() < LEN_SIZE + len {
+            return None;
+        }
+        self.buf.advance(LEN_SIZE);
+        Some(self.buf.split_to(len))
+    }
+}
+
+// An ICMP port unreachable from the target shows up as `ConnectionRefused` on a connected
+// socket. It only means nobody listens yet, so it must not tear down the tunnel.
+fn is_transient(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::ConnectionRefused
+}
+
+/// The controlled side of a UDP tunnel, a socket connected to the forwarded address.
+pub struct UdpForward {
+    socket: UdpSocket,
+    decoder: Decoder,
+    buf: Vec<u8>,
+}
+
+impl UdpForward {
+    pub async fn connect(addr: &str) -> ResultType<Self> {
+        let Some(target) = net::lookup_host(addr).await?.next() else {
+            hbb_common::bail!("Failed to resolve {}", addr);
+        };
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(target).await?;
+        Ok(Self {
+            socket,
+            decoder: Default::default(),
+            buf: vec![0; MAX_DATAGRAM_SIZE],
+        })
+    }
+
+    /// Receives the next datagram from the target, already framed for the stream.
+    pub async fn next(&mut self) -> Option<io::Result<BytesMut>> {
+        loop {
+            match self.socket.recv(&mut self.buf).await {
+                Ok(n) => return Some(Ok(encode(&self.buf[..n]))),
+                Err(err) if is_transient(&err) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    /// Sends every complete datagram received from the stream to the target.
+    pub async fn send(&mut self, bytes: BytesMut) -> io::Result<()> {
+        self.decoder.push(&bytes);
+        while let Some(datagram) = self.decoder.next_datagram() {
+            match self.socket.send(&datagram).await {
+                Err(err) if !is_transient(&err) => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_splits_datagrams() {
+        let mut stream = BytesMut::new();
+        stream.extend_from_slice(&encode(b"hello"));
+        stream.extend_from_slice(&encode(b""));
+        stream.extend_from_slice(&encode(b"world"));
+
+        let mut decoder = Decoder::default();
+        let mut datagrams = Vec::new();
+        // Feed the stream in odd sized chunks, the way a raw stream delivers it.
+        for chunk in stream.chunks(3) {
+            decoder.push(chunk);
+            while let Some(datagram) = decoder.next_datagram() {
+                datagrams.push(datagram.to_vec());
+            }
+        }
+        assert_eq!(
+            datagrams,
+            vec![b"hello".to_vec(), b"".to_vec(), b"world".to_vec()]
+        );
+        assert!(decoder.next_datagram().is_none());
+    }
+}
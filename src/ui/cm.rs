@@ -63,6 +63,10 @@ impl InvokeUiCM for SciterHandler {
         self.call("showElevation", &make_args!(show));
     }
 
+    fn show_connection(&self, id: i32) {
+        self.call("showConnection", &make_args!(id));
+    }
+
     fn update_voice_call_state(&self, client: &crate::ui_cm_interface::Client) {
         self.call(
             "updateVoiceCallState",
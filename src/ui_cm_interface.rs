@@ -107,6 +107,8 @@ pub trait InvokeUiCM: Send + Clone + 'static + Sized {
 
     fn show_elevation(&self, show: bool);
 
+    fn show_connection(&self, id: i32);
+
     fn update_voice_call_state(&self, client: &Client);
 
     fn file_transfer_log(&self, action: &str, log: &str);
@@ -310,6 +312,13 @@ pub fn switch_permission(id: i32, name: String, enabled: bool) {
     };
 }
 
+// The peer can't get control back until the connection is closed.
+#[inline]
+#[cfg(not(any(target_os = "ios")))]
+pub fn force_view_only(id: i32) {
+    switch_permission(id, "view_only".to_owned(), true);
+}
+
 #[inline]
 #[cfg(target_os = "android")]
 pub fn switch_permission_all(name: String, enabled: bool) {
@@ -439,6 +448,9 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                                 Data::ChatMessage { text } => {
                                     self.cm.new_message(self.conn_id, text);
                                 }
+                                Data::ShowConnection => {
+                                    self.cm.show_connection(self.conn_id);
+                                }
                                 Data::FS(mut fs) => {
                                     if let ipc::FS::WriteBlock { id, file_num, data: _, compressed } = fs {
                                         if let Ok(bytes) = self.stream.next_raw().await {
@@ -789,13 +801,25 @@ async fn handle_fs(
             total_size,
             conn_id,
         } => {
+            let path = if crate::file_drop::is_drop_path(&path) {
+                match crate::file_drop::resolve(&path, &files) {
+                    Some(path) => path,
+                    None => {
+                        log::warn!("Rejected file drop to {}", path);
+                        send_raw(fs::new_error(id, "Invalid drop target", 0), tx);
+                        return;
+                    }
+                }
+            } else {
+                PathBuf::from(&path)
+            };
             // cm has no show_hidden context
             // dummy remote, show_hidden, is_remote
             let mut job = fs::TransferJob::new_write(
                 id,
                 fs::JobType::Generic,
                 "".to_string(),
-                fs::DataSource::FilePath(PathBuf::from(&path)),
+                fs::DataSource::FilePath(path),
                 file_num,
                 false,
                 false,
@@ -37,6 +37,7 @@ use std::{
 };
 use uuid::Uuid;
 
+use crate::client::clipboard_history::ClipboardHistory;
 use crate::client::io_loop::Remote;
 use crate::client::{
     check_if_retry, handle_hash, handle_login_error, handle_login_from_ui, handle_test_delay,
@@ -67,6 +68,7 @@ pub struct Session<T: InvokeUiSession> {
     // Indicate whether the session is reconnected.
     // Used to auto start file transfer after reconnection.
     pub reconnect_count: Arc<AtomicUsize>,
+    pub clipboard_history: Arc<Mutex<ClipboardHistory>>,
 }
 
 #[derive(Clone)]
@@ -444,6 +446,14 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::RecordScreen(start));
     }
 
+    pub fn pause_record(&self, paused: bool) {
+        self.send(Data::RecordPause(paused));
+    }
+
+    pub fn save_replay(&self) {
+        self.send(Data::SaveReplay);
+    }
+
     pub fn is_screenshot_supported(&self) -> bool {
         crate::common::is_support_screenshot_num(self.lc.read().unwrap().version)
     }
@@ -572,6 +582,72 @@ impl<T: InvokeUiSession> Session<T> {
         )
     }
 
+    // Dropped files are uploaded to the drop folder of the remote side, as jobs of their own.
+    pub fn drop_files(&self, paths: Vec<String>) {
+        for path in paths {
+            let Some(name) = std::path::Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            let id = hbb_common::fs::get_next_job_id();
+            hbb_common::fs::update_next_job_id(id + 1);
+            self.send(Data::SendFiles((
+                id,
+                hbb_common::fs::JobType::Generic,
+                path,
+                crate::file_drop::target(&name),
+                0,
+                false,
+                false,
+            )));
+        }
+    }
+
+    pub fn get_clipboard_history(&self) -> String {
+        self.clipboard_history.lock().unwrap().to_json()
+    }
+
+    // Makes an entry of the history the clipboard of the remote side, ready to be pasted there.
+    pub fn paste_clipboard_history(&self, index: usize) {
+        let Some(clipboards) = self
+            .clipboard_history
+            .lock()
+            .unwrap()
+            .get(index)
+            .map(|e| e.clipboards.clone())
+        else {
+            return;
+        };
+        let multi_clipboards = MultiClipboards {
+            clipboards,
+            ..Default::default()
+        };
+        #[cfg(not(target_os = "ios"))]
+        {
+            let (version, platform) = self
+                .lc
+                .read()
+                .unwrap()
+                .peer_info
+                .as_ref()
+                .map(|pi| (pi.version.clone(), pi.platform.clone()))
+                .unwrap_or_default();
+            if let Some(msg_out) = crate::clipboard::get_msg_if_not_support_multi_clip(
+                &version,
+                &platform,
+                &multi_clipboards,
+            ) {
+                self.send(Data::Message(msg_out));
+                return;
+            }
+        }
+        let mut msg_out = Message::new();
+        msg_out.set_multi_clipboards(multi_clipboards);
+        self.send(Data::Message(msg_out));
+    }
+
     pub fn send_note(&self, note: String) {
         let url = self.get_audit_server("conn".to_string());
         let id = self.get_id();
@@ -753,11 +829,24 @@ impl<T: InvokeUiSession> Session<T> {
         }
     }
 
+    fn remap_key(&self, msg: &mut KeyEvent) -> bool {
+        let rules = crate::keyboard_remap::rules();
+        if rules.is_empty() {
+            return true;
+        }
+        let mut peer = self.peer_platform().to_lowercase();
+        peer.retain(|c| !c.is_whitespace());
+        rules.apply(msg, &peer)
+    }
+
     pub fn send_key_event(&self, evt: &KeyEvent) {
         // mode: legacy(0), map(1), translate(2), auto(3)
 
         let mut msg = evt.clone();
         self.swap_modifier_key(&mut msg);
+        if !self.remap_key(&mut msg) {
+            return;
+        }
         let mut msg_out = Message::new();
         msg_out.set_key_event(msg);
         self.send(Data::Message(msg_out));
@@ -1712,6 +1801,7 @@ pub trait InvokeUiSession: Send + Sync + Clone + 'static + Sized + Default {
     fn is_multi_ui_session(&self) -> bool;
     fn update_record_status(&self, start: bool);
     fn update_empty_dirs(&self, _res: ReadEmptyDirsResponse) {}
+    fn update_folder_sync(&self, _id: i32, _plan: String) {}
     fn printer_request(&self, id: i32, path: String);
     fn handle_screenshot_resp(&self, sid: String, msg: String);
     fn handle_terminal_response(&self, response: TerminalResponse);
@@ -1932,8 +2022,20 @@ pub async fn io_loop<T: InvokeUiSession>(handler: Session<T>, round: u32) {
             );
             log::info!("Remote rdp port: {}", port);
             start_one_port_forward(handler, 0, "".to_owned(), port, receiver, &key, &token).await;
-        } else if handler.args.len() == 0 {
-            let pfs = handler.lc.read().unwrap().port_forwards.clone();
+        } else {
+            // Tunnels come from the peer config, or from the command line as
+            // `listen-port remote-host remote-port` triples.
+            let pfs = if handler.args.is_empty() {
+                handler.lc.read().unwrap().port_forwards.clone()
+            } else {
+                match parse_port_forward_args(&handler.args) {
+                    Some(pfs) => pfs,
+                    None => {
+                        handler.on_error("Invalid arguments, usage:<br><br> rustdesk --port-forward remote-id listen-port remote-host remote-port [listen-port remote-host remote-port ...]");
+                        return;
+                    }
+                }
+            };
             let mut queues = HashMap::<i32, mpsc::UnboundedSender<Data>>::new();
             for d in pfs {
                 sender.send(Data::AddPortForward(d)).ok();
@@ -1941,7 +2043,7 @@ pub async fn io_loop<T: InvokeUiSession>(handler: Session<T>, round: u32) {
             loop {
                 match receiver.recv().await {
                     Some(Data::AddPortForward((port, remote_host, remote_port))) => {
-                        if port <= 0 || remote_port <= 0 {
+                        if port <= 0 || remote_port <= 0 || queues.contains_key(&port) {
                             continue;
                         }
                         let (sender, receiver) = mpsc::unbounded_channel::<Data>();
@@ -1978,26 +2080,6 @@ pub async fn io_loop<T: InvokeUiSession>(handler: Session<T>, round: u32) {
                     _ => {}
                 }
             }
-        } else {
-            let port = handler.args[0].parse::<i32>().unwrap_or(0);
-            if handler.args.len() != 3
-                || handler.args[2].parse::<i32>().unwrap_or(0) <= 0
-                || port <= 0
-            {
-                handler.on_error("Invalid arguments, usage:<br><br> rustdesk --port-forward remote-id listen-port remote-host remote-port");
-            }
-            let remote_host = handler.args[1].clone();
-            let remote_port = handler.args[2].parse::<i32>().unwrap_or(0);
-            start_one_port_forward(
-                handler,
-                port,
-                remote_host,
-                remote_port,
-                receiver,
-                &key,
-                &token,
-            )
-            .await;
         }
         return;
     }
@@ -2006,6 +2088,23 @@ pub async fn io_loop<T: InvokeUiSession>(handler: Session<T>, round: u32) {
     remote.sync_jobs_status_to_local().await;
 }
 
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn parse_port_forward_args(args: &[String]) -> Option<Vec<(i32, String, i32)>> {
+    if args.is_empty() || args.len() % 3 != 0 {
+        return None;
+    }
+    let mut pfs: Vec<(i32, String, i32)> = Vec::new();
+    for chunk in args.chunks(3) {
+        let port = chunk[0].parse::<i32>().unwrap_or(0);
+        let remote_port = chunk[2].parse::<i32>().unwrap_or(0);
+        if port <= 0 || remote_port <= 0 || pfs.iter().any(|x| x.0 == port) {
+            return None;
+        }
+        pfs.push((port, chunk[1].clone(), remote_port));
+    }
+    Some(pfs)
+}
+
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 async fn start_one_port_forward<T: InvokeUiSession>(
     handler: Session<T>,
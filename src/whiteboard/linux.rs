@@ -1,6 +1,6 @@
 use super::{
-    server::{Ripple, EVENT_PROXY},
-    win_linux::{create_font_face, draw_text},
+    server::{InkMark, Ripple, EVENT_PROXY},
+    win_linux::{create_font_face, draw_ink, draw_text},
     Cursor, CustomEvent,
 };
 use hbb_common::{bail, log, tokio::sync::mpsc::unbounded_channel, ResultType};
@@ -136,6 +136,7 @@ struct WindowState {
     // NOTE: This surface must be dropped before the `Window`.
     surface: Surface<DisplayHandle<'static>, Arc<Window>>,
     ripples: Vec<Ripple>,
+    ink_marks: Vec<InkMark>,
     last_cursors: HashMap<String, Cursor>,
 }
 
@@ -195,6 +196,12 @@ impl ApplicationHandler<(String, CustomEvent)> for WhiteboardApplication {
                     state.window.request_redraw();
                 }
             }
+            CustomEvent::Ink(ink) => {
+                if let Some(state) = self.windows.first_mut() {
+                    InkMark::add(&mut state.ink_marks, k, ink.x, ink.y, &ink);
+                    state.window.request_redraw();
+                }
+            }
             CustomEvent::Exit => {
                 self.close_requested = true;
             }
@@ -304,6 +311,7 @@ impl ApplicationHandler<(String, CustomEvent)> for WhiteboardApplication {
             window,
             surface,
             ripples: Vec::new(),
+            ink_marks: Vec::new(),
             last_cursors: HashMap::new(),
         };
 
@@ -402,6 +410,8 @@ impl WindowState {
             }
         }
 
+        draw_ink(&mut pixmap, &mut self.ink_marks);
+
         for cursor in self.last_cursors.values() {
             let (x, y) = (cursor.x, cursor.y);
             let size = 1.5f32;
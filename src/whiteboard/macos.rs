@@ -1,4 +1,7 @@
-use super::{server::EVENT_PROXY, Cursor, CustomEvent, Ripple};
+use super::{
+    server::{InkMark, EVENT_PROXY},
+    Cursor, CustomEvent, Ripple,
+};
 use core_graphics::context::CGContextRef;
 use foreign_types::ForeignTypeRef;
 use hbb_common::{bail, log, ResultType};
@@ -113,6 +116,7 @@ fn draw_cursors(
     windows: &Vec<WindowState>,
     window_id: WindowId,
     window_ripples: &mut HashMap<WindowId, Vec<Ripple>>,
+    window_ink_marks: &mut HashMap<WindowId, Vec<InkMark>>,
     last_cursors: &HashMap<String, CursorInfo>,
     map_cursor_text: &mut HashMap<(String, u32), CoreGraphicsTextLayout>,
 ) {
@@ -151,6 +155,29 @@ fn draw_cursors(
                                 }
                             }
 
+                            if let Some(marks) = window_ink_marks.get_mut(&window_id) {
+                                InkMark::retain_active(marks);
+                                for mark in marks.iter() {
+                                    let mut pb = BezPath::new();
+                                    for line in mark.polylines() {
+                                        for (i, &point) in line.iter().enumerate() {
+                                            if i == 0 {
+                                                pb.move_to(point);
+                                            }
+                                            // A single point still shows as a dot.
+                                            pb.line_to(point);
+                                        }
+                                    }
+                                    let rgba = super::argb_to_rgba(mark.argb);
+                                    let color = piet::Color::rgba8(rgba.0, rgba.1, rgba.2, 255)
+                                        .with_alpha(mark.get_alpha() * 0.8);
+                                    let style = piet::StrokeStyle::new()
+                                        .line_cap(piet::LineCap::Round)
+                                        .line_join(piet::LineJoin::Round);
+                                    context.stroke_styled(pb, &color, 3.0, &style);
+                                }
+                            }
+
                             for info in last_cursors.values() {
                                 if info.window_id != window.window.id() {
                                     continue;
@@ -232,6 +259,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
     };
 
     let mut window_ripples: HashMap<WindowId, Vec<Ripple>> = HashMap::new();
+    let mut window_ink_marks: HashMap<WindowId, Vec<InkMark>> = HashMap::new();
     let mut last_cursors: HashMap<String, CursorInfo> = HashMap::new();
     let mut map_cursor_text: HashMap<(String, u32), CoreGraphicsTextLayout> = HashMap::new();
 
@@ -257,6 +285,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                     &windows,
                     window_id,
                     &mut window_ripples,
+                    &mut window_ink_marks,
                     &last_cursors,
                     &mut map_cursor_text,
                 );
@@ -312,6 +341,25 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                         break;
                     }
                 }
+                CustomEvent::Ink(ink) => {
+                    for window in windows.iter() {
+                        let (x, y) = (
+                            ink.x as f64 - window.display_origin.0,
+                            ink.y as f64 - window.display_origin.1,
+                        );
+                        if x < 0.0
+                            || x > window.logical_size.width
+                            || y < 0.0
+                            || y > window.logical_size.height
+                        {
+                            continue;
+                        }
+                        let marks = window_ink_marks.entry(window.window.id()).or_default();
+                        InkMark::add(marks, k, x, y, &ink);
+                        window.window.request_redraw();
+                        break;
+                    }
+                }
                 CustomEvent::Exit => {
                     *control_flow = ControlFlow::Exit;
                 }
@@ -26,6 +26,7 @@ pub use server::*;
 #[serde(tag = "t", content = "c")]
 pub enum CustomEvent {
     Cursor(Cursor),
+    Ink(Ink),
     Clear,
     Exit,
 }
@@ -39,3 +40,23 @@ pub struct Cursor {
     pub btns: i32,
     pub text: String,
 }
+
+// A point of a mark, `start` begins a new mark.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "t")]
+pub struct Ink {
+    pub x: f32,
+    pub y: f32,
+    pub argb: u32,
+    pub start: bool,
+    #[serde(default)]
+    pub shape: InkShape,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InkShape {
+    #[default]
+    Free,
+    Arrow,
+    Circle,
+}
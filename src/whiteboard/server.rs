@@ -1,4 +1,4 @@
-use super::CustomEvent;
+use super::{CustomEvent, Ink, InkShape};
 use crate::ipc::{new_listener, Connection, Data};
 #[cfg(any(target_os = "windows", target_os = "macos"))]
 use hbb_common::tokio::sync::mpsc::unbounded_channel;
@@ -23,6 +23,12 @@ lazy_static! {
 }
 
 const RIPPLE_DURATION: Duration = Duration::from_millis(500);
+// Marks fade out after the last point, like the trail of a laser pointer.
+const INK_DURATION: Duration = Duration::from_secs(2);
+const ARROW_HEAD_LENGTH: RippleFloat = 18.0;
+// Angle between the shaft and either side of the arrow head, in radians.
+const ARROW_HEAD_ANGLE: RippleFloat = 0.5;
+const CIRCLE_SEGMENTS: usize = 48;
 #[cfg(target_os = "macos")]
 type RippleFloat = f64;
 #[cfg(any(target_os = "windows", target_os = "linux"))]
@@ -169,3 +175,86 @@ impl Ripple {
         (radius, alpha)
     }
 }
+
+pub(super) struct InkMark {
+    pub key: String,
+    pub argb: u32,
+    pub shape: InkShape,
+    pub points: Vec<(RippleFloat, RippleFloat)>,
+    pub last_time: Instant,
+}
+
+impl InkMark {
+    /// Begins a new mark of `key` on `start`, otherwise adds a point to its last mark.
+    ///
+    /// A button down carries no position, so a mark gets its first point from the move that
+    /// follows.
+    pub fn add(marks: &mut Vec<InkMark>, key: String, x: RippleFloat, y: RippleFloat, ink: &Ink) {
+        if !ink.start {
+            if let Some(mark) = marks.iter_mut().rev().find(|m| m.key == key) {
+                // Arrows and circles only keep where the drag began.
+                if mark.shape != InkShape::Free {
+                    mark.points.truncate(1);
+                }
+                mark.points.push((x, y));
+                mark.last_time = Instant::now();
+                return;
+            }
+        }
+        marks.push(InkMark {
+            key,
+            argb: ink.argb,
+            shape: ink.shape,
+            points: if ink.start { vec![] } else { vec![(x, y)] },
+            last_time: Instant::now(),
+        });
+    }
+
+    #[inline]
+    pub fn retain_active(marks: &mut Vec<InkMark>) {
+        marks.retain(|m| m.last_time.elapsed() < INK_DURATION);
+    }
+
+    /// The lines to stroke. An arrow points from the first to the last point, a circle is
+    /// centered on the first point and passes through the last one.
+    pub fn polylines(&self) -> Vec<Vec<(RippleFloat, RippleFloat)>> {
+        let (Some(&first), Some(&last)) = (self.points.first(), self.points.last()) else {
+            return vec![];
+        };
+        match self.shape {
+            InkShape::Free => vec![self.points.clone()],
+            InkShape::Arrow => {
+                let angle = (last.1 - first.1).atan2(last.0 - first.0);
+                let head = |delta: RippleFloat| {
+                    (
+                        last.0 - ARROW_HEAD_LENGTH * (angle + delta).cos(),
+                        last.1 - ARROW_HEAD_LENGTH * (angle + delta).sin(),
+                    )
+                };
+                vec![
+                    vec![first, last],
+                    vec![head(ARROW_HEAD_ANGLE), last, head(-ARROW_HEAD_ANGLE)],
+                ]
+            }
+            InkShape::Circle => {
+                let radius = (last.0 - first.0).hypot(last.1 - first.1);
+                let step = std::f64::consts::TAU as RippleFloat / CIRCLE_SEGMENTS as RippleFloat;
+                vec![(0..=CIRCLE_SEGMENTS)
+                    .map(|i| {
+                        let a = i as RippleFloat * step;
+                        (first.0 + radius * a.cos(), first.1 + radius * a.sin())
+                    })
+                    .collect()]
+            }
+        }
+    }
+
+    pub fn get_alpha(&self) -> RippleFloat {
+        let elapsed = self.last_time.elapsed();
+        #[cfg(target_os = "macos")]
+        let progress = (elapsed.as_secs_f64() / INK_DURATION.as_secs_f64()).min(1.0);
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        let progress = (elapsed.as_secs_f32() / INK_DURATION.as_secs_f32()).min(1.0);
+        1.0 - progress
+    }
+}
@@ -1,5 +1,8 @@
+use super::server::InkMark;
 use hbb_common::{bail, ResultType};
-use tiny_skia::{FillRule, Paint, PathBuilder, PixmapMut, Point, Rect, Transform};
+use tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, PathBuilder, PixmapMut, Point, Rect, Stroke, Transform,
+};
 use ttf_parser::Face;
 // A helper struct to bridge `ttf-parser` and `tiny-skia`.
 struct PathBuilderWrapper<'a> {
@@ -178,3 +181,35 @@ pub(super) fn create_font_face() -> ResultType<Face<'static>> {
     let face = Face::parse(font_data, face_index)?;
     Ok(face)
 }
+
+pub(super) fn draw_ink(pixmap: &mut PixmapMut, marks: &mut Vec<InkMark>) {
+    InkMark::retain_active(marks);
+    for mark in marks.iter() {
+        let mut pb = PathBuilder::new();
+        for line in mark.polylines() {
+            let Some(&(x, y)) = line.first() else {
+                continue;
+            };
+            pb.move_to(x, y);
+            // A single point still shows as a dot.
+            pb.line_to(x, y);
+            for &(x, y) in &line[1..] {
+                pb.line_to(x, y);
+            }
+        }
+        if let Some(path) = pb.finish() {
+            let rgba = super::argb_to_rgba(mark.argb);
+            let mut paint = Paint::default();
+            // Note: The real color is bgra here.
+            paint.set_color_rgba8(rgba.2, rgba.1, rgba.0, (mark.get_alpha() * 200.0) as u8);
+            paint.anti_alias = true;
+            let stroke = Stroke {
+                width: 4.0,
+                line_cap: LineCap::Round,
+                line_join: LineJoin::Round,
+                ..Default::default()
+            };
+            pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::{
-    server::{Ripple, EVENT_PROXY},
-    win_linux::{create_font_face, draw_text},
+    server::{InkMark, Ripple, EVENT_PROXY},
+    win_linux::{create_font_face, draw_ink, draw_text},
     Cursor, CustomEvent,
 };
 use hbb_common::{anyhow::anyhow, log, ResultType};
@@ -70,6 +70,7 @@ pub(super) fn create_event_loop() -> ResultType<()> {
     };
 
     let mut ripples: Vec<Ripple> = Vec::new();
+    let mut ink_marks: Vec<InkMark> = Vec::new();
     let mut last_cursors: HashMap<String, Cursor> = HashMap::new();
     let mut resized = final_size.is_none();
 
@@ -145,6 +146,8 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                     }
                 }
 
+                draw_ink(&mut pixmap, &mut ink_marks);
+
                 for cursor in last_cursors.values() {
                     let (x, y) = (cursor.x, cursor.y);
                     let size = 1.5f32;
@@ -219,6 +222,9 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                     }
                     last_cursors.insert(k, cursor);
                 }
+                CustomEvent::Ink(ink) => {
+                    InkMark::add(&mut ink_marks, k, ink.x, ink.y, &ink);
+                }
                 CustomEvent::Exit => {
                     *control_flow = ControlFlow::Exit;
                 }